@@ -25,6 +25,7 @@ fuzz_target!(|data: &[u8]| {
         source.as_ref(),
         &ParseOptions {
             normalize_emoji_shortcodes: true,
+            ..ParseOptions::default()
         },
     );
     let normalized_json = serialize_tree(&normalized_ast);
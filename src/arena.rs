@@ -0,0 +1,137 @@
+use crate::ast::Ast;
+use crate::parser::{ParseBuffers, ParseOptions, parse_source};
+use std::borrow::Cow;
+
+/// Parses many documents one after another, reusing the allocations of earlier parses.
+///
+/// A fresh `parse` allocates new token, node, extra-data and error vectors for every
+/// document. The arena keeps those vectors between documents: `recycle` an AST once it is
+/// no longer needed and the next `parse` fills its vectors again. ASTs borrow their
+/// source, as with `parse_borrowed`.
+pub struct ParserArena {
+    options: ParseOptions,
+    buffers: ParseBuffers,
+}
+
+impl ParserArena {
+    pub fn new() -> ParserArena {
+        ParserArena::with_options(&ParseOptions::default())
+    }
+
+    pub fn with_options(options: &ParseOptions) -> ParserArena {
+        ParserArena {
+            options: options.clone(),
+            buffers: ParseBuffers::default(),
+        }
+    }
+
+    /// Parse `source`, reusing the vectors of the ASTs recycled so far.
+    pub fn parse<'src>(&mut self, source: &'src str) -> Ast<'src> {
+        parse_source(Cow::Borrowed(source), &self.options, &mut self.buffers)
+    }
+
+    /// Hand back an AST that is no longer needed, so the next `parse` can reuse its
+    /// vectors.
+    pub fn recycle(&mut self, ast: Ast) {
+        self.buffers.recycle(ast);
+    }
+
+    /// Parse each message in turn and pass its AST to `f`, returning what `f` returns in
+    /// message order. Each AST is recycled as soon as `f` is done with it.
+    pub fn parse_many<S, T>(&mut self, messages: &[S], mut f: impl FnMut(&Ast) -> T) -> Vec<T>
+    where
+        S: AsRef<str>,
+    {
+        messages
+            .iter()
+            .map(|message| {
+                let ast = self.parse(message.as_ref());
+                let output = f(&ast);
+                self.recycle(ast);
+                output
+            })
+            .collect()
+    }
+}
+
+impl Default for ParserArena {
+    fn default() -> Self {
+        ParserArena::new()
+    }
+}
+
+/// Input each worker of a `ParserPool` is given at least. Starting a thread costs about
+/// as much as parsing a kilobyte or two, so smaller shares spend a noticeable part of
+/// the batch on start-up instead of parsing.
+const MIN_WORKER_BYTES: usize = 16 * 1024;
+
+/// A `ParserArena` per worker thread, for parsing large batches such as the message
+/// history loaded on startup. The arenas are kept between batches.
+///
+/// The pool pays off from about 32 KiB of input, a few hundred chat messages: each
+/// worker gets at least 16 KiB, and smaller batches are parsed on the calling thread,
+/// as fast as with a single `ParserArena`.
+pub struct ParserPool {
+    arenas: Vec<ParserArena>,
+    /// Threads the machine runs at once; more workers than that only add start-up cost.
+    parallelism: usize,
+}
+
+impl ParserPool {
+    /// A pool of `workers` threads; zero is treated as one.
+    pub fn new(workers: usize) -> ParserPool {
+        ParserPool::with_options(workers, &ParseOptions::default())
+    }
+
+    pub fn with_options(workers: usize, options: &ParseOptions) -> ParserPool {
+        ParserPool {
+            arenas: (0..workers.max(1))
+                .map(|_| ParserArena::with_options(options))
+                .collect(),
+            parallelism: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    /// Like `ParserArena::parse_many`, splitting `messages` into one contiguous run per
+    /// worker. The calling thread parses the first run and scoped threads the others;
+    /// only as many workers are used as the batch has 16 KiB of input for, and no more
+    /// than the machine runs at once.
+    pub fn parse_many<S, T>(&mut self, messages: &[S], f: impl Fn(&Ast) -> T + Sync) -> Vec<T>
+    where
+        S: AsRef<str> + Sync,
+        T: Send,
+    {
+        let bytes: usize = messages.iter().map(|message| message.as_ref().len()).sum();
+        let workers = self
+            .arenas
+            .len()
+            .min(self.parallelism)
+            .min(bytes / MIN_WORKER_BYTES)
+            .max(1);
+        let run = messages.len().div_ceil(workers).max(1);
+        if run >= messages.len() {
+            return self.arenas[0].parse_many(messages, &f);
+        }
+
+        let f = &f;
+        let (first_arena, arenas) = self.arenas.split_first_mut().expect("a pool has an arena");
+        let mut runs = messages.chunks(run);
+        let first_run = runs.next().unwrap_or_default();
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = arenas
+                .iter_mut()
+                .zip(runs)
+                .map(|(arena, run)| scope.spawn(move || arena.parse_many(run, f)))
+                .collect();
+            let mut outputs = first_arena.parse_many(first_run, f);
+            for worker in workers {
+                outputs.extend(
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic)),
+                );
+            }
+            outputs
+        })
+    }
+}
@@ -1,19 +1,36 @@
 use crate::token::Tag as TokenTag;
+use std::borrow::Cow;
+use std::collections::HashMap;
 
 pub type TokenIndex = u32;
 pub type NodeIndex = u32;
 pub type ByteOffset = u32;
 pub const AST_SCHEMA_NAME: &str = "hypernote-mdx-ast";
-pub const AST_SCHEMA_VERSION: u32 = 1;
+pub const AST_SCHEMA_VERSION: u32 = 2;
 
 /// Abstract Syntax Tree for MDX documents.
-pub struct Ast {
-    pub source: String,
+///
+/// `'src` is the lifetime of the source text: `parse_borrowed` keeps borrowing the
+/// caller's input, while `parse` and `parse_owned` return an `Ast<'static>` that owns it.
+pub struct Ast<'src> {
+    pub source: Cow<'src, str>,
     pub token_tags: Vec<TokenTag>,
     pub token_starts: Vec<ByteOffset>,
     pub nodes: Vec<Node>,
+    /// The source each node covers, indexed like `nodes`: from the start of the construct
+    /// through its closing delimiter (`**`, `)`, `</Card>`, a closing fence), without the
+    /// line ending after it. The document covers the whole source.
+    pub node_spans: Vec<Span>,
     pub extra_data: Vec<u32>,
     pub errors: Vec<Error>,
+    /// Image URLs of the custom emoji used in the document, by shortcode (NIP-30).
+    pub custom_emoji: HashMap<String, String>,
+    /// Replacements made to the caller's input to produce `source`, in source order.
+    /// Empty unless `ParseOptions::normalize_emoji_shortcodes` rewrote a shortcode.
+    pub source_edits: Vec<SourceEdit>,
+    /// Nodes auto-closed because the input ended inside them, such as an unclosed `**` or
+    /// code fence at the end of a streamed document. Empty for a complete parse.
+    pub incomplete: Vec<NodeIndex>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -40,6 +57,8 @@ pub enum NodeTag {
     Table,
     TableRow,
     TableCell,
+    Definition,
+    FootnoteDefinition,
 
     // Markdown inline nodes
     Text,
@@ -50,6 +69,32 @@ pub enum NodeTag {
     Link,
     Image,
     HardBreak,
+    SoftBreak,
+    FootnoteReference,
+
+    // HTML comment, as a block or inline
+    HtmlComment,
+
+    // Math (with `ParseOptions::math`)
+    MathInline,
+    MathBlock,
+
+    // NIP-21 `nostr:` URI (with `ParseOptions::nostr`)
+    NostrReference,
+
+    // `#hashtag` and `@mention` (with `ParseOptions::hashtags` / `mentions`)
+    Hashtag,
+    Mention,
+
+    // NIP-30 custom emoji (with `ParseOptions::custom_emoji`)
+    CustomEmoji,
+
+    // Chat formatting (with `ParseOptions::chat_inlines`)
+    Spoiler,
+    Highlight,
+    Underline,
+    Superscript,
+    Subscript,
 
     // MDX expression nodes
     MdxTextExpression,
@@ -67,6 +112,9 @@ pub enum NodeTag {
 
     // Frontmatter
     Frontmatter,
+
+    // Error recovery: a top-level span skipped after a block failed to parse
+    Recovery,
 }
 
 impl NodeTag {
@@ -89,6 +137,7 @@ impl NodeTag {
             NodeTag::Link => "link",
             NodeTag::Image => "image",
             NodeTag::HardBreak => "hard_break",
+            NodeTag::SoftBreak => "soft_break",
             NodeTag::MdxTextExpression => "mdx_text_expression",
             NodeTag::MdxFlowExpression => "mdx_flow_expression",
             NodeTag::MdxJsxElement => "mdx_jsx_element",
@@ -100,7 +149,23 @@ impl NodeTag {
             NodeTag::Table => "table",
             NodeTag::TableRow => "table_row",
             NodeTag::TableCell => "table_cell",
+            NodeTag::Definition => "definition",
+            NodeTag::FootnoteDefinition => "footnote_definition",
+            NodeTag::FootnoteReference => "footnote_reference",
+            NodeTag::HtmlComment => "html_comment",
+            NodeTag::MathInline => "math_inline",
+            NodeTag::MathBlock => "math_block",
+            NodeTag::NostrReference => "nostr_reference",
+            NodeTag::Hashtag => "hashtag",
+            NodeTag::Mention => "mention",
+            NodeTag::CustomEmoji => "custom_emoji",
+            NodeTag::Spoiler => "spoiler",
+            NodeTag::Highlight => "highlight",
+            NodeTag::Underline => "underline",
+            NodeTag::Superscript => "superscript",
+            NodeTag::Subscript => "subscript",
             NodeTag::Frontmatter => "frontmatter",
+            NodeTag::Recovery => "recovery",
         }
     }
 }
@@ -137,6 +202,7 @@ pub enum ErrorTag {
     BlankLineRequired,
     MismatchedTags,
     UnexpectedToken,
+    NestingTooDeep,
 }
 
 impl ErrorTag {
@@ -151,6 +217,7 @@ impl ErrorTag {
             ErrorTag::BlankLineRequired => "blank_line_required",
             ErrorTag::MismatchedTags => "mismatched_tags",
             ErrorTag::UnexpectedToken => "unexpected_token",
+            ErrorTag::NestingTooDeep => "nesting_too_deep",
         }
     }
 
@@ -165,6 +232,7 @@ impl ErrorTag {
             ErrorTag::BlankLineRequired => "A blank line is required before this construct.",
             ErrorTag::MismatchedTags => "JSX closing tag does not match opening tag.",
             ErrorTag::UnexpectedToken => "Unexpected token in current parsing context.",
+            ErrorTag::NestingTooDeep => "JSX elements or expressions are nested too deeply.",
         }
     }
 }
@@ -213,13 +281,58 @@ pub struct Heading {
     pub level: u8,
     pub children_start: u32,
     pub children_end: u32,
+    /// The `#id` token of a trailing `{#id}` attribute, which is not among the children.
+    pub id_token: Option<TokenIndex>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Link {
     pub children_start: u32,
     pub children_end: u32,
+    /// The destination text for inline links, or the definition token for references.
     pub url_token: TokenIndex,
+    pub reference: LinkReference,
+}
+
+/// How a link or image names its destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkReference {
+    /// `[text](url "title")`
+    Inline,
+    /// `[text][label]`
+    Full,
+    /// `[label][]`
+    Collapsed,
+    /// `[label]`
+    Shortcut,
+    /// `<https://example.com>`
+    Autolink,
+    /// A bare URL or email address detected in text (GFM autolink literal).
+    Literal,
+}
+
+impl LinkReference {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LinkReference::Inline => "inline",
+            LinkReference::Full => "full",
+            LinkReference::Collapsed => "collapsed",
+            LinkReference::Shortcut => "shortcut",
+            LinkReference::Autolink => "autolink",
+            LinkReference::Literal => "literal",
+        }
+    }
+
+    fn from_u32(value: u32) -> Self {
+        match value {
+            1 => LinkReference::Full,
+            2 => LinkReference::Collapsed,
+            3 => LinkReference::Shortcut,
+            4 => LinkReference::Autolink,
+            5 => LinkReference::Literal,
+            _ => LinkReference::Inline,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -245,19 +358,56 @@ pub struct TableData {
     pub rows_start: u32,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Span {
     pub start: ByteOffset,
     pub end: ByteOffset,
 }
 
+/// One replacement of the caller's input, such as `:tada:` becoming `🎉`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceEdit {
+    /// The replaced range of the original input.
+    pub original: Span,
+    /// The replacement's range in `Ast::source`.
+    pub source: Span,
+}
+
+/// The result of `Ast::reparse_with_edit`: the AST of the edited source, and which of
+/// its nodes were parsed again.
+pub struct Reparse {
+    pub ast: Ast<'static>,
+    /// The nodes of the old AST that the edit replaced.
+    pub old_nodes: Range,
+    /// The nodes parsed from the edited region. Nodes before them keep their index; the
+    /// old nodes after `old_nodes` follow them unchanged, shifted by
+    /// `new_nodes.end - old_nodes.end`. The document node is always rebuilt.
+    pub new_nodes: Range,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SourcePosition {
     pub line: usize,
     pub column: usize,
 }
 
-impl Ast {
+impl Ast<'_> {
+    /// Detach the AST from the source it borrows, copying the source if needed.
+    pub fn into_owned(self) -> Ast<'static> {
+        Ast {
+            source: Cow::Owned(self.source.into_owned()),
+            token_tags: self.token_tags,
+            token_starts: self.token_starts,
+            nodes: self.nodes,
+            node_spans: self.node_spans,
+            extra_data: self.extra_data,
+            errors: self.errors,
+            custom_emoji: self.custom_emoji,
+            source_edits: self.source_edits,
+            incomplete: self.incomplete,
+        }
+    }
+
     fn node(&self, node_idx: NodeIndex) -> Option<&Node> {
         self.nodes.get(node_idx as usize)
     }
@@ -296,7 +446,13 @@ impl Ast {
             | NodeTag::Strong
             | NodeTag::Emphasis
             | NodeTag::Strikethrough
+            | NodeTag::Spoiler
+            | NodeTag::Highlight
+            | NodeTag::Underline
+            | NodeTag::Superscript
+            | NodeTag::Subscript
             | NodeTag::MdxJsxFragment
+            | NodeTag::FootnoteDefinition
             | NodeTag::TableRow
             | NodeTag::TableCell => {
                 if let NodeData::Children(range) = node.data {
@@ -344,33 +500,10 @@ impl Ast {
 
     /// Get the source text span for a node
     pub fn node_source(&self, node_index: NodeIndex) -> &str {
-        let Some(node) = self.node(node_index) else {
-            return "";
-        };
-        let start_token = node.main_token;
-        let end_token = {
-            let node_children = self.children(node_index);
-            if !node_children.is_empty() {
-                let last_child = node_children[node_children.len() - 1];
-                self.node(last_child)
-                    .map(|child| child.main_token.saturating_add(1))
-                    .unwrap_or_else(|| start_token.saturating_add(1))
-            } else {
-                start_token.saturating_add(1)
-            }
-        };
-
-        let Some(&start) = self.token_starts.get(start_token as usize) else {
-            return "";
-        };
-        let start = start as usize;
-        let end = if (end_token as usize) < self.token_starts.len() {
-            self.token_starts[end_token as usize] as usize
-        } else {
-            self.source.len()
-        };
-
-        self.source.get(start..end).unwrap_or("")
+        let span = self.node_span(node_index);
+        self.source
+            .get(span.start as usize..span.end as usize)
+            .unwrap_or("")
     }
 
     /// Extract extra data as Heading
@@ -380,6 +513,7 @@ impl Ast {
                 level: 0,
                 children_start: 0,
                 children_end: 0,
+                id_token: None,
             };
         };
         if node.tag != NodeTag::Heading {
@@ -387,6 +521,7 @@ impl Ast {
                 level: 0,
                 children_start: 0,
                 children_end: 0,
+                id_token: None,
             };
         }
         let idx = match node.data {
@@ -396,6 +531,7 @@ impl Ast {
                     level: 0,
                     children_start: 0,
                     children_end: 0,
+                    id_token: None,
                 };
             }
         };
@@ -403,6 +539,9 @@ impl Ast {
             level: self.extra_u32(idx).unwrap_or(0) as u8,
             children_start: self.extra_u32(idx.saturating_add(1)).unwrap_or(0),
             children_end: self.extra_u32(idx.saturating_add(2)).unwrap_or(0),
+            id_token: self
+                .extra_u32(idx.saturating_add(3))
+                .filter(|&token| token != u32::MAX),
         }
     }
 
@@ -493,6 +632,7 @@ impl Ast {
                 children_start: 0,
                 children_end: 0,
                 url_token: 0,
+                reference: LinkReference::Inline,
             };
         };
         if node.tag != NodeTag::Link && node.tag != NodeTag::Image {
@@ -500,6 +640,7 @@ impl Ast {
                 children_start: 0,
                 children_end: 0,
                 url_token: 0,
+                reference: LinkReference::Inline,
             };
         }
         let idx = match node.data {
@@ -509,6 +650,7 @@ impl Ast {
                     children_start: 0,
                     children_end: 0,
                     url_token: 0,
+                    reference: LinkReference::Inline,
                 };
             }
         };
@@ -516,6 +658,7 @@ impl Ast {
             children_start: self.extra_u32(idx).unwrap_or(0),
             children_end: self.extra_u32(idx.saturating_add(1)).unwrap_or(0),
             url_token: self.extra_u32(idx.saturating_add(2)).unwrap_or(0),
+            reference: LinkReference::from_u32(self.extra_u32(idx.saturating_add(3)).unwrap_or(0)),
         }
     }
 
@@ -570,32 +713,23 @@ impl Ast {
 
     /// Get the byte span for a node
     pub fn node_span(&self, node_index: NodeIndex) -> Span {
+        if let Some(&span) = self.node_spans.get(node_index as usize) {
+            return span;
+        }
+        // An AST assembled by hand may lack spans; fall back to the main token.
         let Some(node) = self.node(node_index) else {
             return Span { start: 0, end: 0 };
         };
-        let start = self
-            .token_starts
-            .get(node.main_token as usize)
-            .copied()
-            .unwrap_or(self.source.len() as ByteOffset);
-
-        let end = {
-            let node_children = self.children(node_index);
-            if !node_children.is_empty() {
-                let last_child = node_children[node_children.len() - 1];
-                let child_span = self.node_span(last_child);
-                child_span.end
-            } else {
-                let end_token = node.main_token.saturating_add(1);
-                if (end_token as usize) < self.token_starts.len() {
-                    self.token_starts[end_token as usize]
-                } else {
-                    self.source.len() as ByteOffset
-                }
-            }
+        let token_start = |token: usize| {
+            self.token_starts
+                .get(token)
+                .copied()
+                .unwrap_or(self.source.len() as ByteOffset)
         };
-
-        Span { start, end }
+        Span {
+            start: token_start(node.main_token as usize),
+            end: token_start(node.main_token as usize + 1),
+        }
     }
 
     /// Convert a byte offset into a one-based line and column.
@@ -629,43 +763,101 @@ impl Ast {
         self.line_col(self.node_span(node_index).start)
     }
 
-    /// Find the deepest node containing a byte offset
-    pub fn node_at_offset(&self, offset: ByteOffset) -> Option<NodeIndex> {
-        if self.nodes.is_empty() {
-            return None;
+    /// Translate a byte offset in `source` back to the caller's original input, undoing
+    /// `source_edits`. Offsets inside a replacement map to its start.
+    pub fn original_offset(&self, byte_offset: ByteOffset) -> ByteOffset {
+        let mut shifted = byte_offset as i64;
+        for edit in &self.source_edits {
+            if byte_offset < edit.source.end {
+                if byte_offset > edit.source.start {
+                    return edit.original.start;
+                }
+                break;
+            }
+            shifted = byte_offset as i64 + edit.original.end as i64 - edit.source.end as i64;
         }
+        shifted.max(0) as ByteOffset
+    }
 
-        // Find document node
-        let doc_idx = self.nodes.iter().enumerate().find_map(|(i, n)| {
-            if n.tag == NodeTag::Document {
-                Some(i as NodeIndex)
-            } else {
-                None
+    /// Get the byte span for a node in the caller's original input.
+    pub fn original_span(&self, node_index: NodeIndex) -> Span {
+        let span = self.node_span(node_index);
+        Span {
+            start: self.original_offset(span.start),
+            end: self.original_offset(span.end),
+        }
+    }
+
+    /// Like `line_col`, but counting columns in the caller's original input.
+    pub fn original_line_col(&self, byte_offset: ByteOffset) -> SourcePosition {
+        let mut position = self.line_col(byte_offset);
+        let line_start = self.source[..(byte_offset as usize).min(self.source.len())]
+            .rfind('\n')
+            .map_or(0, |i| i + 1) as ByteOffset;
+        for edit in &self.source_edits {
+            if edit.source.start < line_start {
+                continue;
             }
-        })?;
+            if edit.source.end > byte_offset {
+                break;
+            }
+            // Replacements never span lines, so only their width changes.
+            let replacement = self
+                .source
+                .get(edit.source.start as usize..edit.source.end as usize)
+                .map_or(0, |text| text.chars().count());
+            let original = (edit.original.end - edit.original.start) as usize;
+            position.column = position.column + original - replacement;
+        }
+        position
+    }
 
-        self.node_at_offset_recursive(doc_idx, offset)
+    /// Whether a node was auto-closed because the input ended inside it.
+    pub fn is_incomplete(&self, node_index: NodeIndex) -> bool {
+        self.incomplete.contains(&node_index)
     }
 
-    fn node_at_offset_recursive(
+    /// Replace the bytes in `edit` of the source with `new_text` and parse the result,
+    /// tokenizing and parsing again only the top-level blocks the edit can affect. The
+    /// AST is the same as `parse` of the edited source. An edit that adds or removes a
+    /// link or footnote definition parses the whole source again.
+    pub fn reparse_with_edit(&self, edit: Span, new_text: &str) -> Reparse {
+        self.reparse_with_edit_and_options(edit, new_text, &crate::parser::ParseOptions::default())
+    }
+
+    /// Like `reparse_with_edit`, for an AST parsed with `options`. With
+    /// `normalize_emoji_shortcodes`, the whole edited source is parsed again.
+    pub fn reparse_with_edit_and_options(
         &self,
-        node_index: NodeIndex,
-        offset: ByteOffset,
-    ) -> Option<NodeIndex> {
-        let span = self.node_span(node_index);
+        edit: Span,
+        new_text: &str,
+        options: &crate::parser::ParseOptions,
+    ) -> Reparse {
+        crate::parser::reparse_with_edit(self, edit, new_text, options)
+    }
 
+    /// Find the deepest node containing a byte offset
+    pub fn node_at_offset(&self, offset: ByteOffset) -> Option<NodeIndex> {
+        // The document node is last, unless the AST was assembled by hand.
+        let mut node_index = self
+            .nodes
+            .iter()
+            .rposition(|n| n.tag == NodeTag::Document)? as NodeIndex;
+        let span = self.node_span(node_index);
         if offset < span.start || offset >= span.end {
             return None;
         }
 
-        let node_children = self.children(node_index);
-        for &child_idx in node_children {
-            if let Some(found) = self.node_at_offset_recursive(child_idx, offset) {
-                return Some(found);
+        // Children are in source order and do not overlap, so at most one holds `offset`.
+        loop {
+            let node_children = self.children(node_index);
+            let candidate =
+                node_children.partition_point(|&child| self.node_span(child).end <= offset);
+            match node_children.get(candidate) {
+                Some(&child) if self.node_span(child).start <= offset => node_index = child,
+                _ => return Some(node_index),
             }
         }
-
-        Some(node_index)
     }
 
     /// Extract frontmatter info from extra_data (3 u32s: format, content_start, content_end)
@@ -780,7 +972,8 @@ impl Ast {
         crate::semantic::code_block_info(self, node_index)
     }
 
-    /// Extract typed semantic details for a link node.
+    /// Extract typed semantic details for a link node. Brackets with no destination and
+    /// no matching definition are not links; they stay text.
     pub fn link_view(&self, node_index: NodeIndex) -> Option<crate::semantic::LinkInfo<'_>> {
         crate::semantic::link_view(self, node_index)
     }
@@ -790,6 +983,134 @@ impl Ast {
         crate::semantic::image_view(self, node_index)
     }
 
+    /// Extract the TeX source and display mode of a math node.
+    pub fn math_info(&self, node_index: NodeIndex) -> Option<crate::semantic::MathInfo<'_>> {
+        crate::semantic::math_info(self, node_index)
+    }
+
+    /// Decode the NIP-19 entity of a `nostr:` URI node.
+    pub fn nostr_reference_view(
+        &self,
+        node_index: NodeIndex,
+    ) -> Option<crate::semantic::NostrReferenceView<'_>> {
+        crate::semantic::nostr_reference_view(self, node_index)
+    }
+
+    /// The name of a `#hashtag` node, without the `#`.
+    pub fn hashtag_name(&self, node_index: NodeIndex) -> Option<&str> {
+        crate::semantic::hashtag_name(self, node_index)
+    }
+
+    /// The name of an `@mention` node, without the `@`.
+    pub fn mention_name(&self, node_index: NodeIndex) -> Option<&str> {
+        crate::semantic::mention_name(self, node_index)
+    }
+
+    /// All hashtags in the document, lowercased and deduplicated in order of first
+    /// appearance, e.g. for the `t` tags of a Nostr event.
+    pub fn collect_hashtags(&self) -> Vec<String> {
+        crate::semantic::collect_hashtags(self)
+    }
+
+    /// Extract the shortcode and image URL of a custom emoji node.
+    pub fn custom_emoji_view(
+        &self,
+        node_index: NodeIndex,
+    ) -> Option<crate::semantic::CustomEmojiView<'_>> {
+        crate::semantic::custom_emoji_view(self, node_index)
+    }
+
+    /// Ids and slugs of every heading, for looking up many headings or fragments in one
+    /// pass without slugging the document for each.
+    pub fn heading_table(&self) -> crate::semantic::HeadingTable<'_> {
+        crate::semantic::heading_table(self)
+    }
+
+    /// Slugs every heading in the document on each call, so calling it per heading is
+    /// quadratic; use [`Ast::heading_table`] for many lookups. Extracts the level,
+    /// children, custom id and slug of a heading.
+    pub fn heading_view(&self, node_index: NodeIndex) -> Option<crate::semantic::HeadingView<'_>> {
+        crate::semantic::heading_view(self, node_index)
+    }
+
+    /// The heading a same-document link such as `#setup` points to, by custom `{#id}`
+    /// or slug.
+    pub fn resolve_fragment(&self, href: &str) -> Option<NodeIndex> {
+        crate::semantic::resolve_fragment(self, href)
+    }
+
+    /// The document's headings as a nested outline, e.g. for a table of contents.
+    pub fn outline(&self) -> Vec<crate::semantic::OutlineEntry<'_>> {
+        crate::semantic::outline(self, &crate::semantic::OutlineOptions::default())
+    }
+
+    /// The outline with explicit options, e.g. to leave out headings inside JSX.
+    pub fn outline_with_options(
+        &self,
+        options: &crate::semantic::OutlineOptions,
+    ) -> Vec<crate::semantic::OutlineEntry<'_>> {
+        crate::semantic::outline(self, options)
+    }
+
+    /// Extract the alert kind and children of a blockquote.
+    pub fn blockquote_view(
+        &self,
+        node_index: NodeIndex,
+    ) -> Option<crate::semantic::BlockquoteView<'_>> {
+        crate::semantic::blockquote_view(self, node_index)
+    }
+
+    /// Extract the label, destination and title of a link reference definition.
+    pub fn definition_view(
+        &self,
+        node_index: NodeIndex,
+    ) -> Option<crate::semantic::DefinitionInfo<'_>> {
+        crate::semantic::definition_view(self, node_index)
+    }
+
+    /// Footnotes in citation order (numbered by first reference), each with the
+    /// reference nodes that cite it. Unreferenced definitions are left out, and a
+    /// `[^label]` with no definition stays text.
+    pub fn footnotes(&self) -> Vec<crate::semantic::FootnoteView<'_>> {
+        crate::semantic::footnotes(self)
+    }
+
+    /// Footnote numbering for the whole document, for resolving many references and
+    /// definitions in one pass without renumbering for each.
+    pub fn footnote_table(&self) -> crate::semantic::FootnoteTable<'_> {
+        crate::semantic::footnote_table(self)
+    }
+
+    /// Numbers every footnote in the document on each call, so calling it per reference
+    /// is quadratic; use [`Ast::footnote_table`] for many lookups. Resolves a footnote
+    /// reference node to its footnote number and definition.
+    pub fn footnote_reference_view(
+        &self,
+        node_index: NodeIndex,
+    ) -> Option<crate::semantic::FootnoteReferenceView<'_>> {
+        crate::semantic::footnote_reference_view(self, node_index)
+    }
+
+    /// Get the label of a footnote reference or definition node.
+    pub fn footnote_label(&self, node_index: NodeIndex) -> Option<&str> {
+        crate::semantic::footnote_label(self, node_index)
+    }
+
+    /// Get the text of a text node with backslash escapes and character references decoded.
+    pub fn text_value(&self, node_index: NodeIndex) -> Option<std::borrow::Cow<'_, str>> {
+        crate::semantic::text_value(self, node_index)
+    }
+
+    /// Get the raw source skipped by a recovery node.
+    pub fn recovery_source(&self, node_index: NodeIndex) -> Option<&str> {
+        crate::semantic::recovery_source(self, node_index)
+    }
+
+    /// Extract the imported bindings and exported names of an ESM node.
+    pub fn esm_view(&self, node_index: NodeIndex) -> Option<crate::semantic::EsmView<'_>> {
+        crate::semantic::esm_view(self, node_index)
+    }
+
     /// Extract typed semantic details for an MDX expression node.
     pub fn expression_info(
         &self,
@@ -822,6 +1143,11 @@ impl Ast {
         crate::semantic::jsx_element_view(self, node_index)
     }
 
+    /// Text inside an HTML comment node, without the `<!--` / `-->` delimiters.
+    pub fn html_comment_text(&self, node_index: NodeIndex) -> Option<&str> {
+        crate::semantic::html_comment_text(self, node_index)
+    }
+
     /// Extract plain-text semantic parts for a node.
     pub fn plain_text_parts(
         &self,
@@ -1,11 +1,21 @@
+pub mod arena;
 pub mod ast;
+mod emoji;
+mod entities;
+mod nostr;
 pub mod parser;
 pub mod render;
 pub mod semantic;
+pub mod streaming;
 pub mod token;
 pub mod tokenizer;
 pub mod tree_builder;
 
-pub use parser::{ParseOptions, parse, parse_with_options};
-pub use render::render;
+pub use arena::{ParserArena, ParserPool};
+pub use parser::{
+    ParseOptions, parse, parse_borrowed, parse_borrowed_with_options, parse_owned,
+    parse_owned_with_options, parse_with_options,
+};
+pub use render::{render, render_outline};
+pub use streaming::StreamingParser;
 pub use tree_builder::serialize_tree;
@@ -80,8 +80,14 @@ fn print_ast(ast: &hypernote_mdx::ast::Ast) {
             | NodeTag::ListOrdered
             | NodeTag::ListItem
             | NodeTag::Strikethrough
+            | NodeTag::Spoiler
+            | NodeTag::Highlight
+            | NodeTag::Underline
+            | NodeTag::Superscript
+            | NodeTag::Subscript
             | NodeTag::MdxJsxElement
             | NodeTag::MdxJsxFragment
+            | NodeTag::FootnoteDefinition
             | NodeTag::TableRow
             | NodeTag::TableCell => {
                 let children = ast.children(node_idx);
@@ -143,11 +149,19 @@ fn print_node(
             let name = ast.token_slice(elem.name_token);
             print!(" <{}>", name);
         }
-        NodeTag::Link | NodeTag::Image => {
-            if let NodeData::Extra(idx) = node.data {
-                let url_token = ast.extra_data[idx as usize + 2];
-                let url = ast.token_slice(url_token);
-                print!(" (url={})", url);
+        NodeTag::Link => {
+            if let Some(link) = ast.link_view(node_idx) {
+                print!(" (url={})", link.url);
+            }
+        }
+        NodeTag::Image => {
+            if let Some(image) = ast.image_view(node_idx) {
+                print!(" (url={})", image.url);
+            }
+        }
+        NodeTag::Definition => {
+            if let Some(def) = ast.definition_view(node_idx) {
+                print!(" [{}] (url={})", def.label, def.url);
             }
         }
         NodeTag::Frontmatter => {
@@ -3,6 +3,7 @@ use std::fs;
 
 use hypernote_mdx::ast::*;
 use hypernote_mdx::parse;
+use hypernote_mdx::semantic::{AlertKind, FootnoteTable, decode_markdown_text};
 
 // ANSI escape codes
 const RESET: &str = "\x1b[0m";
@@ -19,6 +20,7 @@ const BLUE: &str = "\x1b[34m";
 const MAGENTA: &str = "\x1b[35m";
 const GRAY: &str = "\x1b[90m";
 const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -41,6 +43,7 @@ fn main() {
 }
 
 fn render_pretty(ast: &Ast, output: &mut String) {
+    let footnotes = ast.footnote_table();
     let doc_idx = ast
         .nodes
         .iter()
@@ -54,19 +57,19 @@ fn render_pretty(ast: &Ast, output: &mut String) {
             if i > 0 {
                 output.push('\n');
             }
-            render_node(ast, child_idx, output);
+            render_node(ast, &footnotes, child_idx, output);
         }
     }
 }
 
-fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
+fn render_node(ast: &Ast, footnotes: &FootnoteTable, node_idx: NodeIndex, output: &mut String) {
     let node = &ast.nodes[node_idx as usize];
 
     match node.tag {
         NodeTag::Document => {
             let children = ast.children(node_idx);
             for &child_idx in children {
-                render_node(ast, child_idx, output);
+                render_node(ast, footnotes, child_idx, output);
             }
         }
 
@@ -99,7 +102,7 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
             let children =
                 &ast.extra_data[info.children_start as usize..info.children_end as usize];
             for &child_raw in children {
-                render_inline(ast, child_raw, output);
+                render_inline(ast, footnotes, child_raw, output);
             }
             output.push_str(&format!("{RESET}\n"));
         }
@@ -110,21 +113,33 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
                 return;
             }
             for &child_idx in children {
-                render_inline(ast, child_idx, output);
+                render_inline(ast, footnotes, child_idx, output);
             }
             output.push('\n');
         }
 
         NodeTag::CodeBlock => {
-            render_code_block(ast, node, output);
+            render_code_block(ast, node_idx, output);
         }
 
         NodeTag::Blockquote => {
             let children = ast.children(node_idx);
+            let alert = ast.blockquote_view(node_idx).and_then(|view| view.alert);
+            let bar_color = match alert {
+                Some(AlertKind::Note) => BLUE,
+                Some(AlertKind::Tip) => GREEN,
+                Some(AlertKind::Important) => MAGENTA,
+                Some(AlertKind::Warning) => YELLOW,
+                Some(AlertKind::Caution) => RED,
+                None => GRAY,
+            };
+            if let Some(alert) = alert {
+                output.push_str(&format!("{bar_color}  | {BOLD}{}{RESET}\n", alert.marker()));
+            }
             for &child_idx in children {
-                output.push_str(&format!("{GRAY}  | {RESET}"));
+                output.push_str(&format!("{bar_color}  | {RESET}"));
                 output.push_str(DIM);
-                render_inline(ast, child_idx, output);
+                render_inline(ast, footnotes, child_idx, output);
                 output.push_str(RESET);
             }
             output.push('\n');
@@ -137,14 +152,14 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
         NodeTag::ListUnordered => {
             let children = ast.children(node_idx);
             for &child_idx in children {
-                render_list_item(ast, child_idx, output, None);
+                render_list_item(ast, footnotes, child_idx, output, None);
             }
         }
 
         NodeTag::ListOrdered => {
             let children = ast.children(node_idx);
             for (i, &child_idx) in children.iter().enumerate() {
-                render_list_item(ast, child_idx, output, Some(i + 1));
+                render_list_item(ast, footnotes, child_idx, output, Some(i + 1));
             }
         }
 
@@ -172,7 +187,7 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
                 let children =
                     &ast.extra_data[elem.children_start as usize..elem.children_end as usize];
                 for &child_raw in children {
-                    render_node(ast, child_raw, output);
+                    render_node(ast, footnotes, child_raw, output);
                 }
                 output.push_str(&format!("{DIM}</{name}>{RESET}\n"));
             }
@@ -181,7 +196,7 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
         NodeTag::MdxJsxFragment => {
             let children = ast.children(node_idx);
             for &child_idx in children {
-                render_node(ast, child_idx, output);
+                render_node(ast, footnotes, child_idx, output);
             }
         }
 
@@ -193,26 +208,57 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
             }
         }
 
+        NodeTag::MdxEsmImport | NodeTag::MdxEsmExport => {
+            if let Some(view) = ast.esm_view(node_idx) {
+                output.push_str(&format!("{DIM}{}{RESET}\n", view.value));
+            }
+        }
+
+        NodeTag::Recovery => {
+            let source = ast.recovery_source(node_idx).unwrap_or("");
+            output.push_str(&format!("{GRAY}{source}{RESET}\n"));
+        }
+
+        NodeTag::MathBlock => {
+            let tex = ast.math_info(node_idx).map_or("", |info| info.tex);
+            for line in tex.lines() {
+                output.push_str(&format!("  {ITALIC}{MAGENTA}{line}{RESET}\n"));
+            }
+        }
+
+        // Definitions are not shown; links display their resolved URL instead.
+        // Comments are not shown either.
+        NodeTag::Definition | NodeTag::HtmlComment => {}
+
+        NodeTag::FootnoteDefinition => {
+            let number = footnotes.definition(node_idx).map_or(0, |f| f.number);
+            output.push_str(&format!("{DIM}[{number}]{RESET} "));
+            for &child_idx in ast.children(node_idx) {
+                render_inline(ast, footnotes, child_idx, output);
+            }
+            output.push('\n');
+        }
+
         _ => {
-            render_inline(ast, node_idx, output);
+            render_inline(ast, footnotes, node_idx, output);
         }
     }
 }
 
-fn render_inline(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
+fn render_inline(ast: &Ast, footnotes: &FootnoteTable, node_idx: NodeIndex, output: &mut String) {
     let node = &ast.nodes[node_idx as usize];
 
     match node.tag {
         NodeTag::Text => {
-            let text = ast.token_slice(node.main_token);
-            output.push_str(text);
+            let text = decode_markdown_text(ast.token_slice(node.main_token));
+            output.push_str(&text);
         }
 
         NodeTag::Strong => {
             output.push_str(BOLD);
             let children = ast.children(node_idx);
             for &child_idx in children {
-                render_inline(ast, child_idx, output);
+                render_inline(ast, footnotes, child_idx, output);
             }
             output.push_str(RESET);
         }
@@ -221,7 +267,7 @@ fn render_inline(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
             output.push_str(ITALIC);
             let children = ast.children(node_idx);
             for &child_idx in children {
-                render_inline(ast, child_idx, output);
+                render_inline(ast, footnotes, child_idx, output);
             }
             output.push_str(RESET);
         }
@@ -230,7 +276,27 @@ fn render_inline(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
             output.push_str(STRIKETHROUGH);
             let children = ast.children(node_idx);
             for &child_idx in children {
-                render_inline(ast, child_idx, output);
+                render_inline(ast, footnotes, child_idx, output);
+            }
+            output.push_str(RESET);
+        }
+
+        NodeTag::Spoiler
+        | NodeTag::Highlight
+        | NodeTag::Underline
+        | NodeTag::Superscript
+        | NodeTag::Subscript => {
+            // Terminals cannot raise or lower text, so sub/superscripts are only dimmed.
+            let styles: &[&str] = match node.tag {
+                NodeTag::Spoiler => &[GRAY, REVERSE],
+                NodeTag::Highlight => &[YELLOW, REVERSE],
+                NodeTag::Underline => &[UNDERLINE],
+                _ => &[DIM],
+            };
+            styles.iter().for_each(|style| output.push_str(style));
+            let children = ast.children(node_idx);
+            for &child_idx in children {
+                render_inline(ast, footnotes, child_idx, output);
             }
             output.push_str(RESET);
         }
@@ -245,31 +311,76 @@ fn render_inline(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
         }
 
         NodeTag::Link => {
-            let info = ast.link_info(node_idx);
-            let url = ast.token_slice(info.url_token);
+            let url = ast
+                .link_view(node_idx)
+                .map(|view| view.url)
+                .unwrap_or_default();
 
             output.push_str(&format!("{BLUE}{UNDERLINE}"));
             for &child_idx in ast.link_children(node_idx) {
-                render_inline(ast, child_idx, output);
+                render_inline(ast, footnotes, child_idx, output);
             }
             output.push_str(&format!("{RESET} {DIM}({url}){RESET}"));
         }
 
         NodeTag::Image => {
-            let info = ast.link_info(node_idx);
-            let url = ast.token_slice(info.url_token);
+            let url = ast
+                .image_view(node_idx)
+                .map(|view| view.url)
+                .unwrap_or_default();
 
             output.push_str(&format!("{MAGENTA}[img: "));
             for &child_idx in ast.link_children(node_idx) {
-                render_inline(ast, child_idx, output);
+                render_inline(ast, footnotes, child_idx, output);
             }
             output.push_str(&format!("]{RESET} {DIM}({url}){RESET}"));
         }
 
+        NodeTag::FootnoteReference => {
+            let number = footnotes.reference(ast, node_idx).map_or(0, |f| f.number);
+            output.push_str(&format!("{BLUE}[{number}]{RESET}"));
+        }
+
         NodeTag::HardBreak => {
             output.push('\n');
         }
 
+        NodeTag::SoftBreak => {
+            output.push(' ');
+        }
+
+        NodeTag::HtmlComment => {}
+
+        NodeTag::NostrReference => {
+            if let Some(view) = ast.nostr_reference_view(node_idx) {
+                let short: String = view.bech32.chars().take(16).collect();
+                output.push_str(&format!("{MAGENTA}@{short}…{RESET}"));
+            }
+        }
+
+        // Terminals cannot show the image, so keep the shortcode.
+        NodeTag::CustomEmoji => {
+            let shortcode = ast
+                .custom_emoji_view(node_idx)
+                .map_or("", |view| view.shortcode);
+            output.push_str(&format!("{YELLOW}:{shortcode}:{RESET}"));
+        }
+
+        NodeTag::Hashtag => {
+            let name = ast.hashtag_name(node_idx).unwrap_or("");
+            output.push_str(&format!("{BLUE}#{name}{RESET}"));
+        }
+
+        NodeTag::Mention => {
+            let name = ast.mention_name(node_idx).unwrap_or("");
+            output.push_str(&format!("{MAGENTA}@{name}{RESET}"));
+        }
+
+        NodeTag::MathInline => {
+            let tex = ast.math_info(node_idx).map_or("", |info| info.tex);
+            output.push_str(&format!("{ITALIC}{MAGENTA}{tex}{RESET}"));
+        }
+
         NodeTag::MdxTextExpression => {
             if let NodeData::Extra(idx) = node.data {
                 let range = ast.extra_range(idx);
@@ -281,7 +392,7 @@ fn render_inline(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
         NodeTag::Paragraph => {
             let children = ast.children(node_idx);
             for &child_idx in children {
-                render_inline(ast, child_idx, output);
+                render_inline(ast, footnotes, child_idx, output);
             }
         }
 
@@ -292,7 +403,13 @@ fn render_inline(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
     }
 }
 
-fn render_list_item(ast: &Ast, node_idx: NodeIndex, output: &mut String, number: Option<usize>) {
+fn render_list_item(
+    ast: &Ast,
+    footnotes: &FootnoteTable,
+    node_idx: NodeIndex,
+    output: &mut String,
+    number: Option<usize>,
+) {
     let info = ast.list_item_info(node_idx);
 
     let bullet = match number {
@@ -310,21 +427,37 @@ fn render_list_item(ast: &Ast, node_idx: NodeIndex, output: &mut String, number:
     }
 
     let children = ast.children(node_idx);
-    for &child_idx in children {
+    for (i, &child_idx) in children.iter().enumerate() {
         let child = &ast.nodes[child_idx as usize];
-        if child.tag == NodeTag::Paragraph {
-            let para_children = ast.children(child_idx);
-            for &para_child_idx in para_children {
-                render_inline(ast, para_child_idx, output);
+        match child.tag {
+            NodeTag::Paragraph if i == 0 => {
+                for &para_child_idx in ast.children(child_idx) {
+                    render_inline(ast, footnotes, para_child_idx, output);
+                }
             }
-        } else {
-            render_inline(ast, child_idx, output);
+            NodeTag::Paragraph
+            | NodeTag::CodeBlock
+            | NodeTag::Blockquote
+            | NodeTag::ListUnordered
+            | NodeTag::ListOrdered
+            | NodeTag::MdxJsxElement
+            | NodeTag::MdxJsxSelfClosing => {
+                // Nested blocks go on their own lines, indented under the bullet.
+                let mut nested = String::new();
+                render_node(ast, footnotes, child_idx, &mut nested);
+                for line in nested.trim_end_matches('\n').split('\n') {
+                    output.push_str("\n    ");
+                    output.push_str(line);
+                }
+            }
+            _ => render_inline(ast, footnotes, child_idx, output),
         }
     }
     output.push('\n');
 }
 
-fn render_code_block(ast: &Ast, node: &Node, output: &mut String) {
+fn render_code_block(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
+    let node = &ast.nodes[node_idx as usize];
     use hypernote_mdx::token::Tag as TokenTag;
 
     let fence_token = node.main_token;
@@ -346,9 +479,12 @@ fn render_code_block(ast: &Ast, node: &Node, output: &mut String) {
     }
 
     // Extract code content
-    let code = extract_code_block_content(ast, fence_token);
+    let code = ast
+        .code_block_info(node_idx)
+        .map(|info| info.code)
+        .unwrap_or_default();
     output.push_str(&format!("{DIM}"));
-    output.push_str(code);
+    output.push_str(&code);
     if !code.is_empty() && !code.ends_with('\n') {
         output.push('\n');
     }
@@ -468,10 +604,17 @@ fn render_inline_plain(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
     let node = &ast.nodes[node_idx as usize];
     match node.tag {
         NodeTag::Text => {
-            let text = ast.token_slice(node.main_token);
-            output.push_str(text);
-        }
-        NodeTag::Strong | NodeTag::Emphasis | NodeTag::Strikethrough => {
+            let text = decode_markdown_text(ast.token_slice(node.main_token));
+            output.push_str(&text);
+        }
+        NodeTag::Strong
+        | NodeTag::Emphasis
+        | NodeTag::Strikethrough
+        | NodeTag::Spoiler
+        | NodeTag::Highlight
+        | NodeTag::Underline
+        | NodeTag::Superscript
+        | NodeTag::Subscript => {
             let children = ast.children(node_idx);
             for &child_idx in children {
                 render_inline_plain(ast, child_idx, output);
@@ -524,40 +667,3 @@ fn extract_token_range<'a>(ast: &'a Ast, range: &Range) -> &'a str {
     };
     &ast.source[start..end]
 }
-
-fn extract_code_block_content<'a>(ast: &'a Ast, fence_token: u32) -> &'a str {
-    use hypernote_mdx::token::Tag as TokenTag;
-
-    let mut code_start: u32 = u32::MAX;
-    let mut code_end: u32 = 0;
-    let mut in_code = false;
-
-    let mut i = fence_token;
-    while (i as usize) < ast.token_tags.len() {
-        if ast.token_tags[i as usize] == TokenTag::CodeFenceEnd {
-            break;
-        }
-        if ast.token_tags[i as usize] == TokenTag::Newline && !in_code {
-            in_code = true;
-            i += 1;
-            continue;
-        }
-        if in_code {
-            let start = ast.token_starts[i as usize];
-            let end = if (i as usize + 1) < ast.token_starts.len() {
-                ast.token_starts[i as usize + 1]
-            } else {
-                ast.source.len() as u32
-            };
-            code_start = code_start.min(start);
-            code_end = code_end.max(end);
-        }
-        i += 1;
-    }
-
-    if code_start < code_end {
-        &ast.source[code_start as usize..code_end as usize]
-    } else {
-        ""
-    }
-}
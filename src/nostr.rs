@@ -0,0 +1,202 @@
+//! NIP-19 bech32 entities, as used by NIP-21 `nostr:` URIs.
+//!
+//! Decoding is purely local: the checksum is verified and TLV payloads are split into
+//! hex keys, relay hints and kinds. `nsec` keys are deliberately not recognized.
+
+use crate::semantic::{NostrEntity, NostrEntityKind};
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+const TLV_SPECIAL: u8 = 0;
+const TLV_RELAY: u8 = 1;
+const TLV_AUTHOR: u8 = 2;
+const TLV_KIND: u8 = 3;
+
+/// Decode a bech32 NIP-19 entity such as `npub1...` or `nevent1...`.
+pub(crate) fn decode(bech32: &str) -> Option<NostrEntity> {
+    let (hrp, data) = decode_bech32(bech32)?;
+    let kind = match hrp.as_str() {
+        "npub" => NostrEntityKind::Npub,
+        "note" => NostrEntityKind::Note,
+        "nprofile" => NostrEntityKind::Nprofile,
+        "nevent" => NostrEntityKind::Nevent,
+        "naddr" => NostrEntityKind::Naddr,
+        _ => return None,
+    };
+
+    let mut entity = NostrEntity {
+        kind,
+        pubkey: None,
+        event_id: None,
+        identifier: None,
+        event_kind: None,
+        relays: Vec::new(),
+    };
+    match kind {
+        NostrEntityKind::Npub => entity.pubkey = Some(hex_32(&data)?),
+        NostrEntityKind::Note => entity.event_id = Some(hex_32(&data)?),
+        NostrEntityKind::Nprofile | NostrEntityKind::Nevent | NostrEntityKind::Naddr => {
+            read_tlv(&data, &mut entity)?;
+        }
+    }
+    Some(entity)
+}
+
+/// Fill `entity` from a TLV payload, checking that the fields its kind requires are set.
+fn read_tlv(mut data: &[u8], entity: &mut NostrEntity) -> Option<()> {
+    while !data.is_empty() {
+        let [tag, len, ref rest @ ..] = *data else {
+            return None;
+        };
+        let value = rest.get(..len as usize)?;
+        data = &rest[len as usize..];
+        match (tag, entity.kind) {
+            (TLV_SPECIAL, NostrEntityKind::Nprofile) => {
+                entity.pubkey.get_or_insert(hex_32(value)?);
+            }
+            (TLV_SPECIAL, NostrEntityKind::Nevent) => {
+                entity.event_id.get_or_insert(hex_32(value)?);
+            }
+            (TLV_SPECIAL, _) => {
+                let identifier = String::from_utf8(value.to_vec()).ok()?;
+                entity.identifier.get_or_insert(identifier);
+            }
+            (TLV_RELAY, _) => entity.relays.push(String::from_utf8(value.to_vec()).ok()?),
+            (TLV_AUTHOR, _) => {
+                entity.pubkey.get_or_insert(hex_32(value)?);
+            }
+            (TLV_KIND, _) => {
+                let bytes: [u8; 4] = value.try_into().ok()?;
+                entity.event_kind.get_or_insert(u32::from_be_bytes(bytes));
+            }
+            // Unknown TLV types are skipped, as NIP-19 asks.
+            _ => {}
+        }
+    }
+
+    let complete = match entity.kind {
+        NostrEntityKind::Nprofile => entity.pubkey.is_some(),
+        NostrEntityKind::Nevent => entity.event_id.is_some(),
+        NostrEntityKind::Naddr => {
+            entity.identifier.is_some() && entity.pubkey.is_some() && entity.event_kind.is_some()
+        }
+        NostrEntityKind::Npub | NostrEntityKind::Note => true,
+    };
+    complete.then_some(())
+}
+
+fn hex_32(bytes: &[u8]) -> Option<String> {
+    if bytes.len() != 32 {
+        return None;
+    }
+    Some(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Split a bech32 string into its human-readable part and 8-bit payload, verifying the
+/// checksum. NIP-19 payloads may exceed the 90 character limit of BIP-173.
+fn decode_bech32(input: &str) -> Option<(String, Vec<u8>)> {
+    let has_lower = input.bytes().any(|b| b.is_ascii_lowercase());
+    let has_upper = input.bytes().any(|b| b.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return None;
+    }
+    let input = input.to_ascii_lowercase();
+    let separator = input.rfind('1')?;
+    let (hrp, data) = (&input[..separator], &input[separator + 1..]);
+    if hrp.is_empty() || data.len() < 6 {
+        return None;
+    }
+
+    let values = data
+        .bytes()
+        .map(|b| CHARSET.iter().position(|&c| c == b).map(|v| v as u8))
+        .collect::<Option<Vec<u8>>>()?;
+    let mut checked = hrp_expand(hrp);
+    checked.extend_from_slice(&values);
+    if polymod(&checked) != 1 {
+        return None;
+    }
+
+    let payload = convert_bits(&values[..values.len() - 6])?;
+    Some((hrp.to_string(), payload))
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut out: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    out.push(0);
+    out.extend(hrp.bytes().map(|b| b & 31));
+    out
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut checksum = 1u32;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ff_ffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Regroup 5-bit values into bytes; leftover padding must be short and zero.
+fn convert_bits(values: &[u8]) -> Option<Vec<u8>> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let mut out = Vec::with_capacity(values.len() * 5 / 8);
+    for &value in values {
+        acc = (acc << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    if bits >= 5 || (acc << (8 - bits)) & 0xff != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_nip19_examples() {
+        let npub =
+            decode("npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg").unwrap();
+        assert_eq!(NostrEntityKind::Npub, npub.kind);
+        assert_eq!(
+            Some("7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e"),
+            npub.pubkey.as_deref()
+        );
+
+        let nprofile = decode("nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p").unwrap();
+        assert_eq!(
+            Some("3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d"),
+            nprofile.pubkey.as_deref()
+        );
+        assert_eq!(
+            vec!["wss://r.x.com", "wss://djbas.sadkb.com"],
+            nprofile.relays
+        );
+    }
+
+    #[test]
+    fn rejects_bad_checksums_and_secret_keys() {
+        assert!(
+            decode("npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptq").is_none()
+        );
+        assert!(
+            decode("NPUB10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg").is_none()
+        );
+        assert!(
+            decode("nsec1vl029mgpspedva04g90vltkh6fvh240zqtv9k0t9af8935ke9laqsnlfe5").is_none()
+        );
+    }
+}
@@ -1,31 +1,136 @@
 use crate::ast::*;
 use crate::token::{Tag as TokenTag, Token};
-use crate::tokenizer::Tokenizer;
+use crate::tokenizer::{MAX_NESTING_DEPTH, Tokenizer};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 
 const MAX_PARSE_ERRORS: usize = 4096;
 
 #[derive(Debug, Clone)]
 pub struct ParseOptions {
+    /// Replace gemoji shortcodes such as `:tada:` with their Unicode emoji before parsing.
+    /// Each replacement is recorded in `Ast::source_edits`, so offsets can be mapped back
+    /// with `Ast::original_offset`; shortcodes in `custom_emoji` are left alone.
     pub normalize_emoji_shortcodes: bool,
+    /// Custom emoji image URLs by shortcode, e.g. from the `emoji` tags of a Nostr event
+    /// (NIP-30). A matching `:shortcode:` becomes a `custom_emoji` node; these take
+    /// precedence over the built-in shortcodes.
+    pub custom_emoji: HashMap<String, String>,
+    /// Turn bare `https://`, `www.` and email addresses in text into links (GFM
+    /// autolink literals). `<https://...>` autolinks are always recognized.
+    pub autolink_literals: bool,
+    /// Parse `$...$` inline math and `$$` math blocks. Off by default so dollar amounts
+    /// in ordinary prose stay text. Even when on, `$5 and $10` stays text: a closing `$`
+    /// cannot follow whitespace or come before a digit. Math content is kept verbatim,
+    /// with no escapes or emphasis inside.
+    pub math: bool,
+    /// Recognize NIP-21 `nostr:npub1...`, `nostr:nevent1...` and similar URIs with a valid
+    /// bech32 checksum as `nostr_reference` nodes. `nsec` secret keys are never
+    /// recognized.
+    pub nostr: bool,
+    /// Turn `#topic` words into `hashtag` nodes. A `#word` at the start of a line is a
+    /// hashtag rather than a heading; `# Heading` keeps its space. Words inside code or
+    /// links, after a letter (`a#b`) or made only of digits (`#42`) stay text.
+    pub hashtags: bool,
+    /// Turn `@name` words into `mention` nodes, with the same exceptions as `hashtags`,
+    /// so `me@example.com` stays text.
+    pub mentions: bool,
+    /// Treat every line break inside a paragraph as a hard break, as chat users expect
+    /// (markdown-it's `breaks`). Otherwise single newlines are `soft_break` nodes, which
+    /// plain text turns into a space. `render` writes both back as the original newline.
+    pub breaks: bool,
+    /// Parse the chat formatting users type in Discord and Telegram: `||spoiler||`,
+    /// `==highlight==`, `++underline++`, `^superscript^` and `~subscript~`. A span opens
+    /// only when its closer follows on the same line and neither delimiter hugs
+    /// whitespace on the inside, so `a || b`, `x == y`, `C++` and `2^10` stay text.
+    pub chat_inlines: bool,
 }
 
 impl Default for ParseOptions {
     fn default() -> Self {
         ParseOptions {
             normalize_emoji_shortcodes: false,
+            custom_emoji: HashMap::new(),
+            autolink_literals: true,
+            math: false,
+            nostr: false,
+            hashtags: false,
+            mentions: false,
+            breaks: false,
+            chat_inlines: false,
         }
     }
 }
 
-pub struct Parser {
-    source: String,
+pub struct Parser<'src> {
+    source: &'src str,
     token_tags: Vec<TokenTag>,
     token_starts: Vec<ByteOffset>,
     token_index: TokenIndex,
     nodes: Vec<Node>,
+    /// The span of each node, recorded when the node is added or filled in.
+    node_spans: Vec<Span>,
     extra_data: Vec<u32>,
     scratch: Vec<NodeIndex>,
     errors: Vec<Error>,
+    /// Link reference definitions by normalized label; the first definition wins.
+    definitions: HashMap<String, TokenIndex>,
+    /// Normalized labels of all footnote definitions.
+    footnote_labels: HashSet<String>,
+    /// The `]` closing each link or image label being parsed, innermost last; links
+    /// cannot contain links.
+    link_label_ends: Vec<TokenIndex>,
+    /// The `]` paired with each token of the inline run starting at `link_pairs_start`,
+    /// or `TokenIndex::MAX` where there is none, filled in once per run.
+    link_pairs: Vec<TokenIndex>,
+    link_pairs_start: TokenIndex,
+    /// Open JSX elements and fragments around the current token. A parse error unwinds
+    /// all of them, back to the top level.
+    jsx_depth: u32,
+    options: &'src ParseOptions,
+    /// The input may still grow: constructs cut off by the end of the input are closed
+    /// where it ends and listed in `incomplete` instead of being reported as errors.
+    partial: bool,
+    incomplete: Vec<NodeIndex>,
+    /// Links and footnote references whose definition is not known before them, so a
+    /// definition arriving later could still change how they parse.
+    pending_references: u32,
+    /// The top-level blocks parsed so far, in document order.
+    blocks: Vec<TopLevelBlock>,
+    /// A tokenizer whose tokens from the given index on are the ones in `token_tags`,
+    /// so a recovery can tell where the tokens it produces afresh line up with them.
+    replay: Option<(Tokenizer<'src>, TokenIndex)>,
+}
+
+/// Parser state just before a top-level block, enough to truncate back to it and parse
+/// the rest of the document again.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BlockStart {
+    pub(crate) byte_offset: ByteOffset,
+    pub(crate) token: TokenIndex,
+    pub(crate) nodes: usize,
+    pub(crate) extra_data: usize,
+    pub(crate) errors: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TopLevelBlock {
+    pub(crate) node: NodeIndex,
+    pub(crate) start: BlockStart,
+    /// Parsed without errors or pending references, so text appended after the document
+    /// cannot change it once a later block has begun.
+    pub(crate) settled: bool,
+}
+
+/// Where a `[` or `![` takes its destination from.
+enum LinkTarget {
+    Inline,
+    Reference {
+        definition: TokenIndex,
+        reference: LinkReference,
+        /// Token after the last bracket of the reference.
+        end: TokenIndex,
+    },
 }
 
 #[derive(Debug)]
@@ -35,66 +140,977 @@ pub enum ParseError {
 
 type PResult<T> = Result<T, ParseError>;
 
-pub fn parse(source: &str) -> Ast {
+pub fn parse(source: &str) -> Ast<'static> {
     parse_with_options(source, &ParseOptions::default())
 }
 
-pub fn parse_with_options(source: &str, options: &ParseOptions) -> Ast {
-    let source_owned = if options.normalize_emoji_shortcodes {
-        normalize_emoji_shortcodes(source)
+pub fn parse_with_options(source: &str, options: &ParseOptions) -> Ast<'static> {
+    parse_borrowed_with_options(source, options).into_owned()
+}
+
+/// Parse `source` without copying it: the AST borrows the caller's input.
+pub fn parse_borrowed(source: &str) -> Ast<'_> {
+    parse_borrowed_with_options(source, &ParseOptions::default())
+}
+
+/// Like `parse_borrowed`. With `normalize_emoji_shortcodes`, a source that contains a
+/// shortcode is rewritten, so the AST owns the rewritten copy instead.
+pub fn parse_borrowed_with_options<'src>(source: &'src str, options: &ParseOptions) -> Ast<'src> {
+    parse_source(Cow::Borrowed(source), options, &mut ParseBuffers::default())
+}
+
+/// Parse `source`, taking ownership of it instead of copying it.
+pub fn parse_owned(source: String) -> Ast<'static> {
+    parse_owned_with_options(source, &ParseOptions::default())
+}
+
+pub fn parse_owned_with_options(source: String, options: &ParseOptions) -> Ast<'static> {
+    parse_source(Cow::Owned(source), options, &mut ParseBuffers::default())
+}
+
+/// Vectors a parse fills, kept empty between documents so their allocations can be used
+/// again: those of a finished AST, and the parser's working space.
+#[derive(Default)]
+pub(crate) struct ParseBuffers {
+    token_tags: Vec<TokenTag>,
+    token_starts: Vec<ByteOffset>,
+    nodes: Vec<Node>,
+    node_spans: Vec<Span>,
+    extra_data: Vec<u32>,
+    errors: Vec<Error>,
+    source_edits: Vec<SourceEdit>,
+    incomplete: Vec<NodeIndex>,
+    scratch: Vec<NodeIndex>,
+    blocks: Vec<TopLevelBlock>,
+}
+
+impl ParseBuffers {
+    /// Take back the vectors of an AST that is no longer needed.
+    pub(crate) fn recycle(&mut self, ast: Ast) {
+        fn keep<T>(buffer: &mut Vec<T>, mut vec: Vec<T>) {
+            if vec.capacity() > buffer.capacity() {
+                vec.clear();
+                *buffer = vec;
+            }
+        }
+        keep(&mut self.token_tags, ast.token_tags);
+        keep(&mut self.token_starts, ast.token_starts);
+        keep(&mut self.nodes, ast.nodes);
+        keep(&mut self.node_spans, ast.node_spans);
+        keep(&mut self.extra_data, ast.extra_data);
+        keep(&mut self.errors, ast.errors);
+        keep(&mut self.source_edits, ast.source_edits);
+        keep(&mut self.incomplete, ast.incomplete);
+    }
+}
+
+pub(crate) fn parse_source<'src>(
+    source: Cow<'src, str>,
+    options: &ParseOptions,
+    buffers: &mut ParseBuffers,
+) -> Ast<'src> {
+    let (source, source_edits) = if options.normalize_emoji_shortcodes {
+        match normalize_emoji_shortcodes(&source, &options.custom_emoji) {
+            (_, edits) if edits.is_empty() => (source, std::mem::take(&mut buffers.source_edits)),
+            (normalized, edits) => (Cow::Owned(normalized), edits),
+        }
     } else {
-        source.to_string()
+        (source, std::mem::take(&mut buffers.source_edits))
     };
 
     // Phase 1: Tokenization
-    let mut tokenizer = Tokenizer::new(&source_owned);
-    let mut tokens: Vec<Token> = Vec::new();
+    let mut token_tags = std::mem::take(&mut buffers.token_tags);
+    let mut token_starts = std::mem::take(&mut buffers.token_starts);
+    tokenize_from(&source, 0, options, &mut token_tags, &mut token_starts);
+
+    // Phase 2: Parsing
+    let mut parser = Parser::new(&source, token_tags, token_starts, options);
+    parser.nodes = std::mem::take(&mut buffers.nodes);
+    parser.node_spans = std::mem::take(&mut buffers.node_spans);
+    parser.extra_data = std::mem::take(&mut buffers.extra_data);
+    parser.errors = std::mem::take(&mut buffers.errors);
+    parser.incomplete = std::mem::take(&mut buffers.incomplete);
+    parser.scratch = std::mem::take(&mut buffers.scratch);
+    parser.blocks = std::mem::take(&mut buffers.blocks);
+
+    parser.collect_definitions();
+    let _ = parser.parse_document(&[]);
+    let custom_emoji = parser.used_custom_emoji();
+    let Parser {
+        token_tags,
+        token_starts,
+        nodes,
+        node_spans,
+        extra_data,
+        errors,
+        incomplete,
+        mut scratch,
+        mut blocks,
+        ..
+    } = parser;
+    scratch.clear();
+    blocks.clear();
+    buffers.scratch = scratch;
+    buffers.blocks = blocks;
+
+    Ast {
+        source,
+        token_tags,
+        token_starts,
+        nodes,
+        node_spans,
+        extra_data,
+        errors,
+        custom_emoji,
+        source_edits,
+        incomplete,
+    }
+}
+
+/// Truncate `ast` back to the top-level block starting at `from` and parse the rest of
+/// its source again, returning the blocks found there. The blocks in `finalized` precede
+/// `from` and are kept as they are; the document node lists them followed by the new
+/// blocks. With `partial`, the source is treated as a prefix of a longer document.
+pub(crate) fn reparse_tail(
+    ast: &mut Ast,
+    from: &BlockStart,
+    finalized: &[NodeIndex],
+    options: &ParseOptions,
+    partial: bool,
+) -> Vec<TopLevelBlock> {
+    ast.token_tags.truncate(from.token as usize);
+    ast.token_starts.truncate(from.token as usize);
+    ast.nodes.truncate(from.nodes);
+    ast.node_spans.truncate(from.nodes);
+    ast.extra_data.truncate(from.extra_data);
+    ast.errors.truncate(from.errors);
+    tokenize_from(
+        &ast.source,
+        from.byte_offset,
+        options,
+        &mut ast.token_tags,
+        &mut ast.token_starts,
+    );
+
+    let mut parser = Parser::new(
+        &ast.source,
+        std::mem::take(&mut ast.token_tags),
+        std::mem::take(&mut ast.token_starts),
+        options,
+    );
+    parser.nodes = std::mem::take(&mut ast.nodes);
+    parser.node_spans = std::mem::take(&mut ast.node_spans);
+    parser.extra_data = std::mem::take(&mut ast.extra_data);
+    parser.errors = std::mem::take(&mut ast.errors);
+    parser.token_index = from.token;
+    parser.partial = partial;
+
+    parser.collect_definitions();
+    let _ = parser.parse_document(finalized);
+    ast.custom_emoji = parser.used_custom_emoji();
+
+    ast.token_tags = parser.token_tags;
+    ast.token_starts = parser.token_starts;
+    ast.nodes = parser.nodes;
+    ast.node_spans = parser.node_spans;
+    ast.extra_data = parser.extra_data;
+    ast.errors = parser.errors;
+    ast.incomplete = parser.incomplete;
+    parser.blocks
+}
+
+/// Tokenize `source` starting at the line beginning at byte `start`, appending to the
+/// token arrays. Offsets are absolute, so the output can extend an existing stream.
+fn tokenize_from(
+    source: &str,
+    start: ByteOffset,
+    options: &ParseOptions,
+    token_tags: &mut Vec<TokenTag>,
+    token_starts: &mut Vec<ByteOffset>,
+) {
+    let mut tokenizer = Tokenizer::starting_at(source, start).with_options(options);
 
     loop {
+        let tok: Token = tokenizer.next();
+        token_tags.push(tok.tag);
+        token_starts.push(tok.loc.start);
+        if tok.tag == TokenTag::Eof {
+            break;
+        }
+    }
+}
+
+/// Replace the bytes in `edit` of `ast.source` with `new_text` and parse the result. Only
+/// the top-level blocks the edit can reach are tokenized and parsed again; the blocks
+/// before them are kept as they are, and the blocks after them are carried over with
+/// their offsets and indices shifted.
+pub(crate) fn reparse_with_edit(
+    ast: &Ast,
+    edit: Span,
+    new_text: &str,
+    options: &ParseOptions,
+) -> Reparse {
+    let old_source = &ast.source;
+    let mut start = (edit.start as usize).min(old_source.len());
+    let mut end = (edit.end as usize).clamp(start, old_source.len());
+    while !old_source.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !old_source.is_char_boundary(end) {
+        end += 1;
+    }
+    let mut source = String::with_capacity(old_source.len() - (end - start) + new_text.len());
+    source.push_str(&old_source[..start]);
+    source.push_str(new_text);
+    source.push_str(&old_source[end..]);
+    let edit = Span {
+        start: start as ByteOffset,
+        end: end as ByteOffset,
+    };
+
+    // Shortcode normalization rewrites the source, so its edits cannot be mapped over.
+    if !options.normalize_emoji_shortcodes
+        && let Some(reparse) = reparse_region(ast, &mut source, edit, new_text.len(), options)
+    {
+        return reparse;
+    }
+
+    let new_ast = parse_owned_with_options(source, options);
+    Reparse {
+        old_nodes: Range {
+            start: 0,
+            end: ast.nodes.len().saturating_sub(1) as u32,
+        },
+        new_nodes: Range {
+            start: 0,
+            end: new_ast.nodes.len() as u32 - 1,
+        },
+        ast: new_ast,
+    }
+}
+
+/// Parse `source`, which is `ast.source` with the bytes in `edit` replaced by `inserted`
+/// bytes, reusing what lies outside the affected blocks, and move `source` into the new
+/// AST. Returns `None`, leaving `source` alone, where the edit can change how the rest of
+/// the document parses, such as an edited definition.
+fn reparse_region(
+    ast: &Ast,
+    source: &mut String,
+    edit: Span,
+    inserted: usize,
+    options: &ParseOptions,
+) -> Option<Reparse> {
+    let document = ast.nodes.len().checked_sub(1)?;
+    let NodeData::Children(document_children) = ast.nodes[document].data else {
+        return None;
+    };
+    if ast.nodes[document].tag != NodeTag::Document
+        || ast.node_spans.len() != ast.nodes.len()
+        || ast.token_tags.len() != ast.token_starts.len()
+        || ast.token_tags.last() != Some(&TokenTag::Eof)
+    {
+        return None;
+    }
+    let blocks = ast.children(document as NodeIndex);
+    let growth = inserted as i64 - (edit.end - edit.start) as i64;
+    let moved = |offset: ByteOffset| (offset as i64 + growth) as ByteOffset;
+
+    // Start at the last block that begins after a blank line with its first line among
+    // the first `same_prefix` tokens, so the block before it cannot run on into the edit.
+    // A block that failed to parse may have looked anywhere ahead, so start no later than
+    // that.
+    let recovered = blocks
+        .iter()
+        .position(|&block| ast.nodes[block as usize].tag == NodeTag::Recovery)
+        .unwrap_or(blocks.len());
+    let region_start = |same_prefix: usize| {
+        let mut first = blocks
+            .iter()
+            .take_while(|&&block| ast.nodes[block as usize].main_token as usize <= same_prefix)
+            .count()
+            .min(recovered + 1)
+            .saturating_sub(1);
+        loop {
+            if first == 0 {
+                break (0, BlockStart::default());
+            }
+            if let Some(start) = block_boundary(ast, blocks, first) {
+                let line_end = ast.token_tags[start.token as usize..]
+                    .iter()
+                    .position(|&tag| matches!(tag, TokenTag::Newline | TokenTag::BlankLine))
+                    .map_or(ast.token_tags.len(), |line| start.token as usize + line);
+                if line_end < same_prefix {
+                    break (first, start);
+                }
+            }
+            first -= 1;
+        }
+    };
+
+    // Tokenize again from the block the edit can reach, until a block after the edit
+    // starts with nothing open; the old tokens from there on carry over, shifted. Recovery
+    // tokenized the source after a failed block afresh, so only blocks up to the first
+    // failed one qualify. The tokens before the restart stay as they are, unless one of
+    // them looked ahead for a closer the edit can add, such as the `-->` of an
+    // unterminated `<!--`. Frontmatter is tokenized as Markdown without being parsed as
+    // such, so it may leave something open for the blocks after it, such as an emphasis;
+    // then the whole source is tokenized again.
+    let frontmatter_leaves_open = blocks
+        .first()
+        .is_some_and(|&block| ast.nodes[block as usize].tag == NodeTag::Frontmatter)
+        && blocks.get(1).is_some_and(|&next| {
+            let offset = ast.token_starts[ast.nodes[next as usize].main_token as usize];
+            let mut tokenizer = Tokenizer::new(&ast.source).with_options(options);
+            loop {
+                if tokenizer.at_line_start_with_nothing_open(offset) {
+                    break false;
+                }
+                let tok = tokenizer.next();
+                if tok.loc.start >= offset || tok.tag == TokenTag::Eof {
+                    break true;
+                }
+            }
+        });
+    let old_prefix = ast
+        .token_starts
+        .partition_point(|&start| start < edit.start)
+        .saturating_sub(1);
+    let (_, restart) = region_start(old_prefix);
+    let restart = if frontmatter_leaves_open
+        || may_open_multiline_construct(&source[..restart.byte_offset as usize], options)
+    {
+        BlockStart::default()
+    } else {
+        restart
+    };
+    let resyncable = if frontmatter_leaves_open {
+        0
+    } else {
+        blocks.len().min(recovered + 1)
+    };
+    let mut token_tags = ast.token_tags[..restart.token as usize].to_vec();
+    let mut token_starts = ast.token_starts[..restart.token as usize].to_vec();
+    let mut resync_points = (0..resyncable)
+        .skip_while(|&block| ast.node_spans[blocks[block] as usize].start < edit.end)
+        .filter_map(|block| block_boundary(ast, blocks, block))
+        .filter(|start| start.byte_offset >= edit.end)
+        .peekable();
+    let mut tokenizer = Tokenizer::starting_at(source, restart.byte_offset).with_options(options);
+    let carried = loop {
+        let offset = token_starts.last().map_or(0, |&start| start);
+        while resync_points
+            .next_if(|start| moved(start.byte_offset) <= offset)
+            .is_some()
+        {}
+        if let Some(start) = resync_points.peek()
+            && token_tags.last() == Some(&TokenTag::BlankLine)
+            && tokenizer.at_line_start_with_nothing_open(moved(start.byte_offset))
+        {
+            break start.token as usize;
+        }
         let tok = tokenizer.next();
-        tokens.push(tok);
+        token_tags.push(tok.tag);
+        token_starts.push(tok.loc.start);
         if tok.tag == TokenTag::Eof {
+            break ast.token_tags.len();
+        }
+    };
+    token_tags.extend_from_slice(&ast.token_tags[carried..]);
+    token_starts.extend(
+        ast.token_starts[carried..]
+            .iter()
+            .map(|&start| moved(start)),
+    );
+
+    // The tokens the edit changed lie between an unchanged prefix and an unchanged,
+    // shifted suffix.
+    let restart_token = restart.token as usize;
+    let same_prefix = ast.token_tags[restart_token..]
+        .iter()
+        .zip(&ast.token_starts[restart_token..])
+        .zip(
+            token_tags[restart_token..]
+                .iter()
+                .zip(&token_starts[restart_token..]),
+        )
+        .take_while(|&((old_tag, &old_start), (new_tag, &new_start))| {
+            old_start < edit.start && old_tag == new_tag && old_start == new_start
+        })
+        .count();
+    // The last of them may still end elsewhere.
+    let same_prefix = (restart_token + same_prefix).saturating_sub(1);
+    let carried_len = ast.token_tags.len() - carried;
+    let same_suffix = carried_len
+        + ast.token_tags[..carried]
+            .iter()
+            .zip(&ast.token_starts[..carried])
+            .rev()
+            .zip(
+                token_tags[..token_tags.len() - carried_len]
+                    .iter()
+                    .zip(&token_starts[..token_starts.len() - carried_len])
+                    .rev(),
+            )
+            .take_while(|&((old_tag, &old_start), (new_tag, &new_start))| {
+                old_start >= edit.end && old_tag == new_tag && moved(old_start) == new_start
+            })
+            .count();
+    let old_suffix = ast.token_tags.len() - same_suffix;
+    let token_growth = token_tags.len() as i64 - ast.token_tags.len() as i64;
+    let (first, region_start) = region_start(same_prefix);
+
+    // Blocks after the edit from which the old tokens, nodes and errors carry over. Errors
+    // a failed block found looking ahead are mixed in with those of later blocks, so
+    // blocks after a failed one do not carry over.
+    let resume_points = |after: usize| {
+        (after..blocks.len().min(recovered + 1)).filter_map(move |block| {
+            let start = block_boundary(ast, blocks, block)?;
+            (start.token as usize > old_suffix).then_some((block, start))
+        })
+    };
+
+    // Definitions resolve references anywhere in the document.
+    let defines = |tags: &[TokenTag]| {
+        tags.iter().any(|&tag| {
+            matches!(
+                tag,
+                TokenTag::LinkDefinition | TokenTag::FootnoteDefinitionStart
+            )
+        })
+    };
+    let new_suffix = (old_suffix as i64 + token_growth) as usize;
+    if defines(&ast.token_tags[same_prefix..old_suffix])
+        || defines(&token_tags[same_prefix..new_suffix])
+    {
+        return None;
+    }
+
+    let mut parser = Parser::new(source, token_tags, token_starts, options);
+    parser.nodes = ast.nodes[..region_start.nodes].to_vec();
+    parser.node_spans = ast.node_spans[..region_start.nodes].to_vec();
+    parser.extra_data = ast.extra_data[..region_start.extra_data].to_vec();
+    parser.errors = ast.errors[..region_start.errors].to_vec();
+    parser.token_index = region_start.token;
+    parser.collect_definitions();
+    let mut resume = resume_points(first + 1).next();
+    loop {
+        let stop = resume.map_or(ByteOffset::MAX, |(_, start)| moved(start.byte_offset));
+        parser.parse_top_level_blocks(stop);
+        let offset = parser.byte_offset_for_token(parser.token_index);
+        if resume.is_none() || offset == stop {
             break;
         }
+        // The new blocks run on past the resume point; take the next block after them.
+        resume = resume_points(resume?.0 + 1).find(|(_, start)| moved(start.byte_offset) >= offset);
     }
+    let region_nodes = Range {
+        start: region_start.nodes as u32,
+        end: parser.nodes.len() as u32,
+    };
+    let region_blocks = std::mem::take(&mut parser.scratch);
+
+    let (old_nodes, tail_blocks) = match resume {
+        Some((block, start)) => {
+            let shift = Shift {
+                first_token: old_suffix as TokenIndex,
+                tokens: token_growth,
+                nodes: parser.nodes.len() as i64 - start.nodes as i64,
+                extra: parser.extra_data.len() as i64 - start.extra_data as i64,
+            };
+            if parser.token_index != shift.token(start.token) {
+                return None;
+            }
+            // Recovery tokenizes the rest of the source again, which must agree with the
+            // old tokens for them to carry over.
+            if parser.nodes[region_start.nodes..]
+                .iter()
+                .any(|node| node.tag == NodeTag::Recovery)
+                && (parser.token_tags[new_suffix..] != ast.token_tags[old_suffix..]
+                    || parser.token_starts[new_suffix..]
+                        .iter()
+                        .zip(&ast.token_starts[old_suffix..])
+                        .any(|(&new, &old)| new != moved(old)))
+            {
+                return None;
+            }
 
-    // Phase 2: Parsing
-    let token_tags: Vec<TokenTag> = tokens.iter().map(|t| t.tag).collect();
-    let token_starts: Vec<ByteOffset> = tokens.iter().map(|t| t.loc.start).collect();
-
-    let mut parser = Parser {
-        source: source_owned.clone(),
-        token_tags: token_tags.clone(),
-        token_starts: token_starts.clone(),
-        token_index: 0,
-        nodes: Vec::new(),
-        extra_data: Vec::new(),
-        scratch: Vec::new(),
-        errors: Vec::new(),
+            let old_extra = &ast.extra_data[start.extra_data..document_children.start as usize];
+            // Entries no node owns are node lists left behind when a list was copied
+            // into a longer one, as list items do with their first line.
+            let mut slots = vec![Slot::Node; old_extra.len()];
+            let mut owned = Vec::new();
+            for node in &ast.nodes[start.nodes..document] {
+                owned.clear();
+                node_slots(&ast.extra_data, node, &mut owned)?;
+                for &(index, slot) in &owned {
+                    *slots.get_mut(index.checked_sub(start.extra_data)?)? = slot;
+                }
+            }
+            parser.extra_data.extend(
+                old_extra
+                    .iter()
+                    .zip(&slots)
+                    .map(|(&value, &slot)| shift.value(value, slot)),
+            );
+            parser.nodes.extend(
+                ast.nodes[start.nodes..document]
+                    .iter()
+                    .map(|node| shift.node(node)),
+            );
+            parser
+                .node_spans
+                .extend(
+                    ast.node_spans[start.nodes..document]
+                        .iter()
+                        .map(|span| Span {
+                            start: moved(span.start),
+                            end: moved(span.end),
+                        }),
+                );
+
+            // References from before the edit to definitions after it.
+            for index in 0..region_start.nodes {
+                let node = parser.nodes[index];
+                if let (NodeTag::Link | NodeTag::Image, NodeData::Extra(link)) =
+                    (node.tag, node.data)
+                {
+                    let url_token = &mut parser.extra_data[link as usize + 2];
+                    *url_token = shift.token(*url_token);
+                }
+            }
+
+            for err in &ast.errors[start.errors..] {
+                let byte_offset = moved(err.byte_offset);
+                // The new blocks may have looked ahead and reported this one already.
+                if !parser
+                    .errors
+                    .iter()
+                    .any(|new| new.tag == err.tag && new.byte_offset == byte_offset)
+                {
+                    parser.errors.push(Error {
+                        tag: err.tag,
+                        token: shift.token(err.token),
+                        byte_offset,
+                    });
+                }
+            }
+            let tail_blocks: Vec<NodeIndex> = blocks[block..]
+                .iter()
+                .map(|&node| shift.node_index(node))
+                .collect();
+            (
+                Range {
+                    start: region_start.nodes as u32,
+                    end: start.nodes as u32,
+                },
+                tail_blocks,
+            )
+        }
+        None => (
+            Range {
+                start: region_start.nodes as u32,
+                end: document as u32,
+            },
+            Vec::new(),
+        ),
     };
+    if parser.errors.len() >= MAX_PARSE_ERRORS {
+        return None;
+    }
 
-    let _ = parser.parse_document();
+    let mut children = blocks[..first].to_vec();
+    children.extend_from_slice(&region_blocks);
+    children.extend_from_slice(&tail_blocks);
+    parser.add_document_node(&children);
 
-    Ast {
-        source: source_owned,
+    let source_edits = ast
+        .source_edits
+        .iter()
+        .filter(|source_edit| {
+            source_edit.source.end <= edit.start || source_edit.source.start >= edit.end
+        })
+        .map(|source_edit| {
+            let mut source_edit = *source_edit;
+            if source_edit.source.start >= edit.end {
+                source_edit.original.start = moved(source_edit.original.start);
+                source_edit.original.end = moved(source_edit.original.end);
+                source_edit.source.start = moved(source_edit.source.start);
+                source_edit.source.end = moved(source_edit.source.end);
+            }
+            source_edit
+        })
+        .collect();
+    let custom_emoji = parser.used_custom_emoji();
+    let Parser {
         token_tags,
         token_starts,
-        nodes: parser.nodes,
-        extra_data: parser.extra_data,
-        errors: parser.errors,
+        nodes,
+        node_spans,
+        extra_data,
+        errors,
+        ..
+    } = parser;
+
+    Some(Reparse {
+        ast: Ast {
+            source: Cow::Owned(std::mem::take(source)),
+            token_tags,
+            token_starts,
+            nodes,
+            node_spans,
+            extra_data,
+            errors,
+            custom_emoji,
+            source_edits,
+            incomplete: Vec::new(),
+        },
+        old_nodes,
+        new_nodes: region_nodes,
+    })
+}
+
+/// Whether `text` ends inside something that is only recognized once it is closed and
+/// may span blank lines: an HTML comment, or a `$$` math block.
+pub(crate) fn may_open_multiline_construct(text: &str, options: &ParseOptions) -> bool {
+    let open_comment = text
+        .rfind("<!--")
+        .is_some_and(|open| !text[open..].contains("-->"));
+    let open_math =
+        options.math && text.lines().filter(|line| line.trim() == "$$").count() % 2 == 1;
+    open_comment || open_math
+}
+
+/// Parser state at the start of top-level block `block` of a parsed document, if the
+/// block can be parsed again on its own: it begins at the start of a line after a blank
+/// line, and its nodes and extra data directly follow those of the block before it.
+fn block_boundary(ast: &Ast, blocks: &[NodeIndex], block: usize) -> Option<BlockStart> {
+    let token = ast.nodes.get(*blocks.get(block)? as usize)?.main_token;
+    let offset = *ast.token_starts.get(token as usize)?;
+    let source = ast.source.as_bytes();
+    if block == 0
+        || token == 0
+        || ast.token_tags[token as usize - 1] != TokenTag::BlankLine
+        || offset < 2
+        || source[offset as usize - 2..offset as usize] != *b"\n\n"
+        || matches!(source.get(offset as usize), Some(b' ' | b'\t'))
+    {
+        return None;
+    }
+
+    let before = block_extent(ast, blocks[block - 1])?;
+    let this = block_extent(ast, blocks[block])?;
+    if before.nodes.end != this.nodes.start {
+        return None;
+    }
+    // Blocks without extra data, such as a thematic break, leave it where it was.
+    let mut extra_data = 0;
+    for &earlier in blocks[..block].iter().rev() {
+        if let Some(extra) = block_extent(ast, earlier)?.extra {
+            extra_data = extra.end;
+            break;
+        }
+    }
+    if this.extra.is_some_and(|extra| extra.start < extra_data) {
+        return None;
+    }
+    // Errors found looking ahead from an earlier block belong to that block.
+    let errors = ast
+        .errors
+        .iter()
+        .take_while(|err| err.token < token)
+        .count();
+    if ast.errors[errors..].iter().any(|err| err.token < token) {
+        return None;
+    }
+    Some(BlockStart {
+        byte_offset: offset,
+        token,
+        nodes: this.nodes.start,
+        extra_data,
+        errors,
+    })
+}
+
+/// The nodes and extra data a top-level block and everything in it occupy.
+struct BlockExtent {
+    nodes: std::ops::Range<usize>,
+    extra: Option<std::ops::Range<usize>>,
+}
+
+fn block_extent(ast: &Ast, block: NodeIndex) -> Option<BlockExtent> {
+    let mut nodes = block as usize..block as usize + 1;
+    let mut extra: Option<std::ops::Range<usize>> = None;
+    let mut slots = Vec::new();
+    let mut stack = vec![block];
+    while let Some(node_index) = stack.pop() {
+        let node = ast.nodes.get(node_index as usize)?;
+        nodes.start = nodes.start.min(node_index as usize);
+        nodes.end = nodes.end.max(node_index as usize + 1);
+        slots.clear();
+        node_slots(&ast.extra_data, node, &mut slots)?;
+        for &(index, _) in &slots {
+            extra = Some(match extra {
+                Some(range) => range.start.min(index)..range.end.max(index + 1),
+                None => index..index + 1,
+            });
+        }
+        stack.extend_from_slice(ast.children(node_index));
+        if matches!(node.tag, NodeTag::Link | NodeTag::Image) {
+            stack.extend_from_slice(ast.link_children(node_index));
+        }
     }
+    Some(BlockExtent { nodes, extra })
+}
+
+/// What an `extra_data` entry holds, and so how it moves when nodes are relocated.
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Literal,
+    Token,
+    Node,
+    Extra,
 }
 
-fn normalize_emoji_shortcodes(source: &str) -> String {
+/// Collect the `extra_data` entries `node` owns: its own record and the node lists and
+/// attributes it points to. `None` if they are out of bounds.
+fn node_slots(extra: &[u32], node: &Node, slots: &mut Vec<(usize, Slot)>) -> Option<()> {
+    let at = |index: usize| extra.get(index).copied();
+    let list = |slots: &mut Vec<(usize, Slot)>, start: u32, end: u32| -> Option<()> {
+        if end as usize > extra.len() || start > end {
+            return None;
+        }
+        slots.extend((start as usize..end as usize).map(|index| (index, Slot::Node)));
+        Some(())
+    };
+    // A record field holding an extra-data index; `0..0` marks a failed node and stays put.
+    let range = |slots: &mut Vec<(usize, Slot)>, index: usize| -> Option<(u32, u32)> {
+        let (start, end) = (at(index)?, at(index + 1)?);
+        let slot = if start == 0 && end == 0 {
+            Slot::Literal
+        } else {
+            Slot::Extra
+        };
+        slots.extend([(index, slot), (index + 1, slot)]);
+        Some((start, end))
+    };
+
+    let index = match node.data {
+        NodeData::Children(children) => {
+            if children.start != 0 || children.end != 0 {
+                list(slots, children.start, children.end)?;
+            }
+            return Some(());
+        }
+        NodeData::Extra(index) => index as usize,
+        NodeData::None | NodeData::Token(_) => return Some(()),
+    };
+    match node.tag {
+        NodeTag::Heading => {
+            slots.push((index, Slot::Literal));
+            let (start, end) = range(slots, index + 1)?;
+            list(slots, start, end)?;
+            let id_token = at(index + 3)?;
+            slots.push((index + 3, Slot::Token));
+            if id_token != u32::MAX {
+                // The `{#id}` expression split off the heading text leaves its record and
+                // the end of the child list behind.
+                let start = (start as usize).checked_sub(2)?;
+                slots.extend([
+                    (start, Slot::Token),
+                    (start + 1, Slot::Token),
+                    (end as usize, Slot::Node),
+                ]);
+            }
+        }
+        NodeTag::ListItem => {
+            slots.push((index, Slot::Literal));
+            let (start, end) = range(slots, index + 1)?;
+            list(slots, start, end)?;
+        }
+        NodeTag::MdxJsxElement | NodeTag::MdxJsxSelfClosing => {
+            slots.push((index, Slot::Token));
+            let (start, end) = range(slots, index + 1)?;
+            for attribute in (start as usize..end as usize).step_by(3) {
+                slots.extend([
+                    (attribute, Slot::Token),
+                    (attribute + 1, Slot::Token),
+                    (attribute + 2, Slot::Literal),
+                ]);
+            }
+            let (start, end) = range(slots, index + 3)?;
+            list(slots, start, end)?;
+        }
+        NodeTag::Link | NodeTag::Image => {
+            let (start, end) = range(slots, index)?;
+            list(slots, start, end)?;
+            slots.extend([(index + 2, Slot::Token), (index + 3, Slot::Literal)]);
+        }
+        NodeTag::Recovery | NodeTag::MdxTextExpression => {
+            slots.extend([(index, Slot::Token), (index + 1, Slot::Token)]);
+        }
+        NodeTag::Frontmatter => {
+            slots.extend([
+                (index, Slot::Literal),
+                (index + 1, Slot::Token),
+                (index + 2, Slot::Token),
+            ]);
+        }
+        NodeTag::Table => {
+            let (columns, rows) = (at(index)? as usize, at(index + 1)? as usize);
+            slots.extend((index..index + 2 + columns).map(|index| (index, Slot::Literal)));
+            let rows_start = index + 2 + columns;
+            slots.extend((rows_start..rows_start + rows).map(|index| (index, Slot::Node)));
+        }
+        _ => slots.push((index, Slot::Literal)),
+    }
+    slots
+        .iter()
+        .all(|&(index, _)| index < extra.len())
+        .then_some(())
+}
+
+/// How token, node and extra-data indices move for the nodes after an edit.
+struct Shift {
+    /// Tokens before this one, ahead of the edit, keep their index.
+    first_token: TokenIndex,
+    tokens: i64,
+    nodes: i64,
+    extra: i64,
+}
+
+impl Shift {
+    fn token(&self, token: TokenIndex) -> TokenIndex {
+        if token == u32::MAX || token < self.first_token {
+            token
+        } else {
+            (token as i64 + self.tokens) as TokenIndex
+        }
+    }
+
+    fn node_index(&self, node: NodeIndex) -> NodeIndex {
+        (node as i64 + self.nodes) as NodeIndex
+    }
+
+    fn extra_index(&self, index: u32) -> u32 {
+        (index as i64 + self.extra) as u32
+    }
+
+    fn value(&self, value: u32, slot: Slot) -> u32 {
+        match slot {
+            Slot::Literal => value,
+            Slot::Token => self.token(value),
+            Slot::Node => self.node_index(value),
+            Slot::Extra => self.extra_index(value),
+        }
+    }
+
+    fn node(&self, node: &Node) -> Node {
+        let data = match node.data {
+            NodeData::None => NodeData::None,
+            NodeData::Token(token) => NodeData::Token(self.token(token)),
+            NodeData::Children(range) if range.start == 0 && range.end == 0 => {
+                NodeData::Children(range)
+            }
+            NodeData::Children(range) => NodeData::Children(Range {
+                start: self.extra_index(range.start),
+                end: self.extra_index(range.end),
+            }),
+            NodeData::Extra(index) => NodeData::Extra(self.extra_index(index)),
+        };
+        Node {
+            tag: node.tag,
+            main_token: self.token(node.main_token),
+            data,
+        }
+    }
+}
+
+/// Find where top-level parsing should resume after the block starting at
+/// `block_start` failed: the line after the next blank line, or the next line
+/// that opens a Markdown block, whichever comes first.
+fn find_resync_offset(source: &[u8], block_start: usize) -> usize {
+    let mut line_start = next_line_start(source, block_start);
+
+    while line_start < source.len() {
+        let line_end = next_line_start(source, line_start);
+        let line = &source[line_start..line_end];
+
+        if line
+            .iter()
+            .all(|&b| b == b' ' || b == b'\t' || b == b'\r' || b == b'\n')
+        {
+            return line_end;
+        }
+        if starts_top_level_block(line) {
+            return line_start;
+        }
+
+        line_start = line_end;
+    }
+
+    source.len()
+}
+
+fn next_line_start(source: &[u8], from: usize) -> usize {
+    source[from.min(source.len())..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|pos| from + pos + 1)
+        .unwrap_or(source.len())
+}
+
+/// Mirror of the tokenizer's start-of-line block markers, used only for resynchronization.
+fn starts_top_level_block(line: &[u8]) -> bool {
+    let at = |i: usize| line.get(i).copied().unwrap_or(0);
+
+    match at(0) {
+        b'#' => matches!(at(1), b' ' | b'#' | b'\n' | 0),
+        b'>' | b'|' => true,
+        b'`' => line.starts_with(b"```"),
+        b'~' => line.starts_with(b"~~~"),
+        b'-' | b'*' | b'_' => {
+            let content = line.trim_ascii_end();
+            (content.len() >= 3 && content.iter().all(|&b| b == at(0)))
+                || (at(0) != b'_' && at(1) == b' ')
+        }
+        b'0'..=b'9' => {
+            let digits = line.iter().take_while(|b| b.is_ascii_digit()).count();
+            at(digits) == b'.' && at(digits + 1) == b' '
+        }
+        b'i' | b'e' => line.starts_with(b"import ") || line.starts_with(b"export "),
+        _ => false,
+    }
+}
+
+/// Replace known shortcodes with their emoji, recording each replacement so offsets can be
+/// mapped back to `source`.
+pub(crate) fn normalize_emoji_shortcodes(
+    source: &str,
+    custom_emoji: &HashMap<String, String>,
+) -> (String, Vec<SourceEdit>) {
     let mut output = String::with_capacity(source.len());
+    let mut edits = Vec::new();
     let mut index: usize = 0;
     let bytes = source.as_bytes();
 
     while index < source.len() {
         if bytes[index] == b':' {
-            if let Some((shortcode, end_index)) = parse_shortcode(source, index) {
-                if let Some(emoji) = shortcode_to_emoji(shortcode) {
+            if let Some((shortcode, end_index)) = parse_shortcode(bytes, index) {
+                if let Some(emoji) = crate::emoji::lookup(shortcode)
+                    && !custom_emoji.contains_key(shortcode)
+                {
+                    let replacement_start = output.len() as ByteOffset;
                     output.push_str(emoji);
+                    edits.push(SourceEdit {
+                        original: Span {
+                            start: index as ByteOffset,
+                            end: end_index as ByteOffset,
+                        },
+                        source: Span {
+                            start: replacement_start,
+                            end: output.len() as ByteOffset,
+                        },
+                    });
                     index = end_index;
                     continue;
                 }
@@ -106,11 +1122,11 @@ fn normalize_emoji_shortcodes(source: &str) -> String {
         index += ch.len_utf8();
     }
 
-    output
+    (output, edits)
 }
 
-fn parse_shortcode(source: &str, start: usize) -> Option<(&str, usize)> {
-    let bytes = source.as_bytes();
+/// The shortcode of a `:name:` starting at `start`, and the offset after its closing colon.
+pub(crate) fn parse_shortcode(bytes: &[u8], start: usize) -> Option<(&str, usize)> {
     let mut index = start + 1;
 
     while index < bytes.len() {
@@ -126,36 +1142,61 @@ fn parse_shortcode(source: &str, start: usize) -> Option<(&str, usize)> {
         return None;
     }
 
-    Some((&source[start + 1..index], index + 1))
+    // Shortcodes are ASCII, so this slice is always valid UTF-8.
+    let shortcode = std::str::from_utf8(&bytes[start + 1..index]).ok()?;
+    Some((shortcode, index + 1))
 }
 
-fn shortcode_to_emoji(shortcode: &str) -> Option<&'static str> {
-    match shortcode {
-        "thumbsup" | "+1" => Some("👍"),
-        "thumbsdown" | "-1" => Some("👎"),
-        "wave" => Some("👋"),
-        "fire" => Some("🔥"),
-        "rocket" => Some("🚀"),
-        "sparkles" => Some("✨"),
-        "tada" => Some("🎉"),
-        "smile" => Some("😄"),
-        "heart" => Some("❤️"),
-        "white_check_mark" => Some("✅"),
-        "x" => Some("❌"),
-        "warning" => Some("⚠️"),
-        "thinking" => Some("🤔"),
-        "clap" => Some("👏"),
-        "eyes" => Some("👀"),
-        "point_up" => Some("☝️"),
-        "point_right" => Some("👉"),
-        "point_left" => Some("👈"),
-        "point_down" => Some("👇"),
-        "100" => Some("💯"),
-        _ => None,
+impl<'src> Parser<'src> {
+    fn new(
+        source: &'src str,
+        token_tags: Vec<TokenTag>,
+        token_starts: Vec<ByteOffset>,
+        options: &'src ParseOptions,
+    ) -> Parser<'src> {
+        Parser {
+            source,
+            token_tags,
+            token_starts,
+            token_index: 0,
+            nodes: Vec::new(),
+            node_spans: Vec::new(),
+            extra_data: Vec::new(),
+            scratch: Vec::new(),
+            errors: Vec::new(),
+            definitions: HashMap::new(),
+            footnote_labels: HashSet::new(),
+            link_label_ends: Vec::new(),
+            link_pairs: Vec::new(),
+            link_pairs_start: 0,
+            jsx_depth: 0,
+            options,
+            partial: false,
+            incomplete: Vec::new(),
+            pending_references: 0,
+            blocks: Vec::new(),
+            replay: None,
+        }
+    }
+
+    /// The caller's custom emoji that the document actually uses.
+    fn used_custom_emoji(&self) -> HashMap<String, String> {
+        let mut used = HashMap::new();
+        for (idx, &tag) in self.token_tags.iter().enumerate() {
+            if tag != TokenTag::CustomEmoji {
+                continue;
+            }
+            let start = self.token_starts[idx] as usize;
+            let Some((shortcode, _)) = parse_shortcode(self.source.as_bytes(), start) else {
+                continue;
+            };
+            if let Some(url) = self.options.custom_emoji.get(shortcode) {
+                used.insert(shortcode.to_string(), url.clone());
+            }
+        }
+        used
     }
-}
 
-impl Parser {
     // === Token consumption methods ===
 
     fn eat_token(&mut self, tag: TokenTag) -> Option<TokenIndex> {
@@ -200,12 +1241,16 @@ impl Parser {
 
     fn add_node(&mut self, node: Node) -> NodeIndex {
         let index = self.nodes.len() as NodeIndex;
+        self.node_spans
+            .push(self.node_span_to_here(node.main_token));
         self.nodes.push(node);
         index
     }
 
     fn reserve_node(&mut self, tag: NodeTag) -> NodeIndex {
         let index = self.nodes.len() as NodeIndex;
+        let start = self.byte_offset_for_token(self.token_index);
+        self.node_spans.push(Span { start, end: start });
         self.nodes.push(Node {
             tag,
             main_token: 0,
@@ -215,10 +1260,61 @@ impl Parser {
     }
 
     fn set_node(&mut self, index: NodeIndex, node: Node) -> NodeIndex {
+        let mut span = self.node_span_to_here(node.main_token);
+        // Trailing children such as the text of a whitespace-only last line lie past the
+        // trimmed end; the span still has to hold them.
+        if let NodeData::Children(children) = node.data
+            && children.end > children.start
+        {
+            let last = self.extra_data[children.end as usize - 1];
+            span.end = span.end.max(self.node_spans[last as usize].end);
+        }
+        self.node_spans[index as usize] = span;
         self.nodes[index as usize] = node;
         index
     }
 
+    /// Add a node made of `token` alone, for a token the parser has already moved past.
+    fn add_token_node(&mut self, tag: NodeTag, token: TokenIndex) -> NodeIndex {
+        let index = self.add_node(Node {
+            tag,
+            main_token: token,
+            data: NodeData::None,
+        });
+        self.node_spans[index as usize] = Span {
+            start: self.byte_offset_for_token(token),
+            end: self.byte_offset_for_token(token + 1),
+        };
+        index
+    }
+
+    /// The span of a node that starts at `main_token` and has just been parsed: it ends
+    /// with the last token consumed, closing delimiters included, leaving out the line
+    /// endings and indentation consumed after it.
+    fn node_span_to_here(&self, main_token: TokenIndex) -> Span {
+        let start = self.byte_offset_for_token(main_token);
+        let mut end_token = self.token_index as usize;
+        while end_token > main_token as usize + 1
+            && matches!(
+                self.token_tags.get(end_token - 1),
+                Some(
+                    TokenTag::Newline
+                        | TokenTag::BlankLine
+                        | TokenTag::Space
+                        | TokenTag::Indent
+                        | TokenTag::Eof
+                )
+            )
+        {
+            end_token -= 1;
+        }
+        let end = self.byte_offset_for_token(end_token as TokenIndex);
+        Span {
+            start,
+            end: end.max(start),
+        }
+    }
+
     // === Extra data methods ===
 
     fn add_extra_heading(&mut self, heading: &Heading) -> u32 {
@@ -226,6 +1322,7 @@ impl Parser {
         self.extra_data.push(heading.level as u32);
         self.extra_data.push(heading.children_start);
         self.extra_data.push(heading.children_end);
+        self.extra_data.push(heading.id_token.unwrap_or(u32::MAX));
         start
     }
 
@@ -256,6 +1353,7 @@ impl Parser {
         self.extra_data.push(link.children_start);
         self.extra_data.push(link.children_end);
         self.extra_data.push(link.url_token);
+        self.extra_data.push(link.reference as u32);
         start
     }
 
@@ -301,11 +1399,16 @@ impl Parser {
         children: Vec<NodeIndex>,
     ) -> NodeIndex {
         let span = self.list_to_span(&children);
-        self.add_node(Node {
+        let paragraph = self.add_node(Node {
             tag: NodeTag::Paragraph,
             main_token,
             data: NodeData::Children(span),
-        })
+        });
+        // The main token may be the marker of the enclosing list item or blockquote.
+        if let Some(&first) = children.first() {
+            self.node_spans[paragraph as usize].start = self.node_spans[first as usize].start;
+        }
+        paragraph
     }
 
     // === Error handling ===
@@ -319,6 +1422,14 @@ impl Parser {
             return;
         }
         let byte_offset = self.byte_offset_for_token(token);
+        // Blocks re-parsed after recovery can hit the same problem twice; report it once.
+        if self
+            .errors
+            .iter()
+            .any(|err| err.tag == tag && err.byte_offset == byte_offset)
+        {
+            return;
+        }
         self.errors.push(Error {
             tag,
             token,
@@ -334,20 +1445,80 @@ impl Parser {
         }
     }
 
+    /// Whether a partial document ends at the current token, so whatever is still open
+    /// there may yet be closed by input that has not arrived.
+    fn at_partial_end(&self) -> bool {
+        self.partial && self.current_tag() == TokenTag::Eof
+    }
+
+    /// Expect `tag` unless a partial document ends first; returns whether it was there.
+    fn expect_unless_partial_end(&mut self, tag: TokenTag) -> PResult<bool> {
+        if self.at_partial_end() {
+            return Ok(false);
+        }
+        self.expect_token(tag).map(|_| true)
+    }
+
+    /// Record `node` as auto-closed when its closing `end_tag` was cut off by the end of a
+    /// partial document.
+    fn note_unclosed(&mut self, node: NodeIndex, end_tag: TokenTag) {
+        if self.partial && self.token_tags[self.token_index as usize - 1] != end_tag {
+            self.incomplete.push(node);
+        }
+    }
+
     // === Parsing methods ===
 
-    fn parse_document(&mut self) -> PResult<NodeIndex> {
+    /// Parse the top-level blocks from the current token on. The document lists the
+    /// `finalized` blocks of a streaming parse before them.
+    fn parse_document(&mut self, finalized: &[NodeIndex]) -> PResult<NodeIndex> {
         let scratch_top = self.scratch.len();
+        self.scratch.extend_from_slice(finalized);
 
-        // Check for YAML frontmatter
-        if let Some(fm_start) = self.eat_token(TokenTag::FrontmatterStart) {
-            if let Ok(fm_node) = self.parse_yaml_frontmatter(fm_start) {
-                self.scratch.push(fm_node);
-            }
-        } else if self.is_json_frontmatter() {
-            // Check for JSON frontmatter (```hnmd ... ```)
-            if let Ok(fm_node) = self.parse_json_frontmatter() {
+        self.parse_top_level_blocks(ByteOffset::MAX);
+
+        let children: Vec<NodeIndex> = self.scratch[scratch_top..].to_vec();
+        self.scratch.truncate(scratch_top);
+        Ok(self.add_document_node(&children))
+    }
+
+    /// Add the document node, which covers the whole source.
+    fn add_document_node(&mut self, children: &[NodeIndex]) -> NodeIndex {
+        let children_span = self.list_to_span(children);
+        let document = self.add_node(Node {
+            tag: NodeTag::Document,
+            main_token: 0,
+            data: NodeData::Children(children_span),
+        });
+        self.node_spans[document as usize] = Span {
+            start: 0,
+            end: self.source.len() as ByteOffset,
+        };
+        document
+    }
+
+    /// Parse top-level blocks onto `scratch` until the end of the input, or until the
+    /// next block would start at or after the byte offset `stop`.
+    fn parse_top_level_blocks(&mut self, stop: ByteOffset) {
+        if self.replay.is_none() {
+            let start = self.byte_offset_for_token(self.token_index);
+            let tokenizer = Tokenizer::starting_at(self.source, start).with_options(self.options);
+            self.replay = Some((tokenizer, self.token_index));
+        }
+        if self.token_index == 0 {
+            let start = self.block_start();
+            // Check for YAML frontmatter
+            let frontmatter = if let Some(fm_start) = self.eat_token(TokenTag::FrontmatterStart) {
+                self.parse_yaml_frontmatter(fm_start).ok()
+            } else if self.is_json_frontmatter() {
+                // Check for JSON frontmatter (```hnmd ... ```)
+                self.parse_json_frontmatter().ok()
+            } else {
+                None
+            };
+            if let Some(fm_node) = frontmatter {
                 self.scratch.push(fm_node);
+                self.push_block(fm_node, start, 0);
             }
         }
 
@@ -360,19 +1531,41 @@ impl Parser {
                 self.token_index += 1;
             }
 
-            if self.current_tag() == TokenTag::Eof {
+            if self.current_tag() == TokenTag::Eof
+                || self.byte_offset_for_token(self.token_index) >= stop
+            {
                 break;
             }
 
             let before = self.token_index;
+            let start = self.block_start();
+            let pending_before = self.pending_references;
+            let block_scratch_top = self.scratch.len();
             match self.parse_block() {
                 Ok(block) => {
                     self.scratch.push(block);
+                    self.push_block(block, start, pending_before);
                 }
                 Err(_) => {
-                    // Stop after the first parse failure; callers can fall back to
-                    // plain-text rendering when `ast.errors` is non-empty.
-                    break;
+                    // Keep the failed span as a recovery node and carry on with the
+                    // next block; the error itself is already in `self.errors`. Nodes
+                    // the block built before failing are dropped.
+                    self.jsx_depth = 0;
+                    self.scratch.truncate(block_scratch_top);
+                    self.nodes.truncate(start.nodes);
+                    self.node_spans.truncate(start.nodes);
+                    self.extra_data.truncate(start.extra_data);
+                    self.incomplete
+                        .retain(|&node| (node as usize) < start.nodes);
+                    let recovery = self.recover_block(before, start.errors);
+                    if self.partial && self.current_tag() == TokenTag::Eof {
+                        // The block may only be cut short; more input can still complete it.
+                        self.errors.truncate(start.errors);
+                        self.incomplete.push(recovery);
+                    }
+                    self.scratch.push(recovery);
+                    self.push_block(recovery, start, pending_before);
+                    continue;
                 }
             }
             // Keep forward-progress guard for pathological inputs.
@@ -381,16 +1574,128 @@ impl Parser {
                 self.token_index += 1;
             }
         }
+    }
 
-        let children: Vec<NodeIndex> = self.scratch[scratch_top..].to_vec();
-        self.scratch.truncate(scratch_top);
-        let children_span = self.list_to_span(&children);
+    fn block_start(&self) -> BlockStart {
+        BlockStart {
+            byte_offset: self.byte_offset_for_token(self.token_index),
+            token: self.token_index,
+            nodes: self.nodes.len(),
+            extra_data: self.extra_data.len(),
+            errors: self.errors.len(),
+        }
+    }
 
-        Ok(self.add_node(Node {
-            tag: NodeTag::Document,
-            main_token: 0,
-            data: NodeData::Children(children_span),
-        }))
+    fn push_block(&mut self, node: NodeIndex, start: BlockStart, pending_before: u32) {
+        let settled = self.errors.len() == start.errors
+            && self.pending_references == pending_before
+            && self.nodes[node as usize].tag != NodeTag::Recovery;
+        self.blocks.push(TopLevelBlock {
+            node,
+            start,
+            settled,
+        });
+    }
+
+    /// Skip a top-level block that failed to parse and resynchronize at the next blank
+    /// line or block start. The tail of the token stream is re-tokenized from that point,
+    /// so tokenizer state left behind by the failure (an unclosed `{`, a dangling `**`)
+    /// cannot leak into later blocks.
+    fn recover_block(&mut self, start_token: TokenIndex, errors_before: usize) -> NodeIndex {
+        let block_start = self.byte_offset_for_token(start_token) as usize;
+        let resync = find_resync_offset(self.source.as_bytes(), block_start);
+        let resync_token =
+            self.token_starts
+                .partition_point(|&start| (start as usize) < resync) as TokenIndex;
+
+        if resync < self.source.len() {
+            self.link_pairs.clear();
+            self.retokenize_from(resync as ByteOffset, resync_token);
+
+            for i in errors_before..self.errors.len() {
+                let offset = self.errors[i].byte_offset;
+                if offset as usize >= resync {
+                    let token = self.token_starts.partition_point(|&start| start <= offset);
+                    self.errors[i].token = token.saturating_sub(1) as TokenIndex;
+                }
+            }
+        }
+
+        self.token_index = resync_token;
+        let range_index = self.add_extra_range(&Range {
+            start: start_token,
+            end: resync_token,
+        });
+
+        let node = self.add_node(Node {
+            tag: NodeTag::Recovery,
+            main_token: start_token,
+            data: NodeData::Extra(range_index),
+        });
+        // A skipped token may run over line breaks, as the text of an unclosed `{` does;
+        // end the span where the skipped source does, before them.
+        let span = &mut self.node_spans[node as usize];
+        let skipped = self.source[span.start as usize..span.end as usize].trim_end();
+        span.end = span.start + skipped.len() as ByteOffset;
+        node
+    }
+
+    /// Tokenize the source again from the line at `offset`, replacing the tokens from
+    /// `token` on. Once the fresh tokenizer reaches the state the replay is in at the
+    /// same offset, the old tokens from there on are kept, so only the stretch that the
+    /// failed block's state reached is tokenized again.
+    fn retokenize_from(&mut self, offset: ByteOffset, token: TokenIndex) {
+        let mut fresh = Tokenizer::starting_at(self.source, offset).with_options(self.options);
+        let mut replay = self.replay.take();
+        let (mut token_tags, mut token_starts) = (Vec::new(), Vec::new());
+        let mut line = offset;
+        let old_end = loop {
+            if let Some((old, old_token)) = &mut replay {
+                if !self.replay_to(old, old_token, fresh.offset()) {
+                    replay = None;
+                } else if (*old_token as usize) < self.token_tags.len()
+                    && old.tokenizes_like(&fresh, line)
+                {
+                    break *old_token;
+                }
+            }
+            let before = fresh.offset() as usize;
+            let tok = fresh.next();
+            let read = &self.source.as_bytes()[before..fresh.offset() as usize];
+            if let Some(newline) = read.iter().rposition(|&b| b == b'\n') {
+                line = (before + newline + 1) as ByteOffset;
+            }
+            token_tags.push(tok.tag);
+            token_starts.push(tok.loc.start);
+            if tok.tag == TokenTag::Eof {
+                break self.token_tags.len() as TokenIndex;
+            }
+        };
+
+        self.token_tags
+            .splice(token as usize..old_end as usize, token_tags);
+        self.token_starts
+            .splice(token as usize..old_end as usize, token_starts);
+        // The old tokens kept are the ones the fresh tokenizer would go on to produce, so
+        // one starting over at `offset` replays all the tokens from `token` on.
+        let replay = Tokenizer::starting_at(self.source, offset).with_options(self.options);
+        self.replay = Some((replay, token));
+    }
+
+    /// Run `replay` on to `offset`, checking that it reproduces the tokens from `next`
+    /// on. Returns false if it does not, when its state says nothing about them.
+    fn replay_to(&self, replay: &mut Tokenizer<'src>, next: &mut TokenIndex, offset: u32) -> bool {
+        while replay.offset() < offset {
+            let tok = replay.next();
+            let index = *next as usize;
+            if self.token_tags.get(index) != Some(&tok.tag)
+                || self.token_starts[index] != tok.loc.start
+            {
+                return false;
+            }
+            *next += 1;
+        }
+        true
     }
 
     fn parse_yaml_frontmatter(&mut self, start_token: TokenIndex) -> PResult<NodeIndex> {
@@ -405,20 +1710,24 @@ impl Parser {
         let content_end = self.token_index;
 
         // Expect closing ---
-        if self.current_tag() != TokenTag::Hr {
+        let closed = self.eat_token(TokenTag::Hr).is_some();
+        if !closed && !self.at_partial_end() {
             self.warn(ErrorTag::UnclosedFrontmatter);
             return Err(ParseError::ParseError);
         }
-        self.next_token(); // consume hr
 
         let extra_index =
             self.add_extra_frontmatter(FrontmatterFormat::Yaml, content_start, content_end);
 
-        Ok(self.add_node(Node {
+        let node = self.add_node(Node {
             tag: NodeTag::Frontmatter,
             main_token: start_token,
             data: NodeData::Extra(extra_index),
-        }))
+        });
+        if !closed {
+            self.incomplete.push(node);
+        }
+        Ok(node)
     }
 
     fn is_json_frontmatter(&self) -> bool {
@@ -450,35 +1759,215 @@ impl Parser {
         let content_end = self.token_index;
 
         // Expect closing ```
-        if self.current_tag() != TokenTag::CodeFenceEnd {
+        let closed = self.eat_token(TokenTag::CodeFenceEnd).is_some();
+        if !closed && !self.at_partial_end() {
             self.warn(ErrorTag::UnclosedFrontmatter);
             return Err(ParseError::ParseError);
         }
-        self.next_token(); // consume CodeFenceEnd
 
         let extra_index =
             self.add_extra_frontmatter(FrontmatterFormat::Json, content_start, content_end);
 
-        Ok(self.add_node(Node {
+        let node = self.add_node(Node {
             tag: NodeTag::Frontmatter,
             main_token: start_token,
             data: NodeData::Extra(extra_index),
-        }))
+        });
+        if !closed {
+            self.incomplete.push(node);
+        }
+        Ok(node)
     }
 
     fn parse_block(&mut self) -> PResult<NodeIndex> {
         match self.current_tag() {
+            TokenTag::Indent if Self::is_block_start(self.peek_token(1)) => {
+                self.next_token();
+                self.parse_block()
+            }
             TokenTag::HeadingStart => self.parse_heading(),
             TokenTag::CodeFenceStart => self.parse_code_block(),
+            TokenTag::CodeIndented => self.parse_indented_code(),
+            TokenTag::MathBlock => self.parse_math(NodeTag::MathBlock),
             TokenTag::Hr => self.parse_hr(),
             TokenTag::BlockquoteStart => self.parse_blockquote(),
             TokenTag::ListItemUnordered | TokenTag::ListItemOrdered => self.parse_list(),
             TokenTag::Pipe => self.parse_table(),
             TokenTag::JsxTagStart => self.parse_jsx_element(),
+            TokenTag::EsmImport | TokenTag::EsmExport => self.parse_esm(),
+            TokenTag::LinkDefinition => self.parse_definition(),
+            TokenTag::FootnoteDefinitionStart => self.parse_footnote_definition(),
+            // A comment alone on its line is a block of its own.
+            TokenTag::HtmlComment
+                if matches!(
+                    self.peek_token(1),
+                    TokenTag::Newline | TokenTag::BlankLine | TokenTag::Eof
+                ) =>
+            {
+                self.parse_html_comment()
+            }
             _ => self.parse_paragraph(),
         }
     }
 
+    /// Tokens that open a block when they start a line, and so end an open paragraph.
+    fn is_block_start(tag: TokenTag) -> bool {
+        matches!(
+            tag,
+            TokenTag::HeadingStart
+                | TokenTag::CodeFenceStart
+                | TokenTag::CodeIndented
+                | TokenTag::MathBlock
+                | TokenTag::Hr
+                | TokenTag::BlockquoteStart
+                | TokenTag::ListItemUnordered
+                | TokenTag::ListItemOrdered
+                | TokenTag::Pipe
+                | TokenTag::LinkDefinition
+                | TokenTag::FootnoteDefinitionStart
+        )
+    }
+
+    /// Whether the line following the current newline opens a new block.
+    fn next_line_starts_block(&self) -> bool {
+        let offset = if self.peek_token(1) == TokenTag::Indent {
+            2
+        } else {
+            1
+        };
+        Self::is_block_start(self.peek_token(offset))
+            && self.may_interrupt_paragraph(self.token_index + offset)
+    }
+
+    /// Whether a block opening at `token` may interrupt a paragraph. Of ordered lists,
+    /// only one starting at 1 may; other markers continue the paragraph as text.
+    fn may_interrupt_paragraph(&self, token: TokenIndex) -> bool {
+        if self.token_tags.get(token as usize) != Some(&TokenTag::ListItemOrdered) {
+            return true;
+        }
+        let marker = self.token_slice(token).trim_end();
+        marker.trim_end_matches('.').parse::<u32>() == Ok(1)
+    }
+
+    /// Column of `token` on its line: the width of the indentation in front of it, or of
+    /// the list markers before it when it opens a block on a list item's first line.
+    fn token_column(&self, token: TokenIndex) -> u32 {
+        let start = self.token_starts[token as usize] as usize;
+        let line_start = self.source[..start].rfind('\n').map_or(0, |i| i + 1);
+        (start - line_start) as u32
+    }
+
+    /// Consume the indentation of a line that continues a block opened at column `indent`
+    /// with `tag`. Leaves the position untouched when the line does not continue it.
+    fn eat_indented(&mut self, indent: u32, tag: TokenTag) -> bool {
+        if indent == 0 {
+            return self.current_tag() == tag;
+        }
+        if self.current_tag() == TokenTag::Indent
+            && self.peek_token(1) == tag
+            && self.token_slice(self.token_index).len() as u32 == indent
+        {
+            self.next_token();
+            return true;
+        }
+        false
+    }
+
+    fn parse_esm(&mut self) -> PResult<NodeIndex> {
+        let tag = if self.current_tag() == TokenTag::EsmImport {
+            NodeTag::MdxEsmImport
+        } else {
+            NodeTag::MdxEsmExport
+        };
+        let esm_token = self.next_token();
+        Ok(self.add_node(Node {
+            tag,
+            main_token: esm_token,
+            data: NodeData::None,
+        }))
+    }
+
+    fn parse_definition(&mut self) -> PResult<NodeIndex> {
+        let definition_token = self.next_token();
+        Ok(self.add_node(Node {
+            tag: NodeTag::Definition,
+            main_token: definition_token,
+            data: NodeData::None,
+        }))
+    }
+
+    /// Index every link reference and footnote definition up front, so references may
+    /// precede them.
+    fn collect_definitions(&mut self) {
+        for (token, &tag) in self.token_tags.iter().enumerate() {
+            match tag {
+                TokenTag::LinkDefinition => {
+                    let text = self.token_slice(token as TokenIndex);
+                    if let Some(def) = crate::semantic::split_link_definition(text) {
+                        self.definitions
+                            .entry(crate::semantic::normalize_link_label(def.label))
+                            .or_insert(token as TokenIndex);
+                    }
+                }
+                TokenTag::FootnoteDefinitionStart => {
+                    let text = self.token_slice(token as TokenIndex);
+                    let label = crate::semantic::footnote_token_label(text);
+                    self.footnote_labels
+                        .insert(crate::semantic::normalize_link_label(label));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_footnote_definition(&mut self) -> PResult<NodeIndex> {
+        let indent = self.token_column(self.token_index);
+        let start_token = self.next_token(); // [^label]:
+        let node_index = self.reserve_node(NodeTag::FootnoteDefinition);
+
+        let children_span = match self.parse_list_item_children(start_token, indent, false) {
+            Ok(span) => span,
+            Err(e) => {
+                self.set_node(
+                    node_index,
+                    Node {
+                        tag: NodeTag::FootnoteDefinition,
+                        main_token: start_token,
+                        data: NodeData::Children(Range { start: 0, end: 0 }),
+                    },
+                );
+                return Err(e);
+            }
+        };
+
+        Ok(self.set_node(
+            node_index,
+            Node {
+                tag: NodeTag::FootnoteDefinition,
+                main_token: start_token,
+                data: NodeData::Children(children_span),
+            },
+        ))
+    }
+
+    fn parse_footnote_reference(&mut self) -> PResult<NodeIndex> {
+        let label = crate::semantic::footnote_token_label(self.token_slice(self.token_index));
+        if !self
+            .footnote_labels
+            .contains(&crate::semantic::normalize_link_label(label))
+        {
+            // A reference without a definition is plain text.
+            self.pending_references += 1;
+            return self.parse_text();
+        }
+        let reference_token = self.next_token();
+        Ok(self.add_node(Node {
+            tag: NodeTag::FootnoteReference,
+            main_token: reference_token,
+            data: NodeData::None,
+        }))
+    }
+
     fn parse_heading(&mut self) -> PResult<NodeIndex> {
         let heading_token = self.next_token();
 
@@ -495,6 +1984,7 @@ impl Parser {
                     level,
                     children_start: 0,
                     children_end: 0,
+                    id_token: None,
                 });
                 self.set_node(
                     node_index,
@@ -508,10 +1998,12 @@ impl Parser {
             }
         };
 
+        let (children_span, id_token) = self.take_heading_id(children_span);
         let heading_index = self.add_extra_heading(&Heading {
             level,
             children_start: children_span.start,
             children_end: children_span.end,
+            id_token,
         });
 
         Ok(self.set_node(
@@ -524,6 +2016,56 @@ impl Parser {
         ))
     }
 
+    /// Split a trailing `{#id}` attribute off a heading's children. It was parsed as the
+    /// last inline node, an expression, which is dropped again; the heading keeps the
+    /// token holding `#id`.
+    fn take_heading_id(&mut self, children: Range) -> (Range, Option<TokenIndex>) {
+        let Some(&last) = self.extra_data[children.start as usize..children.end as usize].last()
+        else {
+            return (children, None);
+        };
+        let node = self.nodes[last as usize];
+        let NodeData::Extra(range_index) = node.data else {
+            return (children, None);
+        };
+        if node.tag != NodeTag::MdxTextExpression
+            || last as usize != self.nodes.len() - 1
+            || self.incomplete.contains(&last)
+        {
+            return (children, None);
+        }
+        let content_start = self.extra_data[range_index as usize];
+        let content_end = self.extra_data[range_index as usize + 1];
+        if content_end != content_start + 1
+            || self.token_tags[content_start as usize] != TokenTag::Text
+            || crate::semantic::heading_id_attribute(self.token_slice(content_start)).is_none()
+        {
+            return (children, None);
+        }
+
+        self.nodes.pop();
+        self.node_spans.pop();
+        let mut children = Range {
+            start: children.start,
+            end: children.end - 1,
+        };
+
+        // The whitespace between the text and the attribute is not part of the text.
+        if children.end > children.start {
+            let last = self.extra_data[children.end as usize - 1];
+            let node = self.nodes[last as usize];
+            if node.tag == NodeTag::Text
+                && last as usize == self.nodes.len() - 1
+                && self.token_slice(node.main_token).trim().is_empty()
+            {
+                self.nodes.pop();
+                self.node_spans.pop();
+                children.end -= 1;
+            }
+        }
+        (children, Some(content_start))
+    }
+
     fn parse_paragraph(&mut self) -> PResult<NodeIndex> {
         let start_token = self.token_index;
         let node_index = self.reserve_node(NodeTag::Paragraph);
@@ -543,6 +2085,29 @@ impl Parser {
             }
         };
 
+        if let Some(level) = self.setext_underline_level(self.token_index) {
+            if self.current_tag() == TokenTag::Indent {
+                self.next_token();
+            }
+            self.next_token(); // underline
+            self.eat_token(TokenTag::Newline);
+            let (children_span, id_token) = self.take_heading_id(children_span);
+            let heading_index = self.add_extra_heading(&Heading {
+                level,
+                children_start: children_span.start,
+                children_end: children_span.end,
+                id_token,
+            });
+            return Ok(self.set_node(
+                node_index,
+                Node {
+                    tag: NodeTag::Heading,
+                    main_token: start_token,
+                    data: NodeData::Extra(heading_index),
+                },
+            ));
+        }
+
         Ok(self.set_node(
             node_index,
             Node {
@@ -553,16 +2118,64 @@ impl Parser {
         ))
     }
 
+    /// Heading level of a setext underline (`===` for 1, `---` for 2) on the line starting
+    /// at `token`, which turns the paragraph above it into a heading.
+    fn setext_underline_level(&self, token: TokenIndex) -> Option<u8> {
+        let tag_at = |index: TokenIndex| {
+            self.token_tags
+                .get(index as usize)
+                .copied()
+                .unwrap_or(TokenTag::Eof)
+        };
+
+        let mut token = token;
+        if tag_at(token) == TokenTag::Indent {
+            if self.token_slice(token).len() > 3 {
+                return None;
+            }
+            token += 1;
+        }
+        if !matches!(tag_at(token), TokenTag::Text | TokenTag::Hr)
+            || !matches!(
+                tag_at(token + 1),
+                TokenTag::Newline | TokenTag::BlankLine | TokenTag::Eof
+            )
+        {
+            return None;
+        }
+        let underline = self.token_slice(token).trim_end();
+        if !underline.is_empty() && underline.bytes().all(|b| b == b'=') {
+            Some(1)
+        } else if !underline.is_empty() && underline.bytes().all(|b| b == b'-') {
+            Some(2)
+        } else {
+            None
+        }
+    }
+
     fn parse_inline_content(&mut self, end_tag: TokenTag) -> PResult<Range> {
         let scratch_top = self.scratch.len();
 
-        while self.current_tag() != end_tag
+        while !self.at_inline_end(end_tag)
             && self.current_tag() != TokenTag::Eof
             && self.current_tag() != TokenTag::BlankLine
         {
-            // Skip newlines within inline content (soft breaks)
+            // A newline within inline content is a line break, unless the next line
+            // opens a block such as a list or a fence.
             if self.current_tag() == TokenTag::Newline {
-                self.next_token();
+                let interrupted = self.next_line_starts_block()
+                    || (end_tag == TokenTag::BlankLine
+                        && self.setext_underline_level(self.token_index + 1).is_some());
+                let newline_token = self.next_token();
+                if interrupted {
+                    break;
+                }
+                if !matches!(self.current_tag(), TokenTag::Eof | TokenTag::BlankLine)
+                    && !self.at_inline_end(end_tag)
+                {
+                    let line_break = self.add_line_break(newline_token);
+                    self.scratch.push(line_break);
+                }
                 continue;
             }
 
@@ -574,9 +2187,9 @@ impl Parser {
             }
         }
 
-        if self.current_tag() == end_tag {
+        if self.at_inline_end(end_tag) {
             self.eat_token(end_tag);
-        } else if Self::requires_closing_inline_delimiter(end_tag) {
+        } else if Self::requires_closing_inline_delimiter(end_tag) && !self.at_partial_end() {
             self.warn(ErrorTag::ExpectedToken);
             self.scratch.truncate(scratch_top);
             return Err(ParseError::ParseError);
@@ -587,10 +2200,25 @@ impl Parser {
         Ok(self.list_to_span(&children))
     }
 
+    /// Whether the current token ends inline content running to `end_tag`. A link label
+    /// ends only at the `]` paired with its own `[`, not at one closing literal brackets.
+    fn at_inline_end(&self, end_tag: TokenTag) -> bool {
+        self.current_tag() == end_tag
+            && (end_tag != TokenTag::LinkEnd
+                || self.link_label_ends.last() == Some(&self.token_index))
+    }
+
     fn requires_closing_inline_delimiter(end_tag: TokenTag) -> bool {
         matches!(
             end_tag,
-            TokenTag::StrongEnd | TokenTag::EmphasisEnd | TokenTag::StrikethroughEnd
+            TokenTag::StrongEnd
+                | TokenTag::EmphasisEnd
+                | TokenTag::StrikethroughEnd
+                | TokenTag::SpoilerEnd
+                | TokenTag::HighlightEnd
+                | TokenTag::UnderlineEnd
+                | TokenTag::SuperscriptEnd
+                | TokenTag::SubscriptEnd
         )
     }
 
@@ -600,12 +2228,46 @@ impl Parser {
             TokenTag::StrongStart => self.parse_strong(),
             TokenTag::EmphasisStart => self.parse_emphasis(),
             TokenTag::StrikethroughStart => self.parse_strikethrough(),
+            TokenTag::SpoilerStart
+            | TokenTag::HighlightStart
+            | TokenTag::UnderlineStart
+            | TokenTag::SuperscriptStart
+            | TokenTag::SubscriptStart => self.parse_chat_span(),
             TokenTag::CodeInlineStart => self.parse_code_inline(),
             TokenTag::LinkStart => self.parse_link(),
             TokenTag::ImageStart => self.parse_image(),
+            TokenTag::FootnoteReference => self.parse_footnote_reference(),
+            TokenTag::HtmlComment => self.parse_html_comment(),
+            TokenTag::MathInline => self.parse_math(NodeTag::MathInline),
+            TokenTag::NostrUri if self.link_label_ends.is_empty() => self.parse_nostr_reference(),
+            TokenTag::CustomEmoji => self.parse_word(NodeTag::CustomEmoji),
+            TokenTag::Hashtag if self.link_label_ends.is_empty() => {
+                self.parse_word(NodeTag::Hashtag)
+            }
+            TokenTag::Mention if self.link_label_ends.is_empty() => {
+                self.parse_word(NodeTag::Mention)
+            }
             TokenTag::HardBreak => self.parse_hard_break(),
             TokenTag::ExprStart => self.parse_text_expression(),
             TokenTag::JsxTagStart => self.parse_jsx_element(),
+            TokenTag::AutolinkStart | TokenTag::AutolinkLiteral
+                if self.link_label_ends.is_empty() =>
+            {
+                self.parse_autolink()
+            }
+            // Brackets that did not pair up into a link stay literal text, as do autolinks
+            // and `nostr:` URIs inside a link label.
+            TokenTag::LinkEnd
+            | TokenTag::LinkUrlStart
+            | TokenTag::LinkUrlEnd
+            | TokenTag::AutolinkStart
+            | TokenTag::AutolinkEnd
+            | TokenTag::AutolinkLiteral
+            | TokenTag::NostrUri
+            | TokenTag::Hashtag
+            | TokenTag::Mention => self.parse_text(),
+            // An ordered list marker that may not interrupt the paragraph stays text.
+            TokenTag::ListItemOrdered => self.parse_text(),
             _ => {
                 self.warn(ErrorTag::UnexpectedToken);
                 self.next_token();
@@ -623,6 +2285,54 @@ impl Parser {
         }))
     }
 
+    fn parse_html_comment(&mut self) -> PResult<NodeIndex> {
+        let comment_token = self.next_token();
+        Ok(self.add_node(Node {
+            tag: NodeTag::HtmlComment,
+            main_token: comment_token,
+            data: NodeData::None,
+        }))
+    }
+
+    fn parse_nostr_reference(&mut self) -> PResult<NodeIndex> {
+        let uri_token = self.next_token();
+        Ok(self.add_node(Node {
+            tag: NodeTag::NostrReference,
+            main_token: uri_token,
+            data: NodeData::None,
+        }))
+    }
+
+    /// A hashtag, mention or custom emoji: one token, one node.
+    fn parse_word(&mut self, tag: NodeTag) -> PResult<NodeIndex> {
+        let word_token = self.next_token();
+        Ok(self.add_node(Node {
+            tag,
+            main_token: word_token,
+            data: NodeData::None,
+        }))
+    }
+
+    fn parse_math(&mut self, tag: NodeTag) -> PResult<NodeIndex> {
+        let math_token = self.next_token();
+        Ok(self.add_node(Node {
+            tag,
+            main_token: math_token,
+            data: NodeData::None,
+        }))
+    }
+
+    /// The break for a newline that continues a paragraph: a `SoftBreak`, or a
+    /// `HardBreak` with `ParseOptions::breaks`.
+    fn add_line_break(&mut self, newline_token: TokenIndex) -> NodeIndex {
+        let tag = if self.options.breaks {
+            NodeTag::HardBreak
+        } else {
+            NodeTag::SoftBreak
+        };
+        self.add_token_node(tag, newline_token)
+    }
+
     fn parse_hard_break(&mut self) -> PResult<NodeIndex> {
         let break_token = self.next_token();
         Ok(self.add_node(Node {
@@ -651,6 +2361,7 @@ impl Parser {
             }
         };
 
+        self.note_unclosed(node_index, TokenTag::StrongEnd);
         Ok(self.set_node(
             node_index,
             Node {
@@ -680,6 +2391,7 @@ impl Parser {
             }
         };
 
+        self.note_unclosed(node_index, TokenTag::EmphasisEnd);
         Ok(self.set_node(
             node_index,
             Node {
@@ -709,6 +2421,7 @@ impl Parser {
             }
         };
 
+        self.note_unclosed(node_index, TokenTag::StrikethroughEnd);
         Ok(self.set_node(
             node_index,
             Node {
@@ -719,58 +2432,229 @@ impl Parser {
         ))
     }
 
+    /// A spoiler, highlight, underline, superscript or subscript, depending on the
+    /// current start token.
+    fn parse_chat_span(&mut self) -> PResult<NodeIndex> {
+        let (tag, end_tag) = match self.current_tag() {
+            TokenTag::SpoilerStart => (NodeTag::Spoiler, TokenTag::SpoilerEnd),
+            TokenTag::HighlightStart => (NodeTag::Highlight, TokenTag::HighlightEnd),
+            TokenTag::UnderlineStart => (NodeTag::Underline, TokenTag::UnderlineEnd),
+            TokenTag::SuperscriptStart => (NodeTag::Superscript, TokenTag::SuperscriptEnd),
+            _ => (NodeTag::Subscript, TokenTag::SubscriptEnd),
+        };
+        let start_token = self.next_token();
+        let node_index = self.reserve_node(tag);
+
+        let children_span = match self.parse_inline_content(end_tag) {
+            Ok(span) => span,
+            Err(e) => {
+                self.set_node(
+                    node_index,
+                    Node {
+                        tag,
+                        main_token: start_token,
+                        data: NodeData::Children(Range { start: 0, end: 0 }),
+                    },
+                );
+                return Err(e);
+            }
+        };
+
+        self.note_unclosed(node_index, end_tag);
+        Ok(self.set_node(
+            node_index,
+            Node {
+                tag,
+                main_token: start_token,
+                data: NodeData::Children(children_span),
+            },
+        ))
+    }
+
     fn parse_code_inline(&mut self) -> PResult<NodeIndex> {
         let start_token = self.next_token(); // `
-        self.expect_token(TokenTag::Text)?; // code content
-        self.expect_token(TokenTag::CodeInlineEnd)?; // `
-
-        Ok(self.add_node(Node {
+        let closed = self.expect_unless_partial_end(TokenTag::Text)? // code content
+            && self.expect_unless_partial_end(TokenTag::CodeInlineEnd)?; // `
+        let node = self.add_node(Node {
             tag: NodeTag::CodeInline,
             main_token: start_token,
             data: NodeData::Token(start_token + 1),
-        }))
+        });
+        if !closed {
+            self.incomplete.push(node);
+        }
+        Ok(node)
     }
 
     fn parse_link(&mut self) -> PResult<NodeIndex> {
-        let start_token = self.next_token(); // [
-        let label_span = self.parse_inline_content(TokenTag::LinkEnd)?;
-        self.expect_token(TokenTag::LinkUrlStart)?; // (
-        let url_token = self.expect_token(TokenTag::Text)?;
-        self.expect_token(TokenTag::LinkUrlEnd)?; // )
+        self.parse_link_or_image(NodeTag::Link)
+    }
+
+    fn parse_image(&mut self) -> PResult<NodeIndex> {
+        self.parse_link_or_image(NodeTag::Image)
+    }
+
+    fn parse_link_or_image(&mut self, tag: NodeTag) -> PResult<NodeIndex> {
+        let start_token = self.token_index; // [ or ![
+        let Some(target) = self.link_target(start_token) else {
+            // Neither a destination nor a matching definition: the brackets are literal.
+            if self.matching_link_end(start_token).is_some() {
+                // A definition arriving later could still turn these brackets into a link.
+                self.pending_references += 1;
+            }
+            return self.parse_text();
+        };
+        let label_end = self.matching_link_end(start_token);
+        self.next_token();
+        self.link_label_ends.extend(label_end);
+        let label_span = self.parse_inline_content(TokenTag::LinkEnd);
+        self.link_label_ends.pop();
+        let label_span = label_span?;
+
+        let mut closed = true;
+        let (url_token, reference) = match target {
+            LinkTarget::Inline => {
+                self.expect_token(TokenTag::LinkUrlStart)?; // (
+                let url_token = self.token_index;
+                closed = self.expect_unless_partial_end(TokenTag::Text)?
+                    && self.expect_unless_partial_end(TokenTag::LinkUrlEnd)?; // )
+                (url_token, LinkReference::Inline)
+            }
+            LinkTarget::Reference {
+                definition,
+                reference,
+                end,
+            } => {
+                if definition > start_token {
+                    self.pending_references += 1;
+                }
+                self.token_index = end;
+                (definition, reference)
+            }
+        };
 
         let link_data = self.add_extra_link(&Link {
             children_start: label_span.start,
             children_end: label_span.end,
             url_token,
+            reference,
         });
 
-        Ok(self.add_node(Node {
-            tag: NodeTag::Link,
+        let node = self.add_node(Node {
+            tag,
             main_token: start_token,
             data: NodeData::Extra(link_data),
-        }))
+        });
+        if !closed {
+            self.incomplete.push(node);
+        }
+        Ok(node)
     }
 
-    fn parse_image(&mut self) -> PResult<NodeIndex> {
-        let start_token = self.next_token(); // ![
-        let label_span = self.parse_inline_content(TokenTag::LinkEnd)?;
-        self.expect_token(TokenTag::LinkUrlStart)?; // (
-        let url_token = self.expect_token(TokenTag::Text)?;
-        self.expect_token(TokenTag::LinkUrlEnd)?; // )
+    fn parse_autolink(&mut self) -> PResult<NodeIndex> {
+        let start_token = self.next_token(); // < or the literal itself
+        let (url_token, reference) =
+            if self.token_tags[start_token as usize] == TokenTag::AutolinkStart {
+                let url_token = self.expect_token(TokenTag::Text)?;
+                self.expect_token(TokenTag::AutolinkEnd)?; // >
+                (url_token, LinkReference::Autolink)
+            } else {
+                (start_token, LinkReference::Literal)
+            };
 
+        // The URL doubles as the link text.
+        let text = self.add_token_node(NodeTag::Text, url_token);
+        let label_span = self.list_to_span(&[text]);
         let link_data = self.add_extra_link(&Link {
             children_start: label_span.start,
             children_end: label_span.end,
             url_token,
+            reference,
         });
 
         Ok(self.add_node(Node {
-            tag: NodeTag::Image,
+            tag: NodeTag::Link,
             main_token: start_token,
             data: NodeData::Extra(link_data),
         }))
     }
 
+    /// Decide whether the bracket at `open` starts an inline link, a reference to a known
+    /// definition (`[text][label]`, `[label][]` or `[label]`), or nothing at all.
+    fn link_target(&mut self, open: TokenIndex) -> Option<LinkTarget> {
+        let close = self.matching_link_end(open)?;
+        let after = close + 1;
+        let after_tag = self.token_tags.get(after as usize).copied();
+        if after_tag == Some(TokenTag::LinkUrlStart) {
+            return Some(LinkTarget::Inline);
+        }
+
+        let label_start = self.token_starts[open as usize] as usize + self.token_slice(open).len();
+        let label = &self.source[label_start..self.token_starts[close as usize] as usize];
+        if after_tag == Some(TokenTag::LinkStart)
+            && self.token_starts[after as usize] == self.token_starts[close as usize] + 1
+        {
+            let ref_close = self.matching_link_end(after)?;
+            let ref_label = &self.source[self.token_starts[after as usize] as usize + 1
+                ..self.token_starts[ref_close as usize] as usize];
+            let (label, reference) = if ref_label.is_empty() {
+                (label, LinkReference::Collapsed)
+            } else {
+                (ref_label, LinkReference::Full)
+            };
+            return Some(LinkTarget::Reference {
+                definition: self.definition(label)?,
+                reference,
+                end: ref_close + 1,
+            });
+        }
+
+        Some(LinkTarget::Reference {
+            definition: self.definition(label)?,
+            reference: LinkReference::Shortcut,
+            end: close + 1,
+        })
+    }
+
+    /// The `]` closing the bracket opened at `open`, within the same paragraph.
+    fn matching_link_end(&mut self, open: TokenIndex) -> Option<TokenIndex> {
+        if open.wrapping_sub(self.link_pairs_start) as usize >= self.link_pairs.len() {
+            self.pair_link_brackets(open);
+        }
+        let close = self.link_pairs[(open - self.link_pairs_start) as usize];
+        (close != TokenIndex::MAX).then_some(close)
+    }
+
+    /// Pair the brackets from `from` to the end of its paragraph in one pass, so the
+    /// brackets after it are looked up instead of each scanning ahead for its `]`.
+    fn pair_link_brackets(&mut self, from: TokenIndex) {
+        self.link_pairs.clear();
+        self.link_pairs_start = from;
+        let mut open = Vec::new();
+        for index in from as usize..self.token_tags.len() {
+            self.link_pairs.push(TokenIndex::MAX);
+            match self.token_tags[index] {
+                TokenTag::LinkStart | TokenTag::ImageStart => open.push(index - from as usize),
+                TokenTag::LinkEnd => {
+                    if let Some(bracket) = open.pop() {
+                        self.link_pairs[bracket] = index as TokenIndex;
+                    }
+                }
+                TokenTag::BlankLine | TokenTag::Eof => break,
+                _ => {}
+            }
+        }
+    }
+
+    fn definition(&self, label: &str) -> Option<TokenIndex> {
+        if self.definitions.is_empty() {
+            return None;
+        }
+        self.definitions
+            .get(&crate::semantic::normalize_link_label(label))
+            .copied()
+    }
+
     fn parse_code_block(&mut self) -> PResult<NodeIndex> {
         let start_token = self.next_token(); // ```
 
@@ -783,12 +2667,25 @@ impl Parser {
             self.token_index += 1;
         }
 
-        self.expect_token(TokenTag::CodeFenceEnd)?;
+        let closed = self.expect_unless_partial_end(TokenTag::CodeFenceEnd)?;
 
-        Ok(self.add_node(Node {
+        let node = self.add_node(Node {
             tag: NodeTag::CodeBlock,
             main_token: start_token,
             data: NodeData::None,
+        });
+        if !closed {
+            self.incomplete.push(node);
+        }
+        Ok(node)
+    }
+
+    fn parse_indented_code(&mut self) -> PResult<NodeIndex> {
+        let code_token = self.next_token();
+        Ok(self.add_node(Node {
+            tag: NodeTag::CodeBlock,
+            main_token: code_token,
+            data: NodeData::None,
         }))
     }
 
@@ -801,15 +2698,35 @@ impl Parser {
         }))
     }
 
+    fn line_is_alert_marker(&self, quote_token: TokenIndex) -> bool {
+        let line_start = self.token_starts[quote_token as usize + 1] as usize;
+        let line = self.source[line_start..].split('\n').next().unwrap_or("");
+        crate::semantic::alert_marker(line).is_some()
+    }
+
     fn parse_blockquote(&mut self) -> PResult<NodeIndex> {
         let start_token = self.token_index;
+        let quote_indent = self.token_column(start_token);
         let node_index = self.reserve_node(NodeTag::Blockquote);
         let mut block_children = Vec::new();
+        let mut continues = self.current_tag() == TokenTag::BlockquoteStart;
 
-        while self.current_tag() == TokenTag::BlockquoteStart {
+        while continues {
             let quote_token = self.next_token();
             let mut paragraph_children = Vec::new();
             let mut saw_content = false;
+            let mut line_end: Option<TokenIndex> = None;
+            continues = false;
+
+            // A `[!NOTE]` alert marker on the first line is not content.
+            if quote_token == start_token && self.line_is_alert_marker(quote_token) {
+                while !matches!(
+                    self.current_tag(),
+                    TokenTag::Newline | TokenTag::BlankLine | TokenTag::Eof
+                ) {
+                    self.token_index += 1;
+                }
+            }
 
             loop {
                 if self.current_tag() == TokenTag::Space {
@@ -818,6 +2735,7 @@ impl Parser {
 
                 if self.current_tag() == TokenTag::Newline {
                     self.next_token();
+                    continues = self.eat_indented(quote_indent, TokenTag::BlockquoteStart);
                     break;
                 }
 
@@ -837,16 +2755,24 @@ impl Parser {
                 };
 
                 if !line_children.is_empty() {
+                    if saw_content && let Some(newline_token) = line_end {
+                        paragraph_children.push(self.add_line_break(newline_token));
+                    }
                     saw_content = true;
                     paragraph_children.extend(line_children);
                 }
+                line_end = self
+                    .token_index
+                    .checked_sub(1)
+                    .filter(|&previous| self.token_tags[previous as usize] == TokenTag::Newline);
 
-                if self.current_tag() != TokenTag::BlockquoteStart {
+                if !self.eat_indented(quote_indent, TokenTag::BlockquoteStart) {
                     break;
                 }
 
                 let before_next_line = self.peek_token(1);
                 if before_next_line == TokenTag::Newline {
+                    continues = true;
                     break;
                 }
                 self.next_token();
@@ -878,14 +2804,20 @@ impl Parser {
         };
 
         let start_token = self.token_index;
+        let marker_indent = self.token_column(start_token);
         let node_index = self.reserve_node(list_tag);
 
         let scratch_top = self.scratch.len();
 
-        while self.current_tag() == first_item_tag {
-            match self.parse_list_item() {
+        loop {
+            let item_token = self.token_index;
+            match self.parse_list_item(marker_indent) {
                 Ok(item) => {
                     self.scratch.push(item);
+                    let content_column = self.list_content_column(item_token, marker_indent);
+                    if !self.eat_sibling_marker(marker_indent, content_column, first_item_tag) {
+                        break;
+                    }
                 }
                 Err(e) => {
                     let children: Vec<NodeIndex> = self.scratch[scratch_top..].to_vec();
@@ -918,7 +2850,49 @@ impl Parser {
         ))
     }
 
-    fn parse_list_item(&mut self) -> PResult<NodeIndex> {
+    /// Column where the content of the list item whose marker is `item_token` starts.
+    fn list_content_column(&self, item_token: TokenIndex, marker_indent: u32) -> u32 {
+        marker_indent + self.token_slice(item_token).len() as u32
+    }
+
+    /// Move to the next item of a list, skipping blank lines before it. A sibling marker
+    /// sits at or past the list's `marker_indent` but short of the previous item's
+    /// `content_column`, where it would nest instead. Leaves the position untouched when
+    /// the list ends here.
+    fn eat_sibling_marker(
+        &mut self,
+        marker_indent: u32,
+        content_column: u32,
+        tag: TokenTag,
+    ) -> bool {
+        let tag_at = |index: TokenIndex| {
+            self.token_tags
+                .get(index as usize)
+                .copied()
+                .unwrap_or(TokenTag::Eof)
+        };
+
+        let mut index = self.token_index;
+        loop {
+            match tag_at(index) {
+                TokenTag::BlankLine => index += 1,
+                TokenTag::Indent if tag_at(index + 1) == TokenTag::Newline => index += 2,
+                _ => break,
+            }
+        }
+        let (indent, marker) = if tag_at(index) == TokenTag::Indent {
+            (self.token_slice(index).len() as u32, index + 1)
+        } else {
+            (0, index)
+        };
+        if tag_at(marker) != tag || indent < marker_indent || indent >= content_column {
+            return false;
+        }
+        self.token_index = marker;
+        true
+    }
+
+    fn parse_list_item(&mut self, marker_indent: u32) -> PResult<NodeIndex> {
         let item_token = self.next_token();
         let node_index = self.reserve_node(NodeTag::ListItem);
 
@@ -931,7 +2905,7 @@ impl Parser {
             None
         };
 
-        let first_line_span = match self.parse_inline_content(TokenTag::Newline) {
+        let children_span = match self.parse_list_item_children(item_token, marker_indent, true) {
             Ok(span) => span,
             Err(e) => {
                 let extra_idx = self.add_extra_list_item(&ListItemData {
@@ -951,48 +2925,6 @@ impl Parser {
             }
         };
 
-        let first_line_children = self.range_to_nodes(first_line_span);
-        let has_continuation = self.current_tag() == TokenTag::BlankLine;
-
-        let children_span = if has_continuation {
-            let mut block_children = Vec::new();
-            block_children.push(self.add_paragraph_node(item_token, first_line_children));
-
-            while self.current_tag() == TokenTag::BlankLine {
-                self.next_token();
-
-                if self.current_tag() != TokenTag::Indent {
-                    break;
-                }
-
-                let continuation_children = match self.parse_list_item_paragraph() {
-                    Ok(children) => children,
-                    Err(e) => {
-                        let extra_idx = self.add_extra_list_item(&ListItemData {
-                            checked,
-                            children_start: 0,
-                            children_end: 0,
-                        });
-                        self.set_node(
-                            node_index,
-                            Node {
-                                tag: NodeTag::ListItem,
-                                main_token: item_token,
-                                data: NodeData::Extra(extra_idx),
-                            },
-                        );
-                        return Err(e);
-                    }
-                };
-
-                block_children.push(self.add_paragraph_node(item_token, continuation_children));
-            }
-
-            self.list_to_span(&block_children)
-        } else {
-            first_line_span
-        };
-
         let extra_idx = self.add_extra_list_item(&ListItemData {
             checked,
             children_start: children_span.start,
@@ -1009,21 +2941,130 @@ impl Parser {
         ))
     }
 
-    fn parse_list_item_paragraph(&mut self) -> PResult<Vec<NodeIndex>> {
-        let mut paragraph_children = Vec::new();
+    /// Parse the content of a list item or footnote definition. Lines indented past the
+    /// marker belong to the item: they continue its paragraph or open nested blocks
+    /// (sub-lists, fences, quotes, JSX). With `inline_when_tight`, a single-paragraph item
+    /// keeps its inline nodes as direct children; otherwise the content is a list of blocks.
+    fn parse_list_item_children(
+        &mut self,
+        item_token: TokenIndex,
+        marker_indent: u32,
+        inline_when_tight: bool,
+    ) -> PResult<Range> {
+        // Blocks nest only from the item's content column; footnotes take any indent.
+        let block_column = match self.token_tags[item_token as usize] {
+            TokenTag::ListItemUnordered | TokenTag::ListItemOrdered => {
+                self.list_content_column(item_token, marker_indent)
+            }
+            _ => marker_indent + 1,
+        };
+        let mut paragraph = Vec::new();
+        let mut paragraph_token = item_token;
+        let mut blocks = Vec::new();
+        // A block may open on the marker's own line, as in `- > quote`.
+        let mut has_blocks = Self::is_block_start(self.current_tag());
+        if has_blocks {
+            let block = self.parse_block()?;
+            blocks.push(block);
+        } else {
+            let first_line_span = self.parse_inline_content(TokenTag::Newline)?;
+            paragraph = self.range_to_nodes(first_line_span);
+        }
 
-        while self.current_tag() == TokenTag::Indent {
-            self.next_token();
+        loop {
+            // Nested blocks such as fences and JSX stop before their line break.
+            self.eat_token(TokenTag::Newline);
 
-            let line_children = self.parse_inline_content(TokenTag::Newline)?;
-            paragraph_children.extend(self.range_to_nodes(line_children));
+            let content_line = self.next_item_content_line(marker_indent);
+            let Some((line_token, after_blank)) = content_line else {
+                break;
+            };
 
-            if self.current_tag() != TokenTag::Indent {
+            let opens_block = self.item_line_opens_block(line_token + 1)
+                && (after_blank
+                    || paragraph.is_empty()
+                    || self.may_interrupt_paragraph(line_token + 1));
+            if opens_block && (self.token_slice(line_token).len() as u32) < block_column {
                 break;
             }
+            if after_blank || opens_block {
+                has_blocks = true;
+                if !paragraph.is_empty() {
+                    blocks.push(self.add_paragraph_node(paragraph_token, paragraph));
+                    paragraph = Vec::new();
+                }
+            }
+
+            self.token_index = line_token + 1; // past the indent
+            if opens_block {
+                let block = self.parse_block()?;
+                blocks.push(block);
+            } else {
+                if paragraph.is_empty() {
+                    paragraph_token = self.token_index;
+                } else if self.token_tags[line_token as usize - 1] == TokenTag::Newline {
+                    paragraph.push(self.add_line_break(line_token - 1));
+                }
+                let line_span = self.parse_inline_content(TokenTag::Newline)?;
+                paragraph.extend(self.range_to_nodes(line_span));
+            }
+        }
+
+        if !has_blocks && inline_when_tight {
+            return Ok(self.list_to_span(&paragraph));
+        }
+        if !paragraph.is_empty() {
+            blocks.push(self.add_paragraph_node(paragraph_token, paragraph));
+        }
+        Ok(self.list_to_span(&blocks))
+    }
+
+    /// Find the next line that belongs to a list item whose marker sits at
+    /// `marker_indent`, skipping blank lines. Returns the line's indent token and whether
+    /// blank lines were skipped; the position only moves when such a line exists.
+    fn next_item_content_line(&self, marker_indent: u32) -> Option<(TokenIndex, bool)> {
+        let tag_at = |index: TokenIndex| {
+            self.token_tags
+                .get(index as usize)
+                .copied()
+                .unwrap_or(TokenTag::Eof)
+        };
+
+        let mut index = self.token_index;
+        let mut after_blank = false;
+        loop {
+            match tag_at(index) {
+                TokenTag::BlankLine => index += 1,
+                TokenTag::Indent if tag_at(index + 1) == TokenTag::Newline => index += 2,
+                _ => break,
+            }
+            after_blank = true;
         }
 
-        Ok(paragraph_children)
+        let indented = tag_at(index) == TokenTag::Indent
+            && self.token_slice(index).len() as u32 > marker_indent
+            && !matches!(
+                tag_at(index + 1),
+                TokenTag::Newline | TokenTag::BlankLine | TokenTag::Eof
+            );
+        indented.then_some((index, after_blank))
+    }
+
+    /// Whether the item line whose content starts at `token` opens a nested block. JSX
+    /// counts as a block only when the tag fills the line (`<Card>`, `</Card>`), so inline
+    /// components inside a continuation line stay in the paragraph.
+    fn item_line_opens_block(&self, token: TokenIndex) -> bool {
+        let tag = self.token_tags[token as usize];
+        if Self::is_block_start(tag) {
+            return true;
+        }
+        if tag != TokenTag::JsxTagStart {
+            return false;
+        }
+        let line_start = self.token_starts[token as usize] as usize;
+        let rest = &self.source.as_bytes()[line_start..];
+        let line_end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        rest[..line_end].trim_ascii_end().ends_with(b">")
     }
 
     fn parse_table(&mut self) -> PResult<NodeIndex> {
@@ -1105,9 +3146,23 @@ impl Parser {
         // Parse body rows
         while self.current_tag() == TokenTag::Pipe {
             let before = self.token_index;
+            let (nodes_before, extra_before) = (self.nodes.len(), self.extra_data.len());
+            let (scratch_before, errors_before) = (self.scratch.len(), self.errors.len());
             match self.parse_table_row() {
                 Ok(row) => self.scratch.push(row),
-                Err(_) => break,
+                Err(_) => {
+                    // End the table before the failed row. The row is parsed again as
+                    // the next block, which fails there and recovers like any other.
+                    self.scratch.truncate(scratch_before);
+                    self.nodes.truncate(nodes_before);
+                    self.node_spans.truncate(nodes_before);
+                    self.extra_data.truncate(extra_before);
+                    self.errors.truncate(errors_before);
+                    self.incomplete
+                        .retain(|&node| (node as usize) < nodes_before);
+                    self.token_index = before;
+                    break;
+                }
             }
             if self.token_index == before {
                 self.token_index += 1;
@@ -1249,6 +3304,10 @@ impl Parser {
 
         while depth > 0 && self.current_tag() != TokenTag::Eof {
             match self.current_tag() {
+                TokenTag::ExprStart if depth == MAX_NESTING_DEPTH => {
+                    self.warn(ErrorTag::NestingTooDeep);
+                    return Err(ParseError::ParseError);
+                }
                 TokenTag::ExprStart => depth += 1,
                 TokenTag::ExprEnd => depth -= 1,
                 _ => {}
@@ -1258,25 +3317,29 @@ impl Parser {
             }
         }
 
-        if depth > 0 {
+        if depth > 0 && !self.partial {
             self.warn(ErrorTag::UnclosedExpression);
             return Err(ParseError::ParseError);
         }
 
         let content_end = self.token_index;
 
-        self.expect_token(TokenTag::ExprEnd)?;
+        let closed = self.expect_unless_partial_end(TokenTag::ExprEnd)?;
 
         let range_index = self.add_extra_range(&Range {
             start: content_start,
             end: content_end,
         });
 
-        Ok(self.add_node(Node {
+        let node = self.add_node(Node {
             tag: NodeTag::MdxTextExpression,
             main_token: expr_start,
             data: NodeData::Extra(range_index),
-        }))
+        });
+        if !closed {
+            self.incomplete.push(node);
+        }
+        Ok(node)
     }
 
     fn parse_jsx_element(&mut self) -> PResult<NodeIndex> {
@@ -1312,6 +3375,24 @@ impl Parser {
         }
         let attrs_end = self.extra_data.len() as u32;
 
+        if self.at_partial_end() {
+            // The opening tag itself is cut off, as in `<Card title="x"`.
+            let jsx_data = self.add_extra_jsx_element(&JsxElement {
+                name_token: name,
+                attrs_start,
+                attrs_end,
+                children_start: 0,
+                children_end: 0,
+            });
+            let node = self.add_node(Node {
+                tag: NodeTag::MdxJsxElement,
+                main_token: open_bracket,
+                data: NodeData::Extra(jsx_data),
+            });
+            self.incomplete.push(node);
+            return Ok(node);
+        }
+
         if !matches!(
             self.current_tag(),
             TokenTag::JsxSelfClose | TokenTag::JsxTagEnd
@@ -1320,8 +3401,13 @@ impl Parser {
             return Err(ParseError::ParseError);
         }
 
-        // Check for self-closing
-        if self.eat_token(TokenTag::JsxSelfClose).is_some() {
+        // Check for self-closing; void HTML elements such as `<br>` close themselves.
+        let is_void = self.current_tag() == TokenTag::JsxTagEnd
+            && crate::semantic::is_void_html_element(&open_name);
+        if is_void || self.eat_token(TokenTag::JsxSelfClose).is_some() {
+            if is_void {
+                self.next_token();
+            }
             let jsx_data = self.add_extra_jsx_element(&JsxElement {
                 name_token: name,
                 attrs_start,
@@ -1338,6 +3424,7 @@ impl Parser {
         }
 
         self.expect_token(TokenTag::JsxTagEnd)?;
+        self.enter_jsx_children()?;
 
         // Parse children
         let scratch_top = self.scratch.len();
@@ -1356,37 +3443,64 @@ impl Parser {
                 }
                 TokenTag::HeadingStart
                 | TokenTag::CodeFenceStart
+                | TokenTag::MathBlock
                 | TokenTag::Hr
                 | TokenTag::BlockquoteStart
                 | TokenTag::ListItemUnordered
                 | TokenTag::ListItemOrdered
-                | TokenTag::Pipe => {
+                | TokenTag::Pipe
+                | TokenTag::LinkDefinition
+                | TokenTag::FootnoteDefinitionStart => {
                     let child = self.parse_block()?;
                     self.scratch.push(child);
                 }
+                TokenTag::FootnoteReference => {
+                    let child = self.parse_footnote_reference()?;
+                    self.scratch.push(child);
+                }
+                TokenTag::HtmlComment => {
+                    let child = self.parse_html_comment()?;
+                    self.scratch.push(child);
+                }
+                TokenTag::MathInline => {
+                    let child = self.parse_math(NodeTag::MathInline)?;
+                    self.scratch.push(child);
+                }
+                TokenTag::NostrUri => {
+                    let child = self.parse_nostr_reference()?;
+                    self.scratch.push(child);
+                }
+                TokenTag::CustomEmoji => {
+                    let child = self.parse_word(NodeTag::CustomEmoji)?;
+                    self.scratch.push(child);
+                }
+                TokenTag::Hashtag => {
+                    let child = self.parse_word(NodeTag::Hashtag)?;
+                    self.scratch.push(child);
+                }
+                TokenTag::Mention => {
+                    let child = self.parse_word(NodeTag::Mention)?;
+                    self.scratch.push(child);
+                }
                 TokenTag::ExprStart => {
                     let child = self.parse_text_expression()?;
                     self.scratch.push(child);
                 }
-                TokenTag::Text => {
+                TokenTag::Text
+                | TokenTag::LinkEnd
+                | TokenTag::LinkUrlStart
+                | TokenTag::LinkUrlEnd => {
+                    let child = self.parse_text()?;
+                    self.scratch.push(child);
+                }
+                TokenTag::EsmImport | TokenTag::EsmExport => {
+                    // ESM is only meaningful at the top level; inside JSX it stays text.
                     let child = self.parse_text()?;
                     self.scratch.push(child);
                 }
                 TokenTag::Indent => {
-                    let next_tag = self.peek_token(1);
-                    if matches!(
-                        next_tag,
-                        TokenTag::JsxTagStart
-                            | TokenTag::JsxCloseTag
-                            | TokenTag::Newline
-                            | TokenTag::BlankLine
-                            | TokenTag::Eof
-                    ) {
-                        self.token_index += 1;
-                    } else {
-                        let child = self.parse_text()?;
-                        self.scratch.push(child);
-                    }
+                    // Line indentation inside JSX is layout, not content.
+                    self.token_index += 1;
                 }
                 TokenTag::Space => {
                     let child = self.parse_text()?;
@@ -1408,10 +3522,22 @@ impl Parser {
                     let child = self.parse_strikethrough()?;
                     self.scratch.push(child);
                 }
+                TokenTag::SpoilerStart
+                | TokenTag::HighlightStart
+                | TokenTag::UnderlineStart
+                | TokenTag::SuperscriptStart
+                | TokenTag::SubscriptStart => {
+                    let child = self.parse_chat_span()?;
+                    self.scratch.push(child);
+                }
                 TokenTag::LinkStart => {
                     let child = self.parse_link()?;
                     self.scratch.push(child);
                 }
+                TokenTag::AutolinkStart | TokenTag::AutolinkLiteral => {
+                    let child = self.parse_autolink()?;
+                    self.scratch.push(child);
+                }
                 TokenTag::ImageStart => {
                     let child = self.parse_image()?;
                     self.scratch.push(child);
@@ -1433,19 +3559,23 @@ impl Parser {
             }
         }
 
+        self.jsx_depth -= 1;
         let children_vec: Vec<NodeIndex> = self.scratch[scratch_top..].to_vec();
         self.scratch.truncate(scratch_top);
         let children_span = self.list_to_span(&children_vec);
 
         // Expect closing tag
-        let close_tag_token = self.expect_token(TokenTag::JsxCloseTag)?;
-        let close_name = self.expect_token(TokenTag::JsxIdentifier)?;
-        if self.token_slice(close_name).trim() != open_name {
-            self.warn_at(ErrorTag::MismatchedTags, close_tag_token);
-            self.eat_token(TokenTag::JsxTagEnd);
-            return Err(ParseError::ParseError);
+        let closed = !self.skip_partial_closing_tag();
+        if closed {
+            let close_tag_token = self.expect_token(TokenTag::JsxCloseTag)?;
+            let close_name = self.expect_token(TokenTag::JsxIdentifier)?;
+            if self.token_slice(close_name).trim() != open_name {
+                self.warn_at(ErrorTag::MismatchedTags, close_tag_token);
+                self.eat_token(TokenTag::JsxTagEnd);
+                return Err(ParseError::ParseError);
+            }
+            self.expect_token(TokenTag::JsxTagEnd)?;
         }
-        self.expect_token(TokenTag::JsxTagEnd)?;
 
         let jsx_data = self.add_extra_jsx_element(&JsxElement {
             name_token: name,
@@ -1455,11 +3585,45 @@ impl Parser {
             children_end: children_span.end,
         });
 
-        Ok(self.add_node(Node {
+        let node = self.add_node(Node {
             tag: NodeTag::MdxJsxElement,
             main_token: open_bracket,
             data: NodeData::Extra(jsx_data),
-        }))
+        });
+        if !closed {
+            self.incomplete.push(node);
+        }
+        Ok(node)
+    }
+
+    /// Count one more open JSX element or fragment, failing past the nesting limit.
+    fn enter_jsx_children(&mut self) -> PResult<()> {
+        if self.jsx_depth == MAX_NESTING_DEPTH {
+            self.warn(ErrorTag::NestingTooDeep);
+            return Err(ParseError::ParseError);
+        }
+        self.jsx_depth += 1;
+        Ok(())
+    }
+
+    /// In a partial document, skip to the end when all that is left is the start of a
+    /// closing tag such as `</Ca`, or nothing at all. Returns whether it did.
+    fn skip_partial_closing_tag(&mut self) -> bool {
+        let rest = &self.token_tags[self.token_index as usize..];
+        let cut_off = self.partial
+            && rest.iter().all(|tag| {
+                matches!(
+                    tag,
+                    TokenTag::JsxTagStart
+                        | TokenTag::JsxCloseTag
+                        | TokenTag::JsxIdentifier
+                        | TokenTag::Eof
+                )
+            });
+        if cut_off {
+            self.token_index = self.token_tags.len() as TokenIndex - 1;
+        }
+        cut_off
     }
 
     fn parse_jsx_attribute_value(&mut self) -> PResult<(Option<TokenIndex>, JsxAttributeType)> {
@@ -1467,6 +3631,19 @@ impl Parser {
             return Ok((Some(value_token), JsxAttributeType::String));
         }
 
+        // A partial document may end before the value or inside its string, as in
+        // `<Card title=` or `<Card title="Pay`; close what is open where the input ends.
+        if self.at_partial_end() {
+            return Ok((None, JsxAttributeType::String));
+        }
+        if self.partial
+            && self.current_tag() == TokenTag::Invalid
+            && self.peek_token(1) == TokenTag::Eof
+            && self.token_slice(self.token_index).starts_with(['"', '\''])
+        {
+            return Ok((Some(self.next_token()), JsxAttributeType::String));
+        }
+
         if self.eat_token(TokenTag::JsxAttrExprStart).is_some() {
             let expr_content_start = self.token_index;
             let mut depth: u32 = 1;
@@ -1482,6 +3659,11 @@ impl Parser {
                 }
             }
 
+            if depth > 0 && self.at_partial_end() {
+                let value_token =
+                    (expr_content_start < self.token_index).then_some(expr_content_start);
+                return Ok((value_token, JsxAttributeType::Expression));
+            }
             if depth > 0 {
                 self.warn(ErrorTag::UnclosedExpression);
                 return Err(ParseError::ParseError);
@@ -1542,6 +3724,7 @@ impl Parser {
     fn parse_jsx_fragment(&mut self) -> PResult<NodeIndex> {
         let open_bracket = self.token_index - 1; // jsx_tag_start
         self.expect_token(TokenTag::JsxTagEnd)?; // >
+        self.enter_jsx_children()?;
 
         let scratch_top = self.scratch.len();
 
@@ -1549,6 +3732,9 @@ impl Parser {
         while !(self.current_tag() == TokenTag::JsxTagStart
             && self.peek_token(1) == TokenTag::JsxCloseTag)
         {
+            if self.skip_partial_closing_tag() {
+                break;
+            }
             if self.current_tag() == TokenTag::Eof {
                 self.warn(ErrorTag::ExpectedClosingTag);
                 return Err(ParseError::ParseError);
@@ -1562,20 +3748,28 @@ impl Parser {
             }
         }
 
+        self.jsx_depth -= 1;
         let children_vec: Vec<NodeIndex> = self.scratch[scratch_top..].to_vec();
         self.scratch.truncate(scratch_top);
         let children_span = self.list_to_span(&children_vec);
 
         // Expect </>
-        self.expect_token(TokenTag::JsxTagStart)?;
-        self.expect_token(TokenTag::JsxCloseTag)?;
-        self.expect_token(TokenTag::JsxTagEnd)?;
+        let closed = !self.skip_partial_closing_tag();
+        if closed {
+            self.expect_token(TokenTag::JsxTagStart)?;
+            self.expect_token(TokenTag::JsxCloseTag)?;
+            self.expect_token(TokenTag::JsxTagEnd)?;
+        }
 
-        Ok(self.add_node(Node {
+        let node = self.add_node(Node {
             tag: NodeTag::MdxJsxFragment,
             main_token: open_bracket,
             data: NodeData::Children(children_span),
-        }))
+        });
+        if !closed {
+            self.incomplete.push(node);
+        }
+        Ok(node)
     }
 
     fn token_slice(&self, token_index: TokenIndex) -> &str {
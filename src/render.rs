@@ -1,4 +1,5 @@
 use crate::ast::*;
+use crate::semantic::{CodeFence, OutlineEntry};
 use crate::token::Tag as TokenTag;
 
 /// Render an AST back to canonical MDX source.
@@ -16,6 +17,8 @@ pub fn render(ast: &Ast) -> String {
     if let Some(idx) = doc_idx {
         let children = ast.children(idx);
         let mut last_was_content = false;
+        let mut last_was_esm = false;
+        let mut last_was_definition = false;
         for &child_idx in children {
             let child_node = &ast.nodes[child_idx as usize];
 
@@ -36,13 +39,24 @@ pub fn render(ast: &Ast) -> String {
                 }
             }
 
-            // Add blank line between content blocks
-            if last_was_content {
+            // Add blank line between content blocks; consecutive ESM statements and
+            // consecutive definitions stay together.
+            let is_esm = matches!(
+                child_node.tag,
+                NodeTag::MdxEsmImport | NodeTag::MdxEsmExport
+            );
+            let is_definition = child_node.tag == NodeTag::Definition;
+            if last_was_content
+                && !(is_esm && last_was_esm)
+                && !(is_definition && last_was_definition)
+            {
                 output.push('\n');
             }
 
             render_node(ast, child_idx, &mut output, &RenderContext::default());
             last_was_content = child_node.tag != NodeTag::Frontmatter;
+            last_was_esm = is_esm;
+            last_was_definition = is_definition;
         }
     }
 
@@ -58,6 +72,17 @@ struct RenderContext {
     in_jsx: bool,
 }
 
+/// Write `{value}`, moving the closing brace to its own line when `value` ends in a
+/// `//` comment that would otherwise swallow it.
+fn write_expression(output: &mut String, value: &str) {
+    output.push('{');
+    output.push_str(value);
+    if crate::tokenizer::ends_in_line_comment(value) {
+        output.push('\n');
+    }
+    output.push('}');
+}
+
 fn write_indent(output: &mut String, level: u32) {
     for _ in 0..level {
         output.push_str("  ");
@@ -73,6 +98,46 @@ fn can_render_jsx_inline(ast: &Ast, children: &[NodeIndex]) -> bool {
     child.tag == NodeTag::Text || child.tag == NodeTag::MdxTextExpression
 }
 
+/// Render a document outline as a nested MDX list of links to each heading, e.g.
+/// `- [Setup](#setup)` with sub-headings indented under their parent.
+pub fn render_outline(outline: &[OutlineEntry]) -> String {
+    let mut output = String::new();
+    render_outline_entries(outline, 0, &mut output);
+    output
+}
+
+fn render_outline_entries(entries: &[OutlineEntry], depth: usize, output: &mut String) {
+    for entry in entries {
+        output.push_str(&"  ".repeat(depth));
+        output.push_str("- [");
+        for ch in entry.title.chars() {
+            if matches!(
+                ch,
+                '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '{' | '}' | '~' | '|'
+            ) {
+                output.push('\\');
+            }
+            output.push(ch);
+        }
+        output.push_str("](#");
+        output.push_str(entry.anchor());
+        output.push_str(")\n");
+        render_outline_entries(&entry.children, depth + 1, output);
+    }
+}
+
+/// Write a heading's `{#id}` attribute back after its text.
+fn push_heading_id(ast: &Ast, id_token: Option<TokenIndex>, output: &mut String) {
+    if let Some(token) = id_token {
+        if !output.ends_with([' ', '\t', '\n']) {
+            output.push(' ');
+        }
+        output.push('{');
+        output.push_str(ast.token_slice(token));
+        output.push('}');
+    }
+}
+
 fn can_render_all_jsx_children_inline(ast: &Ast, children: &[NodeIndex]) -> bool {
     if children.is_empty() {
         return true;
@@ -86,11 +151,24 @@ fn can_render_all_jsx_children_inline(ast: &Ast, children: &[NodeIndex]) -> bool
                 | NodeTag::Strong
                 | NodeTag::Emphasis
                 | NodeTag::Strikethrough
+                | NodeTag::Spoiler
+                | NodeTag::Highlight
+                | NodeTag::Underline
+                | NodeTag::Superscript
+                | NodeTag::Subscript
                 | NodeTag::CodeInline
                 | NodeTag::Link
                 | NodeTag::Image
                 | NodeTag::MdxTextExpression
                 | NodeTag::HardBreak
+                | NodeTag::SoftBreak
+                | NodeTag::FootnoteReference
+                | NodeTag::HtmlComment
+                | NodeTag::MathInline
+                | NodeTag::NostrReference
+                | NodeTag::Hashtag
+                | NodeTag::Mention
+                | NodeTag::CustomEmoji
         )
     })
 }
@@ -102,11 +180,24 @@ fn is_inline_jsx_child(tag: NodeTag) -> bool {
             | NodeTag::Strong
             | NodeTag::Emphasis
             | NodeTag::Strikethrough
+            | NodeTag::Spoiler
+            | NodeTag::Highlight
+            | NodeTag::Underline
+            | NodeTag::Superscript
+            | NodeTag::Subscript
             | NodeTag::CodeInline
             | NodeTag::Link
             | NodeTag::Image
             | NodeTag::MdxTextExpression
             | NodeTag::HardBreak
+            | NodeTag::SoftBreak
+            | NodeTag::FootnoteReference
+            | NodeTag::HtmlComment
+            | NodeTag::MathInline
+            | NodeTag::NostrReference
+            | NodeTag::Hashtag
+            | NodeTag::Mention
+            | NodeTag::CustomEmoji
     )
 }
 
@@ -179,15 +270,31 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &Render
 
         NodeTag::Heading => {
             let info = ast.heading_info(node_idx);
+            let children =
+                &ast.extra_data[info.children_start as usize..info.children_end as usize];
+            // Setext headings start at their text rather than at a `#` marker.
+            if ast.token_tags.get(node.main_token as usize) != Some(&TokenTag::HeadingStart) {
+                let mut text = String::new();
+                for &child_raw in children {
+                    render_node(ast, child_raw, &mut text, ctx);
+                }
+                push_heading_id(ast, info.id_token, &mut text);
+                let width = text.lines().last().map_or(0, |line| line.chars().count());
+                let underline = if info.level == 1 { "=" } else { "-" };
+                output.push_str(&text);
+                output.push('\n');
+                output.push_str(&underline.repeat(width.max(3)));
+                output.push('\n');
+                return;
+            }
             for _ in 0..info.level {
                 output.push('#');
             }
             output.push(' ');
-            let children =
-                &ast.extra_data[info.children_start as usize..info.children_end as usize];
             for &child_raw in children {
                 render_node(ast, child_raw, output, ctx);
             }
+            push_heading_id(ast, info.id_token, output);
             output.push('\n');
         }
 
@@ -205,8 +312,13 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &Render
                     }
                 }
             }
+            // Inline JSX and HTML stay on the paragraph's line.
+            let inline_ctx = RenderContext {
+                in_jsx: true,
+                ..*ctx
+            };
             for &child_idx in children {
-                render_node(ast, child_idx, output, ctx);
+                render_node(ast, child_idx, output, &inline_ctx);
             }
             if !ctx.in_jsx {
                 output.push('\n');
@@ -245,6 +357,26 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &Render
             output.push_str("~~");
         }
 
+        NodeTag::Spoiler
+        | NodeTag::Highlight
+        | NodeTag::Underline
+        | NodeTag::Superscript
+        | NodeTag::Subscript => {
+            let delimiter = match node.tag {
+                NodeTag::Spoiler => "||",
+                NodeTag::Highlight => "==",
+                NodeTag::Underline => "++",
+                NodeTag::Superscript => "^",
+                _ => "~",
+            };
+            output.push_str(delimiter);
+            let children = ast.children(node_idx);
+            for &child_idx in children {
+                render_node(ast, child_idx, output, ctx);
+            }
+            output.push_str(delimiter);
+        }
+
         NodeTag::CodeInline => {
             output.push('`');
             if let NodeData::Token(content_token) = node.data {
@@ -255,31 +387,43 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &Render
         }
 
         NodeTag::CodeBlock => {
-            output.push_str("```");
-            let fence_token = node.main_token;
-
-            if fence_token + 1 < ast.token_tags.len() as u32 {
-                let next_token = fence_token + 1;
-                if ast.token_tags[next_token as usize] == TokenTag::Text {
-                    let lang_text = ast.token_slice(next_token);
-                    let trimmed = lang_text.trim();
-                    if !trimmed.is_empty() {
-                        output.push_str(trimmed);
+            let Some(info) = ast.code_block_info(node_idx) else {
+                return;
+            };
+            let fence = match info.fence {
+                CodeFence::Backtick(len) => "`".repeat(len),
+                CodeFence::Tilde(len) => "~".repeat(len),
+                CodeFence::Indented => {
+                    for line in info.code.split_inclusive('\n') {
+                        if line != "\n" {
+                            output.push_str("    ");
+                        }
+                        output.push_str(line);
                     }
+                    return;
                 }
+            };
+
+            output.push_str(&fence);
+            if let Some(lang) = info.lang {
+                output.push_str(lang);
             }
             output.push('\n');
-
-            let code = extract_code_block_content(ast, fence_token);
-            output.push_str(code);
-            if !code.is_empty() && !code.ends_with('\n') {
+            output.push_str(&info.code);
+            if !info.code.is_empty() && !info.code.ends_with('\n') {
                 output.push('\n');
             }
-            output.push_str("```\n");
+            output.push_str(&fence);
+            output.push('\n');
         }
 
         NodeTag::Blockquote => {
             let children = ast.children(node_idx);
+            if let Some(alert) = ast.blockquote_view(node_idx).and_then(|view| view.alert) {
+                output.push_str("> [!");
+                output.push_str(alert.marker());
+                output.push_str("]\n");
+            }
             for (i, &child_idx) in children.iter().enumerate() {
                 if i > 0 {
                     output.push_str(">\n");
@@ -311,7 +455,11 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &Render
 
         NodeTag::ListUnordered => {
             let children = ast.children(node_idx);
-            for &child_idx in children {
+            let loose = list_is_loose(ast, node_idx);
+            for (i, &child_idx) in children.iter().enumerate() {
+                if loose && i > 0 {
+                    output.push('\n');
+                }
                 let child_ctx = RenderContext {
                     in_list: true,
                     list_index: 0,
@@ -324,7 +472,11 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &Render
 
         NodeTag::ListOrdered => {
             let children = ast.children(node_idx);
+            let loose = list_is_loose(ast, node_idx);
             for (i, &child_idx) in children.iter().enumerate() {
+                if loose && i > 0 {
+                    output.push('\n');
+                }
                 let child_ctx = RenderContext {
                     in_list: true,
                     list_index: (i + 1) as u32,
@@ -337,31 +489,31 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &Render
 
         NodeTag::ListItem => {
             write_indent(output, ctx.indent_level);
+            let marker_start = output.len();
             if ctx.list_index == 0 {
                 output.push_str("- ");
             } else {
                 output.push_str(&format!("{}. ", ctx.list_index));
             }
+            let content_indent = ctx.indent_level as usize * 2 + (output.len() - marker_start);
             let info = ast.list_item_info(node_idx);
             if let Some(checked) = info.checked {
                 output.push_str(if checked { "[x] " } else { "[ ] " });
             }
-            let children = ast.children(node_idx);
-            for (i, &child_idx) in children.iter().enumerate() {
-                let child = &ast.nodes[child_idx as usize];
-                if child.tag == NodeTag::Paragraph {
-                    if i > 0 {
-                        output.push('\n');
-                        output.push('\n');
-                        write_indent(output, ctx.indent_level + 1);
-                    }
-                    let para_children = ast.children(child_idx);
-                    for &para_child_idx in para_children {
-                        render_node(ast, para_child_idx, output, ctx);
+
+            let mut body = String::new();
+            render_list_item_body(ast, node_idx, &mut body, ctx);
+
+            // Continuation lines are indented to the item's content column so nested
+            // blocks parse back into the same item.
+            for (i, line) in body.trim_end_matches('\n').split('\n').enumerate() {
+                if i > 0 {
+                    output.push('\n');
+                    if !line.is_empty() {
+                        output.push_str(&" ".repeat(content_indent));
                     }
-                } else {
-                    render_node(ast, child_idx, output, ctx);
                 }
+                output.push_str(line);
             }
             output.push('\n');
         }
@@ -371,51 +523,123 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &Render
         }
 
         NodeTag::HardBreak => {
-            output.push_str("  \n");
+            // A plain newline under `ParseOptions::breaks` stays a plain newline.
+            if ast.token_tags.get(node.main_token as usize) == Some(&TokenTag::Newline) {
+                output.push('\n');
+            } else {
+                output.push_str("  \n");
+            }
         }
 
-        NodeTag::Link => {
+        NodeTag::SoftBreak => {
+            output.push('\n');
+        }
+
+        NodeTag::Link | NodeTag::Image => {
             let info = ast.link_info(node_idx);
-            output.push('[');
+            match info.reference {
+                LinkReference::Autolink => {
+                    output.push('<');
+                    output.push_str(ast.token_slice(info.url_token));
+                    output.push('>');
+                    return;
+                }
+                LinkReference::Literal => {
+                    output.push_str(ast.token_slice(info.url_token));
+                    return;
+                }
+                _ => {}
+            }
+            output.push_str(if node.tag == NodeTag::Image {
+                "!["
+            } else {
+                "["
+            });
             for &child_idx in ast.link_children(node_idx) {
                 render_node(ast, child_idx, output, ctx);
             }
-            output.push_str("](");
-            let url = ast.token_slice(info.url_token);
-            output.push_str(url);
-            output.push(')');
+            output.push(']');
+            match info.reference {
+                LinkReference::Inline => {
+                    output.push('(');
+                    output.push_str(ast.token_slice(info.url_token));
+                    output.push(')');
+                }
+                LinkReference::Full => {
+                    let definition = ast.token_slice(info.url_token);
+                    if let Some(def) = crate::semantic::split_link_definition(definition) {
+                        output.push('[');
+                        output.push_str(def.label);
+                        output.push(']');
+                    }
+                }
+                LinkReference::Collapsed => output.push_str("[]"),
+                LinkReference::Shortcut | LinkReference::Autolink | LinkReference::Literal => {}
+            }
         }
 
-        NodeTag::Image => {
-            let info = ast.link_info(node_idx);
-            output.push_str("![");
-            for &child_idx in ast.link_children(node_idx) {
-                render_node(ast, child_idx, output, ctx);
+        NodeTag::FootnoteReference => {
+            output.push_str(ast.token_slice(node.main_token));
+        }
+
+        NodeTag::MathInline
+        | NodeTag::NostrReference
+        | NodeTag::Hashtag
+        | NodeTag::Mention
+        | NodeTag::CustomEmoji => {
+            output.push_str(ast.token_slice(node.main_token));
+        }
+
+        NodeTag::MathBlock => {
+            output.push_str(ast.token_slice(node.main_token).trim_end());
+            output.push('\n');
+        }
+
+        NodeTag::HtmlComment => {
+            output.push_str(ast.token_slice(node.main_token));
+            if !ctx.in_jsx {
+                output.push('\n');
             }
-            output.push_str("](");
-            let url = ast.token_slice(info.url_token);
-            output.push_str(url);
-            output.push(')');
         }
 
-        NodeTag::MdxTextExpression => {
-            output.push('{');
-            if let NodeData::Extra(idx) = node.data {
-                let range = ast.extra_range(idx);
-                let content = extract_token_range_content(ast, &range);
-                output.push_str(content.trim());
+        NodeTag::FootnoteDefinition => {
+            let label = ast.footnote_label(node_idx).unwrap_or("");
+            output.push_str(&format!("[^{label}]: "));
+            let mut body = String::new();
+            render_list_item_body(ast, node_idx, &mut body, ctx);
+            // Later blocks are indented so they stay inside the definition.
+            for (i, line) in body.trim_end_matches('\n').split('\n').enumerate() {
+                if i > 0 {
+                    output.push('\n');
+                    if !line.is_empty() {
+                        output.push_str("    ");
+                    }
+                }
+                output.push_str(line);
             }
-            output.push('}');
+            output.push('\n');
+        }
+
+        NodeTag::Definition => {
+            output.push_str(ast.token_slice(node.main_token).trim_end());
+            output.push('\n');
+        }
+
+        NodeTag::MdxTextExpression => {
+            let content = match node.data {
+                NodeData::Extra(idx) => extract_token_range_content(ast, &ast.extra_range(idx)),
+                _ => "",
+            };
+            write_expression(output, content.trim());
         }
 
         NodeTag::MdxFlowExpression => {
-            output.push('{');
-            if let NodeData::Extra(idx) = node.data {
-                let range = ast.extra_range(idx);
-                let content = extract_token_range_content(ast, &range);
-                output.push_str(content.trim());
-            }
-            output.push_str("}\n");
+            let content = match node.data {
+                NodeData::Extra(idx) => extract_token_range_content(ast, &ast.extra_range(idx)),
+                _ => "",
+            };
+            write_expression(output, content.trim());
+            output.push('\n');
         }
 
         NodeTag::MdxJsxElement => {
@@ -550,6 +774,22 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &Render
             // These are handled by render_table_row; if called directly, fall through
         }
 
+        NodeTag::MdxEsmImport | NodeTag::MdxEsmExport => {
+            if let Some(view) = ast.esm_view(node_idx) {
+                output.push_str(view.value);
+                output.push('\n');
+            }
+        }
+
+        NodeTag::Recovery => {
+            // Emit the skipped source verbatim so nothing the user wrote is dropped.
+            let source = ast.recovery_source(node_idx).unwrap_or("");
+            if !source.is_empty() {
+                output.push_str(source);
+                output.push('\n');
+            }
+        }
+
         NodeTag::MdxJsxFragment => {
             write_indent(output, ctx.indent_level);
             output.push_str("<>\n");
@@ -577,6 +817,67 @@ fn render_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &Render
     }
 }
 
+/// Whether blank lines separate the items of a list in the source. Such a list is
+/// rendered with a blank line between every pair of items.
+fn list_is_loose(ast: &Ast, node_idx: NodeIndex) -> bool {
+    ast.children(node_idx).windows(2).any(|items| {
+        let gap = ast.node_span(items[0]).end as usize..ast.node_span(items[1]).start as usize;
+        ast.source
+            .get(gap)
+            .is_some_and(|gap| gap.matches('\n').count() > 1)
+    })
+}
+
+/// Render the children of a list item without the marker. A tight item holds inline
+/// nodes directly; otherwise each block child is rendered on its own lines.
+fn render_list_item_body(ast: &Ast, node_idx: NodeIndex, output: &mut String, ctx: &RenderContext) {
+    let children = ast.children(node_idx);
+    let mut previous: Option<NodeTag> = None;
+
+    for &child_idx in children {
+        let child = &ast.nodes[child_idx as usize];
+        if is_inline_jsx_child(child.tag) {
+            render_node(ast, child_idx, output, ctx);
+            continue;
+        }
+
+        let fenced = child.tag == NodeTag::CodeBlock
+            && ast.token_tags.get(child.main_token as usize) != Some(&TokenTag::CodeIndented);
+        if let Some(previous) = previous {
+            // Sub-lists and fences can follow a paragraph directly; other blocks need a
+            // blank line so they are not merged into the previous one.
+            let tight = previous == NodeTag::Paragraph
+                && (fenced || matches!(child.tag, NodeTag::ListUnordered | NodeTag::ListOrdered));
+            output.push_str(if tight { "\n" } else { "\n\n" });
+        } else if !fenced
+            && !matches!(
+                child.tag,
+                NodeTag::Paragraph
+                    | NodeTag::Heading
+                    | NodeTag::Blockquote
+                    | NodeTag::ListUnordered
+                    | NodeTag::ListOrdered
+                    | NodeTag::Hr
+                    | NodeTag::MathBlock
+            )
+        {
+            // Other blocks cannot share the marker line; start them on the next one.
+            output.push('\n');
+        }
+
+        let mut child_output = String::new();
+        if child.tag == NodeTag::Paragraph {
+            for &para_child_idx in ast.children(child_idx) {
+                render_node(ast, para_child_idx, &mut child_output, ctx);
+            }
+        } else {
+            render_node(ast, child_idx, &mut child_output, &RenderContext::default());
+        }
+        output.push_str(child_output.trim_end_matches('\n'));
+        previous = Some(child.tag);
+    }
+}
+
 fn render_table_row(ast: &Ast, row_idx: NodeIndex, output: &mut String, ctx: &RenderContext) {
     let cells = ast.children(row_idx);
     output.push('|');
@@ -612,12 +913,11 @@ fn render_jsx_attributes(ast: &Ast, node_idx: NodeIndex, output: &mut String) {
             }
             JsxAttributeType::Expression => {
                 output.push('=');
-                output.push('{');
-                if let Some(val_tok) = attr.value_token {
-                    let val_text = ast.token_slice(val_tok).trim();
-                    output.push_str(val_text);
-                }
-                output.push('}');
+                let val_text = attr
+                    .value_token
+                    .map(|val_tok| ast.token_slice(val_tok).trim())
+                    .unwrap_or("");
+                write_expression(output, val_text);
             }
             JsxAttributeType::Number => {
                 output.push('=');
@@ -657,6 +957,9 @@ fn decode_jsx_quoted_value(raw: &str) -> String {
             || (trimmed.starts_with('\'') && trimmed.ends_with('\'')))
     {
         &trimmed[1..trimmed.len() - 1]
+    } else if let Some(rest) = trimmed.strip_prefix(['"', '\'']) {
+        // A string cut off by the end of a streamed chunk has no closing quote yet.
+        rest
     } else {
         trimmed
     };
@@ -720,41 +1023,6 @@ fn extract_token_range_content<'a>(ast: &'a Ast, range: &Range) -> &'a str {
     &ast.source[start..end]
 }
 
-fn extract_code_block_content<'a>(ast: &'a Ast, fence_token: TokenIndex) -> &'a str {
-    let mut code_start: u32 = u32::MAX;
-    let mut code_end: u32 = 0;
-    let mut in_code = false;
-
-    let mut i = fence_token;
-    while (i as usize) < ast.token_tags.len() {
-        if ast.token_tags[i as usize] == TokenTag::CodeFenceEnd {
-            break;
-        }
-        if ast.token_tags[i as usize] == TokenTag::Newline && !in_code {
-            in_code = true;
-            i += 1;
-            continue;
-        }
-        if in_code {
-            let start = ast.token_starts[i as usize];
-            let end = if (i as usize + 1) < ast.token_starts.len() {
-                ast.token_starts[i as usize + 1]
-            } else {
-                ast.source.len() as u32
-            };
-            code_start = code_start.min(start);
-            code_end = code_end.max(end);
-        }
-        i += 1;
-    }
-
-    if code_start < code_end {
-        &ast.source[code_start as usize..code_end as usize]
-    } else {
-        ""
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use crate::parser;
@@ -1,37 +1,345 @@
 use crate::ast::{
-    Ast, FrontmatterFormat, JsxAttributeType, NodeData, NodeIndex, NodeTag, TokenIndex,
+    Ast, FrontmatterFormat, JsxAttributeType, LinkReference, NodeData, NodeIndex, NodeTag, Span,
+    TokenIndex,
 };
 use crate::token::Tag as TokenTag;
+use crate::tokenizer::INDENTED_CODE_COLUMNS;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CodeBlockInfo<'a> {
     pub lang: Option<&'a str>,
-    pub code: &'a str,
+    /// Code between the fences. Borrowed from the source unless the fence is indented
+    /// (e.g. under a list item), in which case that indentation is stripped from each line.
+    /// Indented code blocks have four columns stripped and end with a newline.
+    pub code: Cow<'a, str>,
+    pub fence: CodeFence,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// How a code block is delimited in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeFence {
+    /// A fence of this many backticks.
+    Backtick(usize),
+    /// A fence of this many tildes.
+    Tilde(usize),
+    /// Four or more columns of indentation and no fence.
+    Indented,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct LinkInfo<'a> {
     pub label_children: &'a [NodeIndex],
-    pub url: &'a str,
+    /// Destination, resolved through the matching definition for reference links. Email
+    /// autolinks get a `mailto:` scheme and `www.` links an `http://` scheme, as in GFM.
+    pub url: Cow<'a, str>,
+    pub title: Option<Cow<'a, str>>,
+    pub reference: LinkReference,
+    /// Label of the definition a reference link resolved to.
+    pub reference_label: Option<&'a str>,
+    /// Whether the link is a bare URL or email address found in text rather than written
+    /// as link syntax. Its label keeps the text as written.
+    pub auto_detected: bool,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ImageInfo<'a> {
     pub alt_children: &'a [NodeIndex],
+    /// Destination, resolved through the matching definition for reference images.
+    pub url: Cow<'a, str>,
+    pub title: Option<Cow<'a, str>>,
+    pub reference: LinkReference,
+    /// Label of the definition a reference image resolved to.
+    pub reference_label: Option<&'a str>,
+}
+
+/// A `[label]: destination "title"` link reference definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefinitionInfo<'a> {
+    pub label: &'a str,
+    /// Destination with backslash escapes and character references decoded.
+    pub url: Cow<'a, str>,
+    /// Title with backslash escapes and character references decoded.
+    pub title: Option<Cow<'a, str>>,
+}
+
+/// TeX source of a math node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MathInfo<'a> {
+    /// The formula between the `$` delimiters, verbatim.
+    pub tex: &'a str,
+    /// `true` for `$$` blocks on their own lines, `false` for inline spans.
+    pub display: bool,
+}
+
+/// The NIP-19 entity type named by the bech32 prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NostrEntityKind {
+    Npub,
+    Note,
+    Nprofile,
+    Nevent,
+    Naddr,
+}
+
+impl NostrEntityKind {
+    /// The bech32 human-readable prefix, e.g. `"npub"`.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            NostrEntityKind::Npub => "npub",
+            NostrEntityKind::Note => "note",
+            NostrEntityKind::Nprofile => "nprofile",
+            NostrEntityKind::Nevent => "nevent",
+            NostrEntityKind::Naddr => "naddr",
+        }
+    }
+}
+
+/// A decoded NIP-19 entity. Keys and ids are lowercase hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NostrEntity {
+    pub kind: NostrEntityKind,
+    /// The profile of `npub` / `nprofile`, or the author of `naddr` and (optionally) `nevent`.
+    pub pubkey: Option<String>,
+    /// The event of `note` / `nevent`.
+    pub event_id: Option<String>,
+    /// The `d` tag of an `naddr`.
+    pub identifier: Option<String>,
+    /// The event kind of an `naddr`, or of an `nevent` when given.
+    pub event_kind: Option<u32>,
+    /// Relay hints, in order.
+    pub relays: Vec<String>,
+}
+
+/// A NIP-30 custom emoji.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CustomEmojiView<'a> {
+    /// The shortcode without colons, e.g. `pepe` for `:pepe:`.
+    pub shortcode: &'a str,
     pub url: &'a str,
 }
 
+/// A `nostr:` URI found in text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NostrReferenceView<'a> {
+    /// The bech32 entity without the `nostr:` scheme.
+    pub bech32: &'a str,
+    pub entity: NostrEntity,
+}
+
+/// A heading with its anchor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadingView<'a> {
+    pub level: u8,
+    pub children: &'a [NodeIndex],
+    /// The custom id from a trailing `{#id}` attribute, without the `#`.
+    pub id: Option<&'a str>,
+    /// The heading's unique anchor: its custom id if it has one, else the GitHub-style
+    /// slug of its text. Repeats get a `-1`, `-2`, ... suffix, including a custom id
+    /// that an earlier heading already uses.
+    pub slug: String,
+}
+
+impl HeadingView<'_> {
+    /// The fragment that links to this heading, the same as `slug`.
+    pub fn anchor(&self) -> &str {
+        &self.slug
+    }
+}
+
+/// Heading views for a whole document, as returned by [`Ast::heading_table`].
+///
+/// Build it once per render or serialization pass; each lookup is then constant time
+/// instead of slugging every heading again.
+#[derive(Debug, Clone, Default)]
+pub struct HeadingTable<'a> {
+    /// Headings in document order with their views.
+    headings: Vec<(NodeIndex, HeadingView<'a>)>,
+    /// Position in `headings` of each heading node.
+    positions: HashMap<NodeIndex, usize>,
+    /// Position in `headings` by slug.
+    slugs: HashMap<String, usize>,
+}
+
+impl<'a> HeadingTable<'a> {
+    /// Headings in document order with their views.
+    pub fn headings(&self) -> &[(NodeIndex, HeadingView<'a>)] {
+        &self.headings
+    }
+
+    /// The view of a heading node.
+    pub fn view(&self, node_index: NodeIndex) -> Option<&HeadingView<'a>> {
+        self.positions
+            .get(&node_index)
+            .map(|&position| &self.headings[position].1)
+    }
+
+    /// The heading a same-document link such as `#setup` points to.
+    pub fn resolve_fragment(&self, href: &str) -> Option<NodeIndex> {
+        let fragment = href.strip_prefix('#').unwrap_or(href);
+        self.slugs
+            .get(fragment)
+            .map(|&position| self.headings[position].0)
+    }
+}
+
+/// A heading in the document outline, with the headings nested under it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineEntry<'a> {
+    pub node: NodeIndex,
+    pub level: u8,
+    /// The heading's plain text, trimmed.
+    pub title: String,
+    /// The custom `{#id}`, if any.
+    pub id: Option<&'a str>,
+    pub slug: String,
+    pub span: Span,
+    /// Following headings of a deeper level, up to the next heading at this level or
+    /// above.
+    pub children: Vec<OutlineEntry<'a>>,
+}
+
+impl OutlineEntry<'_> {
+    /// The fragment that links to this heading, the same as `slug`.
+    pub fn anchor(&self) -> &str {
+        &self.slug
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutlineOptions {
+    /// Include headings inside JSX elements and fragments such as `<Card>`.
+    pub include_jsx: bool,
+}
+
+impl Default for OutlineOptions {
+    fn default() -> Self {
+        Self { include_jsx: true }
+    }
+}
+
+/// A blockquote, possibly a GFM alert (`> [!NOTE]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockquoteView<'a> {
+    /// The alert kind when the first line is an alert marker; the marker line itself
+    /// is not among the children.
+    pub alert: Option<AlertKind>,
+    pub children: &'a [NodeIndex],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl AlertKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertKind::Note => "note",
+            AlertKind::Tip => "tip",
+            AlertKind::Important => "important",
+            AlertKind::Warning => "warning",
+            AlertKind::Caution => "caution",
+        }
+    }
+
+    /// The marker as written in canonical source, e.g. `NOTE` in `[!NOTE]`.
+    pub fn marker(&self) -> &'static str {
+        match self {
+            AlertKind::Note => "NOTE",
+            AlertKind::Tip => "TIP",
+            AlertKind::Important => "IMPORTANT",
+            AlertKind::Warning => "WARNING",
+            AlertKind::Caution => "CAUTION",
+        }
+    }
+}
+
+/// A footnote in citation order, as returned by [`Ast::footnotes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FootnoteView<'a> {
+    /// Label as written in the definition, without `[^` and `]`.
+    pub label: &'a str,
+    /// One-based number, assigned in order of first reference.
+    pub number: u32,
+    pub definition: NodeIndex,
+    /// Reference nodes citing this footnote, in document order (the back-references).
+    pub references: Vec<NodeIndex>,
+}
+
+/// Footnote numbering for a whole document, as returned by [`Ast::footnote_table`].
+///
+/// A footnote's number depends on every reference before it, so references and
+/// definitions are looked up here rather than numbered one at a time.
+#[derive(Debug, Clone, Default)]
+pub struct FootnoteTable<'a> {
+    footnotes: Vec<FootnoteView<'a>>,
+    /// Position in `footnotes` and one-based occurrence of each resolved reference.
+    references: HashMap<NodeIndex, (usize, u32)>,
+    /// Position in `footnotes` of each cited definition.
+    definitions: HashMap<NodeIndex, usize>,
+}
+
+impl<'a> FootnoteTable<'a> {
+    /// Footnotes in citation order.
+    pub fn footnotes(&self) -> &[FootnoteView<'a>] {
+        &self.footnotes
+    }
+
+    /// The footnote a definition node defines, if anything cites it.
+    pub fn definition(&self, node_index: NodeIndex) -> Option<&FootnoteView<'a>> {
+        self.definitions
+            .get(&node_index)
+            .map(|&position| &self.footnotes[position])
+    }
+
+    /// Resolve a footnote reference node to its footnote number and definition.
+    pub fn reference(
+        &self,
+        ast: &'a Ast,
+        node_index: NodeIndex,
+    ) -> Option<FootnoteReferenceView<'a>> {
+        let &(position, occurrence) = self.references.get(&node_index)?;
+        let footnote = &self.footnotes[position];
+        Some(FootnoteReferenceView {
+            label: footnote_label(ast, node_index)?,
+            number: footnote.number,
+            definition: footnote.definition,
+            occurrence,
+        })
+    }
+}
+
+/// A `[^label]` footnote reference resolved to its footnote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FootnoteReferenceView<'a> {
+    /// The label as written at this reference.
+    pub label: &'a str,
+    pub number: u32,
+    pub definition: NodeIndex,
+    /// One-based position of this reference among those citing the same footnote.
+    pub occurrence: u32,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExpressionKind {
     Text,
     Flow,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PlainTextPart<'a> {
-    Text(&'a str),
+    /// Text with backslash escapes and character references decoded.
+    Text(Cow<'a, str>),
     Code(&'a str),
     HardBreak,
+    /// A line break within a paragraph; plain text renders it as a space.
+    SoftBreak,
     Expression {
         kind: ExpressionKind,
         source: &'a str,
@@ -73,6 +381,17 @@ pub enum JsxAttributeValue<'a> {
     Expression(&'a str),
 }
 
+impl JsxAttributeValue<'_> {
+    /// Decode a string value holding a NIP-19 entity, such as `pubkey="npub1..."` or
+    /// `id="nostr:nevent1..."`. See [`decode_nostr_entity`].
+    pub fn nostr_entity(&self) -> Option<NostrEntity> {
+        match self {
+            JsxAttributeValue::String(value) => decode_nostr_entity(value),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct JsxAttributeView<'a> {
     pub name: &'a str,
@@ -91,6 +410,9 @@ pub struct JsxElementView<'a> {
     pub attrs: Vec<JsxAttributeView<'a>>,
     pub children: &'a [NodeIndex],
     pub kind: JsxElementKind,
+    /// A lowercase HTML element such as `div` or `br` rather than a component. Void
+    /// elements such as `<br>` and `<img ...>` need no closing tag.
+    pub is_intrinsic: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -99,6 +421,45 @@ pub struct FrontmatterInfoView<'a> {
     pub value: &'a str,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EsmKind {
+    Import,
+    Export,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EsmImportKind {
+    /// `import name from "mod"`
+    Default,
+    /// `import { name } from "mod"` or `import { name as alias } from "mod"`
+    Named,
+    /// `import * as name from "mod"`
+    Namespace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EsmImportBinding<'a> {
+    pub kind: EsmImportKind,
+    /// Name exported by the module: `default`, `*`, or the named export.
+    pub imported: &'a str,
+    /// Name the binding is visible under in the document.
+    pub local: &'a str,
+    /// Module specifier, without quotes.
+    pub source: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EsmView<'a> {
+    pub kind: EsmKind,
+    /// Statement source exactly as written.
+    pub value: &'a str,
+    pub imports: Vec<EsmImportBinding<'a>>,
+    /// Names made available to importers of the document (`default` for a default export).
+    pub exports: Vec<&'a str>,
+    /// Module specifiers referenced by `from` clauses and side-effect imports.
+    pub sources: Vec<&'a str>,
+}
+
 pub fn decode_html_entities(value: &str) -> String {
     value
         .replace("&quot;", "\"")
@@ -107,6 +468,77 @@ pub fn decode_html_entities(value: &str) -> String {
         .replace("&amp;", "&")
 }
 
+/// Decode CommonMark backslash escapes (`\*`) and HTML character references (`&amp;`,
+/// `&#128512;`, `&#x1F600;`) in Markdown text. Borrows when there is nothing to decode.
+pub fn decode_markdown_text(raw: &str) -> Cow<'_, str> {
+    if !raw.contains(['\\', '&']) {
+        return Cow::Borrowed(raw);
+    }
+
+    let bytes = raw.as_bytes();
+    let mut output = String::with_capacity(raw.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if bytes.get(i + 1).is_some_and(u8::is_ascii_punctuation) => {
+                output.push_str(&raw[copied..i]);
+                copied = i + 1;
+                i += 2;
+            }
+            b'&' => {
+                output.push_str(&raw[copied..i]);
+                copied = i;
+                match push_character_reference(&raw[i..], &mut output) {
+                    Some(len) => {
+                        i += len;
+                        copied = i;
+                    }
+                    None => i += 1,
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    output.push_str(&raw[copied..]);
+
+    Cow::Owned(output)
+}
+
+/// Decode the character reference at the start of `text` into `output`, returning its
+/// length, or `None` if `text` does not start with a complete reference.
+fn push_character_reference(text: &str, output: &mut String) -> Option<usize> {
+    let body = text.strip_prefix('&')?;
+    let end = body.find(';')?;
+    let name = &body[..end];
+
+    if let Some(number) = name.strip_prefix('#') {
+        let (digits, radix, max_len) = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => (hex, 16, 6),
+            None => (number, 10, 7),
+        };
+        if digits.is_empty()
+            || digits.len() > max_len
+            || !digits.chars().all(|ch| ch.is_digit(radix))
+        {
+            return None;
+        }
+        let decoded = u32::from_str_radix(digits, radix)
+            .ok()
+            .filter(|&value| value != 0)
+            .and_then(char::from_u32)
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+        output.push(decoded);
+    } else {
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return None;
+        }
+        output.push_str(crate::entities::lookup(name)?);
+    }
+
+    Some(end + 2)
+}
+
 pub fn decode_jsx_string(raw: &str) -> String {
     let trimmed = raw.trim();
     let inner = if trimmed.len() >= 2
@@ -114,6 +546,9 @@ pub fn decode_jsx_string(raw: &str) -> String {
             || (trimmed.starts_with('\'') && trimmed.ends_with('\'')))
     {
         &trimmed[1..trimmed.len() - 1]
+    } else if let Some(rest) = trimmed.strip_prefix(['"', '\'']) {
+        // A string cut off by the end of a streamed chunk has no closing quote yet.
+        rest
     } else {
         trimmed
     };
@@ -152,13 +587,30 @@ pub fn decode_jsx_string(raw: &str) -> String {
     decode_html_entities(&output)
 }
 
-pub(crate) fn code_block_info(ast: &Ast, node_idx: NodeIndex) -> Option<CodeBlockInfo<'_>> {
+pub(crate) fn code_block_info<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<CodeBlockInfo<'a>> {
     let node = ast.nodes.get(node_idx as usize)?;
     if node.tag != NodeTag::CodeBlock {
         return None;
     }
 
     let fence_token = node.main_token;
+    let (raw, indent) = code_block_text(ast, fence_token);
+    if ast.token_tags.get(fence_token as usize) == Some(&TokenTag::CodeIndented) {
+        let mut code: String = dedented_lines(raw, indent).collect();
+        code.push('\n');
+        return Some(CodeBlockInfo {
+            lang: None,
+            code: Cow::Owned(code),
+            fence: CodeFence::Indented,
+        });
+    }
+
+    let fence_text = ast.token_slice(fence_token);
+    let fence = if fence_text.starts_with('~') {
+        CodeFence::Tilde(fence_text.len())
+    } else {
+        CodeFence::Backtick(fence_text.len())
+    };
     let lang = match ast.token_tags.get(fence_token.saturating_add(1) as usize) {
         Some(TokenTag::Text) => {
             let trimmed = ast.token_slice(fence_token.saturating_add(1)).trim();
@@ -167,39 +619,593 @@ pub(crate) fn code_block_info(ast: &Ast, node_idx: NodeIndex) -> Option<CodeBloc
         _ => None,
     };
 
-    Some(CodeBlockInfo {
-        lang,
-        code: code_block_content_from_fence(ast, fence_token),
+    let code = if indent == 0 {
+        Cow::Borrowed(raw)
+    } else {
+        Cow::Owned(dedented_lines(raw, indent).collect())
+    };
+
+    Some(CodeBlockInfo { lang, code, fence })
+}
+
+/// The raw text of a code block and the columns of indentation to strip from each of
+/// its lines: the indentation that made an indented block code, past the content column
+/// of a list item it sits in, or the indentation of the opening fence.
+fn code_block_text<'a>(ast: &'a Ast, fence_token: TokenIndex) -> (&'a str, usize) {
+    if ast.token_tags.get(fence_token as usize) == Some(&TokenTag::CodeIndented) {
+        // In a list item the token starts at the item's content column; take its first
+        // line from the line start so every line loses the same indentation.
+        let margin = code_fence_indent(ast, fence_token);
+        let text = ast.token_slice(fence_token);
+        let line_start = ast
+            .token_starts
+            .get(fence_token as usize)
+            .map_or(0, |&start| start as usize - margin);
+        let text = ast
+            .source
+            .get(line_start..line_start + margin + text.len())
+            .unwrap_or(text);
+        (text, margin + INDENTED_CODE_COLUMNS)
+    } else {
+        (
+            code_block_content_from_fence(ast, fence_token),
+            code_fence_indent(ast, fence_token),
+        )
+    }
+}
+
+/// Column of a code fence on its line: the whitespace in front of it, and the list
+/// marker when the fence opens on a list item's first line.
+fn code_fence_indent(ast: &Ast, fence_token: TokenIndex) -> usize {
+    let Some(&start) = ast.token_starts.get(fence_token as usize) else {
+        return 0;
+    };
+    ast.source.as_bytes()[..start as usize]
+        .iter()
+        .rev()
+        .take_while(|&&b| b != b'\n')
+        .count()
+}
+
+/// Lines of `code` (newlines included) with up to `indent` columns of leading
+/// whitespace removed. Tabs advance to the next multiple of four columns.
+fn dedented_lines(code: &str, indent: usize) -> impl Iterator<Item = &str> {
+    code.split_inclusive('\n').map(move |line| {
+        let mut columns = 0;
+        let strip = line
+            .bytes()
+            .take_while(|&b| {
+                if columns >= indent || !(b == b' ' || b == b'\t') {
+                    return false;
+                }
+                columns = if b == b'\t' {
+                    columns + 4 - columns % 4
+                } else {
+                    columns + 1
+                };
+                true
+            })
+            .count();
+        &line[strip..]
     })
 }
 
-pub(crate) fn link_view(ast: &Ast, node_idx: NodeIndex) -> Option<LinkInfo<'_>> {
+pub(crate) fn link_view<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<LinkInfo<'a>> {
     let node = ast.nodes.get(node_idx as usize)?;
     if node.tag != NodeTag::Link {
         return None;
     }
 
-    let info = ast.link_info(node_idx);
+    let (url, title, reference_label) = link_destination(ast, node_idx);
+    let reference = ast.link_info(node_idx).reference;
     Some(LinkInfo {
         label_children: ast.link_children(node_idx),
-        url: ast.token_slice(info.url_token),
+        url,
+        title,
+        reference,
+        reference_label,
+        auto_detected: reference == LinkReference::Literal,
     })
 }
 
-pub(crate) fn image_view(ast: &Ast, node_idx: NodeIndex) -> Option<ImageInfo<'_>> {
+pub(crate) fn image_view<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<ImageInfo<'a>> {
     let node = ast.nodes.get(node_idx as usize)?;
     if node.tag != NodeTag::Image {
         return None;
     }
 
-    let info = ast.link_info(node_idx);
+    let (url, title, reference_label) = link_destination(ast, node_idx);
     Some(ImageInfo {
         alt_children: ast.link_children(node_idx),
-        url: ast.token_slice(info.url_token),
+        url,
+        title,
+        reference: ast.link_info(node_idx).reference,
+        reference_label,
+    })
+}
+
+/// Destination of an autolink: email addresses get `mailto:` and `www.` links `http://`.
+fn autolink_destination(url: &str) -> Cow<'_, str> {
+    if url.starts_with("www.") {
+        Cow::Owned(format!("http://{url}"))
+    } else if url.contains('@') && !url.contains(':') {
+        Cow::Owned(format!("mailto:{url}"))
+    } else {
+        Cow::Borrowed(url)
+    }
+}
+
+/// Decoded destination, decoded title and (for references) definition label of a link
+/// or image. Autolinks are taken literally, as escapes do not apply inside them.
+fn link_destination<'a>(
+    ast: &'a Ast,
+    node_idx: NodeIndex,
+) -> (Cow<'a, str>, Option<Cow<'a, str>>, Option<&'a str>) {
+    let info = ast.link_info(node_idx);
+    let raw = ast.token_slice(info.url_token);
+    match info.reference {
+        LinkReference::Inline => {
+            let (url, title) = split_link_destination(raw.trim()).unwrap_or((raw, None));
+            (
+                decode_markdown_text(url),
+                title.map(decode_markdown_text),
+                None,
+            )
+        }
+        LinkReference::Autolink | LinkReference::Literal => (autolink_destination(raw), None, None),
+        LinkReference::Full | LinkReference::Collapsed | LinkReference::Shortcut => {
+            match split_link_definition(raw) {
+                Some(def) => (def.url, def.title, Some(def.label)),
+                None => (Cow::Borrowed(""), None, None),
+            }
+        }
+    }
+}
+
+/// Label of a `[^label]` or `[^label]:` footnote token.
+pub(crate) fn footnote_token_label(token: &str) -> &str {
+    let label = token.strip_prefix("[^").unwrap_or(token);
+    label.split(']').next().unwrap_or(label)
+}
+
+pub(crate) fn footnote_label<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<&'a str> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    match node.tag {
+        NodeTag::FootnoteReference | NodeTag::FootnoteDefinition => {
+            Some(footnote_token_label(ast.token_slice(node.main_token)))
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn footnote_table<'a>(ast: &'a Ast) -> FootnoteTable<'a> {
+    let mut definitions: HashMap<String, NodeIndex> = HashMap::new();
+    let mut references: Vec<NodeIndex> = Vec::new();
+    for (idx, node) in ast.nodes.iter().enumerate() {
+        let idx = idx as NodeIndex;
+        match node.tag {
+            NodeTag::FootnoteDefinition => {
+                let label = normalize_link_label(footnote_label(ast, idx).unwrap_or(""));
+                definitions.entry(label).or_insert(idx);
+            }
+            NodeTag::FootnoteReference => references.push(idx),
+            _ => {}
+        }
+    }
+    references.sort_by_key(|&idx| ast.nodes[idx as usize].main_token);
+
+    let mut table = FootnoteTable::default();
+    for reference in references {
+        let label = normalize_link_label(footnote_label(ast, reference).unwrap_or(""));
+        let Some(&definition) = definitions.get(&label) else {
+            continue;
+        };
+        let position = *table.definitions.entry(definition).or_insert_with(|| {
+            table.footnotes.push(FootnoteView {
+                label: footnote_label(ast, definition).unwrap_or(""),
+                number: table.footnotes.len() as u32 + 1,
+                definition,
+                references: Vec::new(),
+            });
+            table.footnotes.len() - 1
+        });
+        let footnote = &mut table.footnotes[position];
+        footnote.references.push(reference);
+        table
+            .references
+            .insert(reference, (position, footnote.references.len() as u32));
+    }
+    table
+}
+
+pub(crate) fn footnotes<'a>(ast: &'a Ast) -> Vec<FootnoteView<'a>> {
+    footnote_table(ast).footnotes
+}
+
+pub(crate) fn footnote_reference_view<'a>(
+    ast: &'a Ast,
+    node_idx: NodeIndex,
+) -> Option<FootnoteReferenceView<'a>> {
+    if ast.nodes.get(node_idx as usize)?.tag != NodeTag::FootnoteReference {
+        return None;
+    }
+    footnote_table(ast).reference(ast, node_idx)
+}
+
+/// Decode a NIP-19 entity such as `npub1...`, with or without the `nostr:` scheme.
+///
+/// Useful for JSX attributes like `pubkey="npub1..."` or `id="nostr:nevent1..."`; the
+/// checksum is verified and nothing is fetched.
+pub fn decode_nostr_entity(value: &str) -> Option<NostrEntity> {
+    let value = value.trim();
+    crate::nostr::decode(value.strip_prefix("nostr:").unwrap_or(value))
+}
+
+pub(crate) fn nostr_reference_view<'a>(
+    ast: &'a Ast,
+    node_idx: NodeIndex,
+) -> Option<NostrReferenceView<'a>> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    if node.tag != NodeTag::NostrReference {
+        return None;
+    }
+    let uri = ast.token_slice(node.main_token);
+    let bech32 = uri.strip_prefix("nostr:").unwrap_or(uri);
+    Some(NostrReferenceView {
+        bech32,
+        entity: crate::nostr::decode(bech32)?,
+    })
+}
+
+pub(crate) fn custom_emoji_view<'a>(
+    ast: &'a Ast,
+    node_idx: NodeIndex,
+) -> Option<CustomEmojiView<'a>> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    if node.tag != NodeTag::CustomEmoji {
+        return None;
+    }
+    let shortcode = ast
+        .token_slice(node.main_token)
+        .strip_prefix(':')?
+        .strip_suffix(':')?;
+    let url = ast.custom_emoji.get(shortcode)?;
+    Some(CustomEmojiView { shortcode, url })
+}
+
+pub(crate) fn hashtag_name<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<&'a str> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    if node.tag != NodeTag::Hashtag {
+        return None;
+    }
+    ast.token_slice(node.main_token).strip_prefix('#')
+}
+
+pub(crate) fn mention_name<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<&'a str> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    if node.tag != NodeTag::Mention {
+        return None;
+    }
+    ast.token_slice(node.main_token).strip_prefix('@')
+}
+
+/// Lowercased hashtag names in order of first appearance, without duplicates, ready to
+/// be used as Nostr `t` tags.
+pub(crate) fn collect_hashtags(ast: &Ast) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for idx in 0..ast.nodes.len() as NodeIndex {
+        if let Some(name) = hashtag_name(ast, idx) {
+            let tag = name.to_lowercase();
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}
+
+pub(crate) fn math_info<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<MathInfo<'a>> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    let raw = ast.token_slice(node.main_token);
+    match node.tag {
+        NodeTag::MathInline => {
+            let delimiter = raw.len() - raw.trim_start_matches('$').len();
+            let tex = raw
+                .get(delimiter..raw.len().saturating_sub(delimiter))
+                .unwrap_or("");
+            Some(MathInfo {
+                tex,
+                display: false,
+            })
+        }
+        NodeTag::MathBlock => {
+            // Everything between the opening and closing `$$` lines.
+            let body = raw.split_once('\n').map_or("", |(_, rest)| rest);
+            let tex = body.rsplit_once('\n').map_or("", |(tex, _)| tex);
+            Some(MathInfo { tex, display: true })
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn blockquote_view<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<BlockquoteView<'a>> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    if node.tag != NodeTag::Blockquote {
+        return None;
+    }
+    let alert = match ast.token_tags.get(node.main_token as usize) {
+        Some(TokenTag::BlockquoteStart) => {
+            let line_start = token_end(ast, node.main_token) as usize;
+            let rest = ast.source.get(line_start..).unwrap_or("");
+            alert_marker(rest.split('\n').next().unwrap_or(""))
+        }
+        _ => None,
+    };
+    Some(BlockquoteView {
+        alert,
+        children: ast.children(node_idx),
     })
 }
 
-pub(crate) fn frontmatter_view(ast: &Ast, node_idx: NodeIndex) -> Option<FrontmatterInfoView<'_>> {
+pub(crate) fn heading_view<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<HeadingView<'a>> {
+    if ast.nodes.get(node_idx as usize)?.tag != NodeTag::Heading {
+        return None;
+    }
+    heading_table(ast).view(node_idx).cloned()
+}
+
+/// Every heading in document order with its view. A custom id is the anchor of the
+/// first heading that sets it; the other headings are slugged in order, skipping every
+/// anchor already taken.
+pub(crate) fn heading_table<'a>(ast: &'a Ast) -> HeadingTable<'a> {
+    let mut headings: Vec<NodeIndex> = (0..ast.nodes.len() as NodeIndex)
+        .filter(|&idx| ast.nodes[idx as usize].tag == NodeTag::Heading)
+        .collect();
+    headings.sort_by_key(|&idx| ast.nodes[idx as usize].main_token);
+
+    let heading_id = |idx: NodeIndex| {
+        let token = ast.heading_info(idx).id_token?;
+        heading_id_attribute(ast.token_slice(token))
+    };
+    // The first heading to use each custom id owns it.
+    let mut id_owners: HashMap<&str, NodeIndex> = HashMap::new();
+    for &idx in &headings {
+        if let Some(id) = heading_id(idx) {
+            id_owners.entry(id).or_insert(idx);
+        }
+    }
+    let mut taken: HashSet<String> = id_owners.keys().map(|id| id.to_string()).collect();
+
+    let mut table = HeadingTable::default();
+    for idx in headings {
+        let id = heading_id(idx);
+        let slug = match id {
+            Some(id) if id_owners.get(id) == Some(&idx) => id.to_string(),
+            Some(id) => unique_slug(id, &mut taken),
+            None => unique_slug(
+                &heading_slug(&ast.plain_text(idx).unwrap_or_default()),
+                &mut taken,
+            ),
+        };
+        let position = table.headings.len();
+        table.positions.insert(idx, position);
+        table.slugs.insert(slug.clone(), position);
+        table.headings.push((
+            idx,
+            HeadingView {
+                level: ast.heading_info(idx).level,
+                children: ast.children(idx),
+                id,
+                slug,
+            },
+        ));
+    }
+    table
+}
+
+/// `base`, or `base` with the first `-1`, `-2`, ... suffix not yet in `taken`, which
+/// it is then added to.
+fn unique_slug(base: &str, taken: &mut HashSet<String>) -> String {
+    let mut slug = base.to_string();
+    let mut suffix = 0;
+    while !taken.insert(slug.clone()) {
+        suffix += 1;
+        slug = format!("{base}-{suffix}");
+    }
+    slug
+}
+
+/// The heading a same-document link such as `#setup` points to.
+pub(crate) fn resolve_fragment(ast: &Ast, href: &str) -> Option<NodeIndex> {
+    heading_table(ast).resolve_fragment(href)
+}
+
+pub(crate) fn outline<'a>(ast: &'a Ast, options: &OutlineOptions) -> Vec<OutlineEntry<'a>> {
+    let mut headings = Vec::new();
+    if let Some(document) = ast.nodes.iter().position(|n| n.tag == NodeTag::Document) {
+        collect_outline_headings(ast, document as NodeIndex, options, &mut headings);
+    }
+    let mut views: Vec<Option<HeadingView<'a>>> = Vec::new();
+    views.resize_with(ast.nodes.len(), || None);
+    for (idx, view) in heading_table(ast).headings {
+        views[idx as usize] = Some(view);
+    }
+
+    // Entries whose subtree may still grow, shallowest first.
+    let mut open: Vec<OutlineEntry<'a>> = Vec::new();
+    let mut roots = Vec::new();
+    for idx in headings {
+        let Some(view) = views[idx as usize].take() else {
+            continue;
+        };
+        let entry = OutlineEntry {
+            node: idx,
+            level: view.level,
+            title: ast
+                .plain_text(idx)
+                .map(|text| text.trim().to_string())
+                .unwrap_or_default(),
+            id: view.id,
+            slug: view.slug,
+            span: ast.node_span(idx),
+            children: Vec::new(),
+        };
+        close_outline_entries(&mut open, &mut roots, entry.level);
+        open.push(entry);
+    }
+    close_outline_entries(&mut open, &mut roots, 0);
+    roots
+}
+
+/// Headings under `node_idx` in document order, skipping JSX subtrees unless enabled.
+fn collect_outline_headings(
+    ast: &Ast,
+    node_idx: NodeIndex,
+    options: &OutlineOptions,
+    out: &mut Vec<NodeIndex>,
+) {
+    for &child in ast.children(node_idx) {
+        match ast.nodes[child as usize].tag {
+            NodeTag::Heading => out.push(child),
+            NodeTag::MdxJsxElement | NodeTag::MdxJsxFragment if !options.include_jsx => {}
+            _ => collect_outline_headings(ast, child, options, out),
+        }
+    }
+}
+
+/// Pop open entries at `level` or deeper, attaching each to its parent.
+fn close_outline_entries<'a>(
+    open: &mut Vec<OutlineEntry<'a>>,
+    roots: &mut Vec<OutlineEntry<'a>>,
+    level: u8,
+) {
+    while let Some(entry) = open.pop_if(|entry| entry.level >= level) {
+        match open.last_mut() {
+            Some(parent) => parent.children.push(entry),
+            None => roots.push(entry),
+        }
+    }
+}
+
+/// The id of a `{#id}` heading attribute, given the text between the braces.
+pub(crate) fn heading_id_attribute(text: &str) -> Option<&str> {
+    let id = text.strip_prefix('#')?;
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, '-' | '_' | ':' | '.'));
+    valid.then_some(id)
+}
+
+/// Slug heading text the way GitHub does: lowercase, drop punctuation and symbols, and
+/// turn each space into `-`.
+fn heading_slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|ch| match ch {
+            ' ' => Some('-'),
+            '-' | '_' => Some(ch),
+            _ if ch.is_alphanumeric() => Some(ch),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The alert kind of a blockquote line consisting only of `[!KIND]` (any case).
+pub(crate) fn alert_marker(line: &str) -> Option<AlertKind> {
+    let kind = line.trim().strip_prefix("[!")?.strip_suffix(']')?;
+    [
+        AlertKind::Note,
+        AlertKind::Tip,
+        AlertKind::Important,
+        AlertKind::Warning,
+        AlertKind::Caution,
+    ]
+    .into_iter()
+    .find(|alert| alert.marker().eq_ignore_ascii_case(kind))
+}
+
+pub(crate) fn definition_view<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<DefinitionInfo<'a>> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    if node.tag != NodeTag::Definition {
+        return None;
+    }
+    split_link_definition(ast.token_slice(node.main_token))
+}
+
+/// Split a `[label]: destination "title"` line into its parts, or `None` when the line
+/// is not a well-formed definition.
+pub(crate) fn split_link_definition(line: &str) -> Option<DefinitionInfo<'_>> {
+    let rest = line.strip_prefix('[')?;
+    let bytes = rest.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'[' => return None,
+            b']' => break,
+            _ => i += 1,
+        }
+    }
+    let label = rest.get(..i)?;
+    if label.trim().is_empty() || label.len() > 999 {
+        return None;
+    }
+    let after = rest[i..].strip_prefix("]:")?;
+    let (url, title) = split_link_destination(after.trim())?;
+    Some(DefinitionInfo {
+        label,
+        url: decode_markdown_text(url),
+        title: title.map(decode_markdown_text),
+    })
+}
+
+/// Split `destination "title"` into the destination (without `<>`) and optional title
+/// (without its quotes). `None` when there is no destination or trailing junk.
+fn split_link_destination(raw: &str) -> Option<(&str, Option<&str>)> {
+    let (url, rest) = if let Some(inner) = raw.strip_prefix('<') {
+        let end = inner.find(['>', '<'])?;
+        if inner.as_bytes()[end] != b'>' {
+            return None;
+        }
+        (&inner[..end], &inner[end + 1..])
+    } else {
+        let end = raw.find([' ', '\t']).unwrap_or(raw.len());
+        (&raw[..end], &raw[end..])
+    };
+    if url.is_empty() && !raw.starts_with('<') {
+        return None;
+    }
+
+    let rest = rest.trim();
+    if rest.is_empty() {
+        return Some((url, None));
+    }
+    let close = match rest.as_bytes()[0] {
+        b'"' => '"',
+        b'\'' => '\'',
+        b'(' => ')',
+        _ => return None,
+    };
+    let inner = rest[1..].strip_suffix(close)?;
+    Some((url, Some(inner)))
+}
+
+/// Normalize a link label for matching references to definitions: case-insensitive,
+/// with runs of whitespace collapsed.
+pub(crate) fn normalize_link_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub(crate) fn frontmatter_view<'a>(
+    ast: &'a Ast,
+    node_idx: NodeIndex,
+) -> Option<FrontmatterInfoView<'a>> {
     let node = ast.nodes.get(node_idx as usize)?;
     if node.tag != NodeTag::Frontmatter {
         return None;
@@ -212,7 +1218,7 @@ pub(crate) fn frontmatter_view(ast: &Ast, node_idx: NodeIndex) -> Option<Frontma
     })
 }
 
-pub(crate) fn expression_info(ast: &Ast, node_idx: NodeIndex) -> Option<ExpressionInfo<'_>> {
+pub(crate) fn expression_info<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<ExpressionInfo<'a>> {
     let node = ast.nodes.get(node_idx as usize)?;
     let kind = match node.tag {
         NodeTag::MdxTextExpression => ExpressionKind::Text,
@@ -231,10 +1237,381 @@ pub(crate) fn expression_info(ast: &Ast, node_idx: NodeIndex) -> Option<Expressi
     Some(ExpressionInfo { kind, value })
 }
 
-pub(crate) fn jsx_attribute_views(
-    ast: &Ast,
+pub(crate) fn text_value<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<Cow<'a, str>> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    if node.tag != NodeTag::Text {
+        return None;
+    }
+
+    Some(decode_markdown_text(ast.token_slice(node.main_token)))
+}
+
+pub(crate) fn recovery_source<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<&'a str> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    if node.tag != NodeTag::Recovery {
+        return None;
+    }
+
+    Some(match node.data {
+        NodeData::Extra(idx) => {
+            let range = ast.extra_range(idx);
+            token_range_source(ast, range.start, range.end).trim_end()
+        }
+        _ => "",
+    })
+}
+
+pub(crate) fn esm_view<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<EsmView<'a>> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    let kind = match node.tag {
+        NodeTag::MdxEsmImport => EsmKind::Import,
+        NodeTag::MdxEsmExport => EsmKind::Export,
+        _ => return None,
+    };
+
+    let value = ast.token_slice(node.main_token).trim_end();
+    let mut view = EsmView {
+        kind,
+        value,
+        imports: Vec::new(),
+        exports: Vec::new(),
+        sources: Vec::new(),
+    };
+
+    let lexemes = js_lexemes(value);
+    for statement in lexemes.split(|&lexeme| lexeme == ";") {
+        let mut cursor = EsmCursor {
+            lexemes: statement,
+            pos: 0,
+        };
+        match cursor.next() {
+            Some("import") => cursor.import_clause(&mut view),
+            Some("export") => cursor.export_clause(&mut view),
+            _ => {}
+        }
+    }
+
+    Some(view)
+}
+
+/// Split JavaScript source into identifiers, string literals, `...` and single punctuation
+/// characters, dropping whitespace and comments. Enough to read binding names without a
+/// full JavaScript parser.
+fn js_lexemes(source: &str) -> Vec<&str> {
+    let bytes = source.as_bytes();
+    let mut lexemes = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                i += 1;
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !bytes[i..].starts_with(b"*/") {
+                    i += 1;
+                }
+                i = (i + 2).min(bytes.len());
+                continue;
+            }
+            quote @ (b'"' | b'\'' | b'`') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+            }
+            b'.' if bytes[i..].starts_with(b"...") => i += 3,
+            b if b == b'_' || b == b'$' || b.is_ascii_alphanumeric() || b >= 0x80 => {
+                while i < bytes.len()
+                    && (bytes[i] == b'_'
+                        || bytes[i] == b'$'
+                        || bytes[i].is_ascii_alphanumeric()
+                        || bytes[i] >= 0x80)
+                {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+        if let Some(lexeme) = source.get(start..i) {
+            lexemes.push(lexeme);
+        }
+    }
+
+    lexemes
+}
+
+fn is_js_identifier(lexeme: &str) -> bool {
+    lexeme
+        .bytes()
+        .next()
+        .is_some_and(|b| b == b'_' || b == b'$' || b.is_ascii_alphabetic() || b >= 0x80)
+}
+
+fn unquote_js_string(lexeme: &str) -> Option<&str> {
+    let first = *lexeme.as_bytes().first()?;
+    if !matches!(first, b'"' | b'\'') || lexeme.len() < 2 || !lexeme.ends_with(first as char) {
+        return None;
+    }
+    Some(&lexeme[1..lexeme.len() - 1])
+}
+
+struct EsmCursor<'l, 'a> {
+    lexemes: &'l [&'a str],
+    pos: usize,
+}
+
+impl<'a> EsmCursor<'_, 'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.lexemes.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let lexeme = self.peek()?;
+        self.pos += 1;
+        Some(lexeme)
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    /// Module specifier after `from`, if present.
+    fn module_specifier(&mut self) -> Option<&'a str> {
+        if !self.eat("from") {
+            return None;
+        }
+        self.next().and_then(unquote_js_string)
+    }
+
+    fn import_clause(&mut self, view: &mut EsmView<'a>) {
+        if let Some(source) = self.peek().and_then(unquote_js_string) {
+            view.sources.push(source);
+            return;
+        }
+        if self.peek() == Some("type") && self.lexemes.get(self.pos + 1) != Some(&"from") {
+            self.pos += 1;
+        }
+
+        let mut bindings = Vec::new();
+        loop {
+            match self.peek() {
+                Some("*") => {
+                    self.pos += 1;
+                    if self.eat("as")
+                        && let Some(local) = self.next()
+                    {
+                        bindings.push((EsmImportKind::Namespace, "*", local));
+                    }
+                }
+                Some("{") => {
+                    self.pos += 1;
+                    for (imported, local) in self.specifier_list() {
+                        bindings.push((EsmImportKind::Named, imported, local));
+                    }
+                }
+                Some(name) if name != "from" && is_js_identifier(name) => {
+                    self.pos += 1;
+                    bindings.push((EsmImportKind::Default, "default", name));
+                }
+                _ => break,
+            }
+            if !self.eat(",") {
+                break;
+            }
+        }
+
+        let source = self.module_specifier().unwrap_or("");
+        if !source.is_empty() {
+            view.sources.push(source);
+        }
+        view.imports.extend(
+            bindings
+                .into_iter()
+                .map(|(kind, imported, local)| EsmImportBinding {
+                    kind,
+                    imported,
+                    local,
+                    source,
+                }),
+        );
+    }
+
+    /// `name`, `name as alias`, `"string name" as alias` entries up to the closing `}`,
+    /// returned as `(name, alias)` pairs.
+    fn specifier_list(&mut self) -> Vec<(&'a str, &'a str)> {
+        let mut specifiers = Vec::new();
+        while let Some(lexeme) = self.next() {
+            match lexeme {
+                "}" => break,
+                "," => continue,
+                "type"
+                    if self
+                        .peek()
+                        .is_some_and(|next| !matches!(next, "," | "}" | "as")) =>
+                {
+                    continue;
+                }
+                name => {
+                    let name = unquote_js_string(name).unwrap_or(name);
+                    let alias = if self.eat("as") {
+                        self.next()
+                            .map(|alias| unquote_js_string(alias).unwrap_or(alias))
+                    } else {
+                        None
+                    };
+                    specifiers.push((name, alias.unwrap_or(name)));
+                }
+            }
+        }
+        specifiers
+    }
+
+    fn export_clause(&mut self, view: &mut EsmView<'a>) {
+        match self.next() {
+            Some("default") => view.exports.push("default"),
+            Some("*") => {
+                if self.eat("as")
+                    && let Some(name) = self.next()
+                {
+                    view.exports.push(unquote_js_string(name).unwrap_or(name));
+                }
+                if let Some(source) = self.module_specifier() {
+                    view.sources.push(source);
+                }
+            }
+            Some("{") => {
+                for (_, exported) in self.specifier_list() {
+                    view.exports.push(exported);
+                }
+                if let Some(source) = self.module_specifier() {
+                    view.sources.push(source);
+                }
+            }
+            Some("const" | "let" | "var") => self.declarators(view),
+            Some("async") => {
+                self.eat("function");
+                self.eat("*");
+                self.declared_name(view);
+            }
+            Some("function") => {
+                self.eat("*");
+                self.declared_name(view);
+            }
+            Some("class" | "type" | "interface" | "enum") => self.declared_name(view),
+            _ => {}
+        }
+    }
+
+    fn declared_name(&mut self, view: &mut EsmView<'a>) {
+        if let Some(name) = self.peek().filter(|name| is_js_identifier(name)) {
+            self.pos += 1;
+            view.exports.push(name);
+        }
+    }
+
+    /// `a = 1, { b, c: d } = obj, [e, ...f] = list`
+    fn declarators(&mut self, view: &mut EsmView<'a>) {
+        loop {
+            self.binding_pattern(&mut view.exports);
+            if self.eat("=") {
+                self.skip_until(&[","]);
+            }
+            if !self.eat(",") {
+                break;
+            }
+        }
+    }
+
+    /// Collect the names bound by an identifier, object or array pattern.
+    fn binding_pattern(&mut self, names: &mut Vec<&'a str>) {
+        match self.next() {
+            Some("{") => loop {
+                match self.next() {
+                    None | Some("}") => break,
+                    Some(",") => {}
+                    Some("...") => self.binding_pattern(names),
+                    Some(key) => {
+                        if key == "[" {
+                            self.skip_until(&["]"]);
+                            self.pos += 1;
+                        }
+                        if self.eat(":") {
+                            self.binding_pattern(names);
+                        } else if is_js_identifier(key) {
+                            names.push(key);
+                        }
+                        if self.eat("=") {
+                            self.skip_until(&[",", "}"]);
+                        }
+                    }
+                }
+            },
+            Some("[") => loop {
+                match self.peek() {
+                    None => break,
+                    Some("]") => {
+                        self.pos += 1;
+                        break;
+                    }
+                    Some(",") => self.pos += 1,
+                    Some("...") => {
+                        self.pos += 1;
+                        self.binding_pattern(names);
+                    }
+                    Some(_) => {
+                        self.binding_pattern(names);
+                        if self.eat("=") {
+                            self.skip_until(&[",", "]"]);
+                        }
+                    }
+                }
+            },
+            Some(name) if is_js_identifier(name) => names.push(name),
+            _ => {}
+        }
+    }
+
+    /// Advance to the next lexeme in `stops` that is not nested inside brackets.
+    fn skip_until(&mut self, stops: &[&str]) {
+        let mut depth = 0usize;
+        while let Some(lexeme) = self.peek() {
+            if depth == 0 && stops.contains(&lexeme) {
+                return;
+            }
+            match lexeme {
+                "{" | "(" | "[" => depth += 1,
+                "}" | ")" | "]" => {
+                    if depth == 0 {
+                        return;
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+pub(crate) fn jsx_attribute_views<'a>(
+    ast: &'a Ast,
     node_idx: NodeIndex,
-) -> Option<Vec<JsxAttributeView<'_>>> {
+) -> Option<Vec<JsxAttributeView<'a>>> {
     let node = ast.nodes.get(node_idx as usize)?;
     if node.tag != NodeTag::MdxJsxElement && node.tag != NodeTag::MdxJsxSelfClosing {
         return None;
@@ -282,7 +1659,10 @@ pub(crate) fn jsx_attribute_views(
     Some(attrs)
 }
 
-pub(crate) fn jsx_element_view(ast: &Ast, node_idx: NodeIndex) -> Option<JsxElementView<'_>> {
+pub(crate) fn jsx_element_view<'a>(
+    ast: &'a Ast,
+    node_idx: NodeIndex,
+) -> Option<JsxElementView<'a>> {
     let node = ast.nodes.get(node_idx as usize)?;
     let kind = match node.tag {
         NodeTag::MdxJsxElement => JsxElementKind::Normal,
@@ -303,9 +1683,51 @@ pub(crate) fn jsx_element_view(ast: &Ast, node_idx: NodeIndex) -> Option<JsxElem
         attrs: jsx_attribute_views(ast, node_idx).unwrap_or_default(),
         children: ast.children(node_idx),
         kind,
+        is_intrinsic: is_intrinsic_jsx_name(name),
     })
 }
 
+/// Whether a JSX name refers to an HTML element: it starts with a lowercase letter and
+/// is not a member expression (`ui.card`). Matches how MDX decides between a string tag
+/// and a component reference.
+fn is_intrinsic_jsx_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase()) && !name.contains('.')
+}
+
+/// HTML elements that never have children or a closing tag, so `<br>` needs no `</br>`.
+pub(crate) fn is_void_html_element(name: &str) -> bool {
+    matches!(
+        name,
+        "area"
+            | "base"
+            | "br"
+            | "col"
+            | "embed"
+            | "hr"
+            | "img"
+            | "input"
+            | "link"
+            | "meta"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+/// Text between `<!--` and `-->` of an HTML comment node.
+pub(crate) fn html_comment_text<'a>(ast: &'a Ast, node_idx: NodeIndex) -> Option<&'a str> {
+    let node = ast.nodes.get(node_idx as usize)?;
+    if node.tag != NodeTag::HtmlComment {
+        return None;
+    }
+    let raw = ast.token_slice(node.main_token);
+    Some(
+        raw.strip_prefix("<!--")
+            .and_then(|rest| rest.strip_suffix("-->"))
+            .unwrap_or(raw),
+    )
+}
+
 pub(crate) fn jsx_attribute_type_name(value: &JsxAttributeValue<'_>) -> &'static str {
     match value {
         JsxAttributeValue::String(_) => "string",
@@ -315,7 +1737,10 @@ pub(crate) fn jsx_attribute_type_name(value: &JsxAttributeValue<'_>) -> &'static
     }
 }
 
-pub(crate) fn plain_text_parts(ast: &Ast, node_idx: NodeIndex) -> Option<Vec<PlainTextPart<'_>>> {
+pub(crate) fn plain_text_parts<'a>(
+    ast: &'a Ast,
+    node_idx: NodeIndex,
+) -> Option<Vec<PlainTextPart<'a>>> {
     ast.nodes.get(node_idx as usize)?;
 
     let mut out = Vec::new();
@@ -411,7 +1836,7 @@ fn token_end(ast: &Ast, token: TokenIndex) -> u32 {
     }
 }
 
-fn source_slice(ast: &Ast, start: u32, end: u32) -> &str {
+fn source_slice<'a>(ast: &'a Ast, start: u32, end: u32) -> &'a str {
     if start >= end {
         return "";
     }
@@ -441,10 +1866,16 @@ fn collect_plain_text_parts<'a>(
             if text.is_empty() {
                 false
             } else {
-                out.push(PlainTextPart::Text(text));
+                out.push(PlainTextPart::Text(decode_markdown_text(text)));
                 true
             }
         }
+        NodeTag::NostrReference | NodeTag::Hashtag | NodeTag::Mention | NodeTag::CustomEmoji => {
+            out.push(PlainTextPart::Text(Cow::Borrowed(
+                ast.token_slice(node.main_token),
+            )));
+            true
+        }
         NodeTag::CodeInline => {
             let text = match node.data {
                 NodeData::Token(token) => ast.token_slice(token),
@@ -458,13 +1889,20 @@ fn collect_plain_text_parts<'a>(
             }
         }
         NodeTag::CodeBlock => {
-            let text = code_block_info(ast, node_idx)
-                .map(|info| info.code)
-                .unwrap_or("");
+            let (text, indent) = code_block_text(ast, node.main_token);
             if text.is_empty() {
                 false
             } else {
-                out.push(PlainTextPart::Code(text));
+                out.extend(dedented_lines(text, indent).map(PlainTextPart::Code));
+                true
+            }
+        }
+        NodeTag::MathInline | NodeTag::MathBlock => {
+            let tex = math_info(ast, node_idx).map_or("", |info| info.tex);
+            if tex.is_empty() {
+                false
+            } else {
+                out.push(PlainTextPart::Code(tex));
                 true
             }
         }
@@ -472,6 +1910,10 @@ fn collect_plain_text_parts<'a>(
             out.push(PlainTextPart::HardBreak);
             true
         }
+        NodeTag::SoftBreak => {
+            out.push(PlainTextPart::SoftBreak);
+            true
+        }
         NodeTag::MdxTextExpression | NodeTag::MdxFlowExpression => {
             if let Some(info) = expression_info(ast, node_idx) {
                 out.push(PlainTextPart::Expression {
@@ -503,6 +1945,15 @@ fn collect_plain_text_parts<'a>(
             }
             false
         }
+        NodeTag::Recovery => {
+            let text = recovery_source(ast, node_idx).unwrap_or("");
+            if text.is_empty() {
+                false
+            } else {
+                out.push(PlainTextPart::Text(Cow::Borrowed(text)));
+                true
+            }
+        }
         NodeTag::Image => image_view(ast, node_idx)
             .map(|info| {
                 collect_plain_text_children(ast, info.alt_children, out, ChildSeparator::None)
@@ -512,12 +1963,16 @@ fn collect_plain_text_parts<'a>(
         | NodeTag::Blockquote
         | NodeTag::ListUnordered
         | NodeTag::ListOrdered
-        | NodeTag::ListItem
         | NodeTag::Table => {
             collect_plain_text_children(ast, ast.children(node_idx), out, ChildSeparator::HardBreak)
         }
-        NodeTag::MdxJsxElement | NodeTag::MdxJsxFragment => {
-            collect_plain_text_children_smart_jsx(ast, ast.children(node_idx), out)
+        // Tight list items hold their inline content directly, so only block children
+        // are separated by breaks.
+        NodeTag::ListItem
+        | NodeTag::FootnoteDefinition
+        | NodeTag::MdxJsxElement
+        | NodeTag::MdxJsxFragment => {
+            collect_plain_text_children_smart(ast, ast.children(node_idx), out)
         }
         NodeTag::TableRow => collect_plain_text_children(
             ast,
@@ -530,6 +1985,11 @@ fn collect_plain_text_parts<'a>(
         | NodeTag::Strong
         | NodeTag::Emphasis
         | NodeTag::Strikethrough
+        | NodeTag::Spoiler
+        | NodeTag::Highlight
+        | NodeTag::Underline
+        | NodeTag::Superscript
+        | NodeTag::Subscript
         | NodeTag::TableCell => {
             collect_plain_text_children(ast, ast.children(node_idx), out, ChildSeparator::None)
         }
@@ -538,7 +1998,10 @@ fn collect_plain_text_parts<'a>(
         | NodeTag::MdxJsxSelfClosing
         | NodeTag::MdxJsxAttribute
         | NodeTag::MdxEsmImport
-        | NodeTag::MdxEsmExport => false,
+        | NodeTag::MdxEsmExport
+        | NodeTag::Definition
+        | NodeTag::FootnoteReference
+        | NodeTag::HtmlComment => false,
     }
 }
 
@@ -567,7 +2030,7 @@ fn collect_plain_text_children<'a>(
     wrote_any
 }
 
-fn collect_plain_text_children_smart_jsx<'a>(
+fn collect_plain_text_children_smart<'a>(
     ast: &'a Ast,
     children: &[NodeIndex],
     out: &mut Vec<PlainTextPart<'a>>,
@@ -582,7 +2045,7 @@ fn collect_plain_text_children_smart_jsx<'a>(
         }
 
         if let Some(previous_child) = previous_child {
-            push_separator(out, jsx_child_separator(ast, previous_child, child));
+            push_separator(out, mixed_child_separator(ast, previous_child, child));
         }
 
         out.extend(child_parts);
@@ -597,11 +2060,11 @@ fn push_separator<'a>(out: &mut Vec<PlainTextPart<'a>>, separator: ChildSeparato
     match separator {
         ChildSeparator::None => {}
         ChildSeparator::HardBreak => out.push(PlainTextPart::HardBreak),
-        ChildSeparator::Text(text) => out.push(PlainTextPart::Text(text)),
+        ChildSeparator::Text(text) => out.push(PlainTextPart::Text(Cow::Borrowed(text))),
     }
 }
 
-fn jsx_child_separator(
+fn mixed_child_separator(
     ast: &Ast,
     previous_child: NodeIndex,
     next_child: NodeIndex,
@@ -631,10 +2094,22 @@ fn is_inline_plain_text_tag(tag: NodeTag) -> bool {
             | NodeTag::Strong
             | NodeTag::Emphasis
             | NodeTag::Strikethrough
+            | NodeTag::Spoiler
+            | NodeTag::Highlight
+            | NodeTag::Underline
+            | NodeTag::Superscript
+            | NodeTag::Subscript
             | NodeTag::CodeInline
             | NodeTag::Link
             | NodeTag::Image
             | NodeTag::HardBreak
+            | NodeTag::SoftBreak
+            | NodeTag::FootnoteReference
+            | NodeTag::MathInline
+            | NodeTag::NostrReference
+            | NodeTag::Hashtag
+            | NodeTag::Mention
+            | NodeTag::CustomEmoji
             | NodeTag::MdxTextExpression
             | NodeTag::MdxFlowExpression
     )
@@ -645,8 +2120,10 @@ fn render_plain_text_parts(parts: &[PlainTextPart<'_>], options: &PlainTextOptio
 
     for part in parts {
         match part {
-            PlainTextPart::Text(value) | PlainTextPart::Code(value) => output.push_str(value),
+            PlainTextPart::Text(value) => output.push_str(value),
+            PlainTextPart::Code(value) => output.push_str(value),
             PlainTextPart::HardBreak => output.push('\n'),
+            PlainTextPart::SoftBreak => output.push(' '),
             PlainTextPart::Expression { source, .. } => match options.expression_policy {
                 ExpressionTextPolicy::Omit => {}
                 ExpressionTextPolicy::Source => output.push_str(source),
@@ -0,0 +1,181 @@
+use crate::ast::*;
+use crate::parser::{
+    BlockStart, ParseOptions, TopLevelBlock, may_open_multiline_construct,
+    normalize_emoji_shortcodes, reparse_tail,
+};
+use crate::token::Tag as TokenTag;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Parses a document that arrives in chunks, such as a streamed bot reply, without
+/// re-parsing the blocks it has already settled.
+///
+/// After every `push` the provisional AST covers all input so far. Constructs cut off by
+/// the end of the input (an unclosed `**`, a code fence or JSX element still open, a tag
+/// ending inside an attribute value as in `<Card title="Pay`) are closed where the input
+/// ends and listed in `Ast::incomplete` instead of being reported as errors;
+/// `serialize_tree` marks them `"incomplete": true`.
+///
+/// A top-level block is finalized once the block after it has started on a line of its
+/// own after a blank line. Finalized blocks keep their node indices and contents for the
+/// rest of the stream, so a UI can render them once. A block containing a link or
+/// footnote reference with no definition before it stays provisional, along with all
+/// blocks after it, since a definition may still arrive.
+pub struct StreamingParser {
+    options: ParseOptions,
+    ast: Ast<'static>,
+    /// Top-level blocks that later input can no longer change, in document order.
+    finalized: Vec<NodeIndex>,
+    /// Where the first block that is not finalized starts; parsing resumes there.
+    resume: BlockStart,
+    /// The caller's input from `resume` on, before shortcode normalization.
+    pending_input: String,
+    /// Offset of `pending_input` in the caller's input.
+    pending_input_offset: usize,
+}
+
+impl StreamingParser {
+    pub fn new() -> StreamingParser {
+        StreamingParser::with_options(&ParseOptions::default())
+    }
+
+    pub fn with_options(options: &ParseOptions) -> StreamingParser {
+        let mut parser = StreamingParser {
+            options: options.clone(),
+            ast: Ast {
+                source: Cow::Owned(String::new()),
+                token_tags: Vec::new(),
+                token_starts: Vec::new(),
+                nodes: Vec::new(),
+                node_spans: Vec::new(),
+                extra_data: Vec::new(),
+                errors: Vec::new(),
+                custom_emoji: HashMap::new(),
+                source_edits: Vec::new(),
+                incomplete: Vec::new(),
+            },
+            finalized: Vec::new(),
+            resume: BlockStart::default(),
+            pending_input: String::new(),
+            pending_input_offset: 0,
+        };
+        reparse_tail(&mut parser.ast, &parser.resume, &[], &parser.options, true);
+        parser
+    }
+
+    /// Append a chunk of input and update the provisional AST.
+    pub fn push(&mut self, chunk: &str) {
+        if chunk.is_empty() {
+            return;
+        }
+        self.pending_input.push_str(chunk);
+        self.replace_tail();
+
+        let blocks = reparse_tail(
+            &mut self.ast,
+            &self.resume,
+            &self.finalized,
+            &self.options,
+            true,
+        );
+        self.finalize_settled_blocks(&blocks);
+    }
+
+    /// The AST of all input so far. Nodes listed in `Ast::incomplete` may still change.
+    pub fn ast(&self) -> &Ast<'static> {
+        &self.ast
+    }
+
+    /// Top-level blocks that are final: later input never changes them.
+    pub fn finalized_blocks(&self) -> &[NodeIndex] {
+        &self.finalized
+    }
+
+    /// End the stream and return the final AST. Anything still open at the end of the
+    /// input is now reported as an error, as `parse` would.
+    pub fn finish(mut self) -> Ast<'static> {
+        reparse_tail(
+            &mut self.ast,
+            &self.resume,
+            &self.finalized,
+            &self.options,
+            false,
+        );
+        self.ast
+    }
+
+    /// Rebuild the source from `resume` on out of the pending input.
+    fn replace_tail(&mut self) {
+        let resume = self.resume.byte_offset;
+        let source = self.ast.source.to_mut();
+        source.truncate(resume as usize);
+        if !self.options.normalize_emoji_shortcodes {
+            source.push_str(&self.pending_input);
+            return;
+        }
+
+        let (normalized, edits) =
+            normalize_emoji_shortcodes(&self.pending_input, &self.options.custom_emoji);
+        source.push_str(&normalized);
+        self.ast
+            .source_edits
+            .retain(|edit| edit.source.start < resume);
+        let original_offset = self.pending_input_offset as ByteOffset;
+        self.ast
+            .source_edits
+            .extend(edits.into_iter().map(|edit| SourceEdit {
+                original: Span {
+                    start: edit.original.start + original_offset,
+                    end: edit.original.end + original_offset,
+                },
+                source: Span {
+                    start: edit.source.start + resume,
+                    end: edit.source.end + resume,
+                },
+            }));
+    }
+
+    /// Finalize the leading blocks of a fresh parse from `resume` that the input still
+    /// to come cannot change, and resume after them from now on.
+    fn finalize_settled_blocks(&mut self, blocks: &[TopLevelBlock]) {
+        let settled = blocks
+            .windows(2)
+            .take_while(|pair| self.ends_before(&pair[0], &pair[1].start))
+            .count();
+        if settled == 0 {
+            return;
+        }
+
+        self.finalized
+            .extend(blocks[..settled].iter().map(|block| block.node));
+        self.resume = blocks[settled].start;
+        let input_offset = self.ast.original_offset(self.resume.byte_offset) as usize;
+        self.pending_input
+            .drain(..input_offset - self.pending_input_offset);
+        self.pending_input_offset = input_offset;
+    }
+
+    /// Whether `block` is complete once the block at `next` has begun: `next` starts at
+    /// the beginning of a line after a blank line, and that line is complete, so neither
+    /// list nor paragraph continuation can pull it into `block`.
+    fn ends_before(&self, block: &TopLevelBlock, next: &BlockStart) -> bool {
+        let source = &self.ast.source;
+        let start = next.byte_offset as usize;
+        let after_blank_line = next.token > 0
+            && self.ast.token_tags[next.token as usize - 1] == TokenTag::BlankLine
+            && !source[start..].starts_with([' ', '\t']);
+        let line_complete = source[start..].contains('\n');
+        let text = &source[block.start.byte_offset as usize..start];
+
+        block.settled
+            && after_blank_line
+            && line_complete
+            && !may_open_multiline_construct(text, &self.options)
+    }
+}
+
+impl Default for StreamingParser {
+    fn default() -> Self {
+        StreamingParser::new()
+    }
+}
@@ -20,6 +20,8 @@ pub enum Tag {
     ParagraphStart,
     CodeFenceStart,
     CodeFenceEnd,
+    CodeIndented,
+    MathBlock,
     ListItemUnordered,
     ListItemOrdered,
     CheckboxUnchecked,
@@ -27,6 +29,8 @@ pub enum Tag {
     BlockquoteStart,
     Hr,
     BlankLine,
+    LinkDefinition,
+    FootnoteDefinitionStart,
 
     // Table tokens
     Pipe,
@@ -39,6 +43,16 @@ pub enum Tag {
     EmphasisEnd,
     StrikethroughStart,
     StrikethroughEnd,
+    SpoilerStart,
+    SpoilerEnd,
+    HighlightStart,
+    HighlightEnd,
+    UnderlineStart,
+    UnderlineEnd,
+    SuperscriptStart,
+    SuperscriptEnd,
+    SubscriptStart,
+    SubscriptEnd,
     CodeInlineStart,
     CodeInlineEnd,
     LinkStart,
@@ -47,6 +61,16 @@ pub enum Tag {
     LinkUrlEnd,
     ImageStart,
     HardBreak,
+    AutolinkStart,
+    AutolinkEnd,
+    AutolinkLiteral,
+    HtmlComment,
+    MathInline,
+    NostrUri,
+    Hashtag,
+    Mention,
+    CustomEmoji,
+    FootnoteReference,
 
     // MDX Expression tokens
     ExprStart,
@@ -92,12 +116,19 @@ impl Tag {
             Tag::StrongStart | Tag::StrongEnd => "**",
             Tag::EmphasisStart | Tag::EmphasisEnd => "*",
             Tag::StrikethroughStart | Tag::StrikethroughEnd => "~~",
+            Tag::SpoilerStart | Tag::SpoilerEnd => "||",
+            Tag::HighlightStart | Tag::HighlightEnd => "==",
+            Tag::UnderlineStart | Tag::UnderlineEnd => "++",
+            Tag::SuperscriptStart | Tag::SuperscriptEnd => "^",
+            Tag::SubscriptStart | Tag::SubscriptEnd => "~",
             Tag::CodeInlineStart | Tag::CodeInlineEnd => "`",
             Tag::LinkStart => "[",
             Tag::LinkEnd => "]",
             Tag::LinkUrlStart => "(",
             Tag::LinkUrlEnd => ")",
             Tag::ImageStart => "![",
+            Tag::AutolinkStart => "<",
+            Tag::AutolinkEnd => ">",
             Tag::ExprStart => "{",
             Tag::ExprEnd => "}",
             Tag::JsxTagStart => "<",
@@ -126,6 +157,8 @@ impl Tag {
             Tag::ParagraphStart => "paragraph_start",
             Tag::CodeFenceStart => "code_fence_start",
             Tag::CodeFenceEnd => "code_fence_end",
+            Tag::CodeIndented => "code_indented",
+            Tag::MathBlock => "math_block",
             Tag::ListItemUnordered => "list_item_unordered",
             Tag::ListItemOrdered => "list_item_ordered",
             Tag::CheckboxUnchecked => "checkbox_unchecked",
@@ -134,6 +167,8 @@ impl Tag {
             Tag::Hr => "hr",
             Tag::Pipe => "pipe",
             Tag::BlankLine => "blank_line",
+            Tag::LinkDefinition => "link_definition",
+            Tag::FootnoteDefinitionStart => "footnote_definition_start",
             Tag::Text => "text",
             Tag::StrongStart => "strong_start",
             Tag::StrongEnd => "strong_end",
@@ -141,6 +176,16 @@ impl Tag {
             Tag::EmphasisEnd => "emphasis_end",
             Tag::StrikethroughStart => "strikethrough_start",
             Tag::StrikethroughEnd => "strikethrough_end",
+            Tag::SpoilerStart => "spoiler_start",
+            Tag::SpoilerEnd => "spoiler_end",
+            Tag::HighlightStart => "highlight_start",
+            Tag::HighlightEnd => "highlight_end",
+            Tag::UnderlineStart => "underline_start",
+            Tag::UnderlineEnd => "underline_end",
+            Tag::SuperscriptStart => "superscript_start",
+            Tag::SuperscriptEnd => "superscript_end",
+            Tag::SubscriptStart => "subscript_start",
+            Tag::SubscriptEnd => "subscript_end",
             Tag::CodeInlineStart => "code_inline_start",
             Tag::CodeInlineEnd => "code_inline_end",
             Tag::LinkStart => "link_start",
@@ -149,6 +194,16 @@ impl Tag {
             Tag::LinkUrlEnd => "link_url_end",
             Tag::ImageStart => "image_start",
             Tag::HardBreak => "hard_break",
+            Tag::AutolinkStart => "autolink_start",
+            Tag::AutolinkEnd => "autolink_end",
+            Tag::AutolinkLiteral => "autolink_literal",
+            Tag::HtmlComment => "html_comment",
+            Tag::MathInline => "math_inline",
+            Tag::NostrUri => "nostr_uri",
+            Tag::Hashtag => "hashtag",
+            Tag::Mention => "mention",
+            Tag::CustomEmoji => "custom_emoji",
+            Tag::FootnoteReference => "footnote_reference",
             Tag::ExprStart => "expr_start",
             Tag::ExprEnd => "expr_end",
             Tag::JsxTagStart => "jsx_tag_start",
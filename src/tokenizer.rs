@@ -1,4 +1,15 @@
+use crate::parser::{ParseOptions, parse_shortcode};
 use crate::token::{Loc, Tag, Token};
+use std::collections::HashMap;
+
+/// Columns of indentation that turn a line outside any container into indented code.
+pub(crate) const INDENTED_CODE_COLUMNS: usize = 4;
+
+/// Deepest nesting of JSX elements and expressions that is tracked. The parser gives up
+/// on anything deeper, and the tokenizer stops counting, so a stream tokenized again
+/// from a later line lines up with the old one within this many levels. It also bounds
+/// the list markers that nest on one line, as in `- - - a`.
+pub(crate) const MAX_NESTING_DEPTH: u32 = 64;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
@@ -20,18 +31,69 @@ pub struct Tokenizer<'a> {
     underscore_strong_depth: u32,
     underscore_emphasis_depth: u32,
     strikethrough_depth: u32,
+    spoiler_depth: u32,
+    highlight_depth: u32,
+    underline_depth: u32,
+    superscript_depth: u32,
+    subscript_depth: u32,
     after_link_text: bool,
     in_link_url: bool,
+    in_autolink: bool,
     in_table: bool,
+    code_fence_indent: u32,
+    /// Fence character (`` ` `` or `~`) and run length of the open code block.
+    code_fence_char: u8,
+    code_fence_len: u32,
+    /// Open JSX elements and fragments; their children may be indented freely.
+    jsx_depth: u32,
+    in_jsx_close_tag: bool,
+    /// The JSX tag being read is a void HTML element (`<br>`), which opens no children.
+    in_void_jsx_tag: bool,
+    /// Whether the last line starting at column 0 opened a list item or footnote, so
+    /// indented lines after it continue that item instead of starting indented code.
+    in_list_container: bool,
+    /// Column where the content of that list item or footnote starts; indented code in
+    /// the item is indented four columns past it.
+    list_content_column: u32,
+    /// List markers read so far on the current line; past the nesting limit the rest of
+    /// the line is item text.
+    line_markers: u32,
+    /// Line start where a new flow construct may begin without a preceding blank line.
+    flow_boundary: u32,
     pending_token: Option<Token>,
+    /// The last closer search for each chat delimiter (`||`, `==`, `++`, `^`, `~`): where
+    /// it began, and the first closer from there or else the end of the line. Openers on
+    /// one line share it rather than each scanning the rest of the line.
+    chat_closers: [(u32, u32); 5],
+    /// Where the last search for the end of a word began, and where that word ends.
+    chat_word_end: (u32, u32),
+    /// The last closer search for inline math opened with `$` and with `$$`, kept like
+    /// `chat_closers`.
+    math_closers: [(u32, u32); 2],
+    /// Where the last search for a closing `$$` line began, and the end of the first one
+    /// from there. Math block openers share it, so the document is scanned once.
+    math_block_closer: (u32, Option<u32>),
+    autolink_literals: bool,
+    math: bool,
+    nostr: bool,
+    hashtags: bool,
+    mentions: bool,
+    chat_inlines: bool,
+    custom_emoji: Option<&'a HashMap<String, String>>,
 }
 
 impl<'a> Tokenizer<'a> {
     pub fn new(source: &'a str) -> Self {
+        Self::starting_at(source, 0)
+    }
+
+    /// Create a tokenizer that resumes at `start`, which must be the first byte of a line.
+    /// Tokens still carry offsets into the full `source`.
+    pub fn starting_at(source: &'a str, start: u32) -> Self {
         Tokenizer {
             buffer: source.as_bytes(),
-            index: 0,
-            line_start: 0,
+            index: start,
+            line_start: start,
             mode: Mode::Markdown,
             mode_stack: Vec::new(),
             star_strong_depth: 0,
@@ -39,13 +101,131 @@ impl<'a> Tokenizer<'a> {
             underscore_strong_depth: 0,
             underscore_emphasis_depth: 0,
             strikethrough_depth: 0,
+            spoiler_depth: 0,
+            highlight_depth: 0,
+            underline_depth: 0,
+            superscript_depth: 0,
+            subscript_depth: 0,
             after_link_text: false,
             in_link_url: false,
+            in_autolink: false,
             in_table: false,
+            code_fence_indent: 0,
+            code_fence_char: b'`',
+            code_fence_len: 3,
+            jsx_depth: 0,
+            in_jsx_close_tag: false,
+            in_void_jsx_tag: false,
+            in_list_container: false,
+            list_content_column: 0,
+            line_markers: 0,
+            flow_boundary: start,
             pending_token: None,
+            chat_closers: [(1, 0); 5],
+            chat_word_end: (1, 0),
+            math_closers: [(1, 0); 2],
+            math_block_closer: (1, Some(0)),
+            autolink_literals: false,
+            math: false,
+            nostr: false,
+            hashtags: false,
+            mentions: false,
+            chat_inlines: false,
+            custom_emoji: None,
         }
     }
 
+    /// Enable the syntax extensions selected in `options`.
+    pub fn with_options(mut self, options: &'a ParseOptions) -> Self {
+        self.autolink_literals = options.autolink_literals;
+        self.math = options.math;
+        self.nostr = options.nostr;
+        self.hashtags = options.hashtags;
+        self.mentions = options.mentions;
+        self.chat_inlines = options.chat_inlines;
+        self.custom_emoji = Some(&options.custom_emoji).filter(|table| !table.is_empty());
+        self
+    }
+
+    /// Whether the next token starts a line at `offset` with nothing open, so a line
+    /// starting at column 0 there tokenizes as it would with `starting_at(source, offset)`.
+    /// Such a line sets `in_list_container` afresh, so it is not checked.
+    pub(crate) fn at_line_start_with_nothing_open(&self, offset: u32) -> bool {
+        self.index == offset
+            && self.line_start == offset
+            && self.pending_token.is_none()
+            && self.mode == Mode::Markdown
+            && self.mode_stack.is_empty()
+            && self.star_strong_depth == 0
+            && self.star_emphasis_depth == 0
+            && self.underscore_strong_depth == 0
+            && self.underscore_emphasis_depth == 0
+            && self.strikethrough_depth == 0
+            && self.spoiler_depth == 0
+            && self.highlight_depth == 0
+            && self.underline_depth == 0
+            && self.superscript_depth == 0
+            && self.subscript_depth == 0
+            && !self.after_link_text
+            && !self.in_link_url
+            && !self.in_autolink
+            && !self.in_table
+            && self.jsx_depth == 0
+            && !self.in_jsx_close_tag
+            && !self.in_void_jsx_tag
+    }
+
+    /// Byte offset the next token is read from, past any pending token.
+    pub(crate) fn offset(&self) -> u32 {
+        self.index
+    }
+
+    /// Whether `self` and `other` stand between tokens at the same offset, in states that
+    /// tokenize the rest of the source alike, so the tokens of one serve for the other.
+    /// `line` is the start of the line the offset is on.
+    pub(crate) fn tokenizes_like(&self, other: &Tokenizer, line: u32) -> bool {
+        // `line_start` only matters while it is the current offset, and `flow_boundary`
+        // only from the current line on; outside a code block the fence is stale.
+        let at_line_start = |t: &Tokenizer| t.line_start == t.index;
+        let flow_boundary = |t: &Tokenizer| (t.flow_boundary >= line).then_some(t.flow_boundary);
+        let code_fence = |t: &Tokenizer| {
+            (t.mode == Mode::CodeBlock || t.mode_stack.contains(&Mode::CodeBlock)).then_some((
+                t.code_fence_indent,
+                t.code_fence_char,
+                t.code_fence_len,
+            ))
+        };
+        self.index == other.index
+            && at_line_start(self) == at_line_start(other)
+            && self.pending_token.is_none()
+            && other.pending_token.is_none()
+            && self.mode == other.mode
+            && self.mode_stack.len() == other.mode_stack.len()
+            && self.star_strong_depth == other.star_strong_depth
+            && self.star_emphasis_depth == other.star_emphasis_depth
+            && self.underscore_strong_depth == other.underscore_strong_depth
+            && self.underscore_emphasis_depth == other.underscore_emphasis_depth
+            && self.strikethrough_depth == other.strikethrough_depth
+            && self.spoiler_depth == other.spoiler_depth
+            && self.highlight_depth == other.highlight_depth
+            && self.underline_depth == other.underline_depth
+            && self.superscript_depth == other.superscript_depth
+            && self.subscript_depth == other.subscript_depth
+            && self.after_link_text == other.after_link_text
+            && self.in_link_url == other.in_link_url
+            && self.in_autolink == other.in_autolink
+            && self.in_table == other.in_table
+            && self.jsx_depth == other.jsx_depth
+            && self.in_jsx_close_tag == other.in_jsx_close_tag
+            && self.in_void_jsx_tag == other.in_void_jsx_tag
+            && self.in_list_container == other.in_list_container
+            && self.code_margin() == other.code_margin()
+            && self.line_markers == other.line_markers
+            && flow_boundary(self) == flow_boundary(other)
+            && code_fence(self) == code_fence(other)
+            && self.mode_stack == other.mode_stack
+    }
+
     pub fn next(&mut self) -> Token {
         if let Some(tok) = self.pending_token.take() {
             return tok;
@@ -113,6 +293,9 @@ impl<'a> Tokenizer<'a> {
 
     fn next_markdown_sol(&mut self, start: u32) -> Token {
         let c = self.buf(self.index);
+        if !matches!(c, b' ' | b'\t' | b'\n' | 0) && (start == 0 || self.buf(start - 1) == b'\n') {
+            self.in_list_container = false;
+        }
 
         match c {
             0 => {
@@ -126,7 +309,7 @@ impl<'a> Tokenizer<'a> {
                 self.make_token(Tag::BlankLine, start)
             }
             b'#' => {
-                if self.is_keycap_emoji_start(start) {
+                if self.is_keycap_emoji_start(start) || self.hashtag_at(start).is_some() {
                     return self.next_markdown_inline(start);
                 }
                 self.index += 1;
@@ -148,10 +331,8 @@ impl<'a> Tokenizer<'a> {
                 self.hr_or_frontmatter(start, c)
             }
             b'`' => {
-                if self.peek_ahead("```") {
-                    self.index += 3;
-                    self.push_mode(Mode::CodeBlock);
-                    self.make_token(Tag::CodeFenceStart, start)
+                if let Some(len) = self.opening_fence_len(start) {
+                    self.code_fence_start(start, len)
                 } else {
                     self.next_markdown_inline(start)
                 }
@@ -165,10 +346,36 @@ impl<'a> Tokenizer<'a> {
                 self.make_token(Tag::BlockquoteStart, start)
             }
             b' ' | b'\t' => {
+                if let Some(end) = self.indented_code_end(start) {
+                    self.flow_boundary = end + 1;
+                    let margin = self.code_margin();
+                    if margin == 0 {
+                        self.index = end;
+                        return self.make_token(Tag::CodeIndented, start);
+                    }
+                    // In a list item the code starts at the item's content column, after
+                    // the indentation that keeps it in the item.
+                    self.index = start + margin;
+                    let indent = self.make_token(Tag::Indent, start);
+                    self.pending_token = Some(Token {
+                        tag: Tag::CodeIndented,
+                        loc: Loc {
+                            start: self.index,
+                            end,
+                        },
+                    });
+                    self.index = end;
+                    return indent;
+                }
                 let indent_start = self.index;
                 while self.buf(self.index) == b' ' || self.buf(self.index) == b'\t' {
                     self.index += 1;
                 }
+                // Block markers after indentation (nested list items, fences, quotes)
+                // are recognized as if they started the line.
+                if !matches!(self.buf(self.index), b'\n' | 0) {
+                    self.line_start = self.index;
+                }
                 self.make_token(Tag::Indent, indent_start)
             }
             b'0'..=b'9' => {
@@ -189,8 +396,7 @@ impl<'a> Tokenizer<'a> {
                     && self.buf(temp_index + 1) == b' '
                 {
                     self.index = temp_index + 2;
-                    self.try_checkbox();
-                    self.make_token(Tag::ListItemOrdered, start)
+                    self.list_marker(Tag::ListItemOrdered, start)
                 } else {
                     self.next_markdown_inline(start)
                 }
@@ -212,11 +418,240 @@ impl<'a> Tokenizer<'a> {
                 if self.in_table {
                     self.in_table = false;
                 }
+                if c == b'~'
+                    && let Some(len) = self.opening_fence_len(start)
+                {
+                    return self.code_fence_start(start, len);
+                }
+                if c == b'$'
+                    && let Some(end) = self.math_block_end(start)
+                {
+                    self.index = end;
+                    self.flow_boundary = end + 1;
+                    return self.make_token(Tag::MathBlock, start);
+                }
+                if let Some((tag, end)) = self.esm_statement(start) {
+                    self.index = end;
+                    self.flow_boundary = end + 1;
+                    return self.make_token(tag, start);
+                }
+                if c == b'['
+                    && let Some(label_end) = self.footnote_label_end(start)
+                    && self.buf(label_end) == b':'
+                {
+                    self.index = label_end + 1;
+                    while self.buf(self.index) == b' ' {
+                        self.index += 1;
+                    }
+                    self.in_list_container = true;
+                    self.list_content_column = self.column_of(start) + INDENTED_CODE_COLUMNS as u32;
+                    return self.make_token(Tag::FootnoteDefinitionStart, start);
+                }
+                if c == b'['
+                    && let Some(end) = self.link_definition(start)
+                {
+                    self.index = end;
+                    self.flow_boundary = end + 1;
+                    return self.make_token(Tag::LinkDefinition, start);
+                }
                 self.next_markdown_inline(start)
             }
         }
     }
 
+    /// Recognize a top-level `import`/`export` statement starting at `start` and return its
+    /// token tag and end offset (before the terminating newline). A statement ends at the
+    /// first line break outside brackets, strings and comments, so multi-line `{ ... }`
+    /// lists stay in one token. Like MDX, ESM cannot interrupt a paragraph: it must start
+    /// unindented, after a blank line or another flow boundary.
+    fn esm_statement(&self, start: u32) -> Option<(Tag, u32)> {
+        let tag = if self.peek_ahead("import ") {
+            Tag::EsmImport
+        } else if self.peek_ahead("export ") {
+            Tag::EsmExport
+        } else {
+            return None;
+        };
+        if !self.mode_stack.is_empty()
+            || self.indent_before(start) != 0
+            || !self.at_flow_boundary(start)
+        {
+            return None;
+        }
+
+        let end = self.esm_statement_end(start)?;
+        let body = &self.buffer[start as usize + 7..end as usize];
+        esm_body_is_plausible(tag, body).then_some((tag, end))
+    }
+
+    /// Emit a list item marker ending at the current position. A block may open right
+    /// after it (`- # Title`, `- > quote`, ``- ```js``), so the rest of the line is read as
+    /// if it started the line, except after a task item's checkbox.
+    fn list_marker(&mut self, tag: Tag, start: u32) -> Token {
+        if self.column_of(start) == self.indent_before(start) {
+            self.line_markers = 0;
+        }
+        self.line_markers += 1;
+        self.in_list_container = true;
+        self.list_content_column = self.column_of(self.index);
+        self.try_checkbox();
+        if self.pending_token.is_none()
+            && self.line_markers < MAX_NESTING_DEPTH
+            && !matches!(self.buf(self.index), b' ' | b'\t' | b'\n' | 0)
+        {
+            self.line_start = self.index;
+        }
+        self.make_token(tag, start)
+    }
+
+    /// Bytes between the start of the physical line and `idx`.
+    fn column_of(&self, idx: u32) -> u32 {
+        let line = self.buffer[..idx as usize]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        idx - line as u32
+    }
+
+    /// Indentation that keeps a line in the list item or footnote it continues, before any
+    /// indented code in it.
+    fn code_margin(&self) -> u32 {
+        if self.in_list_container {
+            self.list_content_column
+        } else {
+            0
+        }
+    }
+
+    /// Whether the line containing `start` may open a flow construct that cannot interrupt
+    /// a paragraph: it follows a blank line or another flow boundary.
+    fn at_flow_boundary(&self, start: u32) -> bool {
+        let line = start - self.indent_before(start);
+        line == self.flow_boundary
+            || (line > 0
+                && self.buf(line - 1) == b'\n'
+                && (line == 1 || self.buf(line - 2) == b'\n'))
+    }
+
+    /// End offset (just past `]`) of a `[^label]` footnote label starting at `start`. Labels
+    /// are non-empty and contain no whitespace or brackets.
+    fn footnote_label_end(&self, start: u32) -> Option<u32> {
+        if self.buf(start + 1) != b'^' {
+            return None;
+        }
+        let mut i = start + 2;
+        while !matches!(self.buf(i), b']' | b'[' | b' ' | b'\t' | b'\n' | 0) {
+            i += 1;
+        }
+        (self.buf(i) == b']' && i > start + 2).then_some(i + 1)
+    }
+
+    /// Recognize a single-line `[label]: destination "title"` link reference definition
+    /// starting at `start` and return its end offset (before the newline).
+    fn link_definition(&self, start: u32) -> Option<u32> {
+        if !self.at_flow_boundary(start) {
+            return None;
+        }
+        let line = &self.buffer[start as usize..];
+        let end = start + line.iter().position(|&b| b == b'\n').unwrap_or(line.len()) as u32;
+        let text = std::str::from_utf8(&self.buffer[start as usize..end as usize]).ok()?;
+        crate::semantic::split_link_definition(text)?;
+        Some(end)
+    }
+
+    fn esm_statement_end(&self, start: u32) -> Option<u32> {
+        let mut i = start;
+        let mut depth: u32 = 0;
+        loop {
+            if let Some(end) = self.js_literal_end(i) {
+                i = end;
+                continue;
+            }
+            match self.buf(i) {
+                0 => return (depth == 0).then_some(i),
+                b'\n' if depth == 0 => return Some(i),
+                b'{' | b'(' | b'[' => depth += 1,
+                b'}' | b')' | b']' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// End of the JavaScript string literal, template literal or comment starting at `i`,
+    /// or `None` if none starts there. Quoted strings stop at an unescaped line break;
+    /// unterminated templates and block comments run to the end of the input.
+    fn js_literal_end(&self, i: u32) -> Option<u32> {
+        let end = match self.buf(i) {
+            quote @ (b'"' | b'\'') => {
+                let mut j = i + 1;
+                while !matches!(self.buf(j), 0 | b'\n') && self.buf(j) != quote {
+                    j += if self.buf(j) == b'\\' { 2 } else { 1 };
+                }
+                if self.buf(j) == quote { j + 1 } else { j }
+            }
+            b'`' => self.template_literal_end(i + 1),
+            b'/' if self.buf(i + 1) == b'/' => {
+                let mut j = i + 2;
+                while !matches!(self.buf(j), 0 | b'\n') {
+                    j += 1;
+                }
+                j
+            }
+            b'/' if self.buf(i + 1) == b'*' => {
+                let mut j = i + 2;
+                while self.buf(j) != 0 && !(self.buf(j) == b'*' && self.buf(j + 1) == b'/') {
+                    j += 1;
+                }
+                if self.buf(j) == 0 { j } else { j + 2 }
+            }
+            _ => return None,
+        };
+        Some(end.min(self.buffer.len() as u32))
+    }
+
+    /// End of a template literal whose body starts at `i`, skipping `${ ... }`
+    /// substitutions, which may themselves contain strings and templates.
+    fn template_literal_end(&self, mut i: u32) -> u32 {
+        loop {
+            match self.buf(i) {
+                0 => return i,
+                b'`' => return i + 1,
+                b'\\' => i += 2,
+                b'$' if self.buf(i + 1) == b'{' => {
+                    i = self
+                        .js_block_end(i + 2)
+                        .map_or(self.buffer.len() as u32, |close| close + 1);
+                }
+                _ => i += 1,
+            }
+        }
+    }
+
+    /// Offset of the `}` that closes an already opened `{`, skipping nested braces,
+    /// strings, templates and comments. `None` if the input ends first.
+    fn js_block_end(&self, mut i: u32) -> Option<u32> {
+        let mut depth: u32 = 1;
+        loop {
+            if let Some(end) = self.js_literal_end(i) {
+                i = end;
+                continue;
+            }
+            match self.buf(i) {
+                0 => return None,
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
     /// Check if current position starts a GFM table.
     /// We need at least two lines: a header row and a separator row.
     /// Both must start with `|`.
@@ -328,6 +763,7 @@ impl<'a> Tokenizer<'a> {
         if count >= 3 {
             let next = self.buf(self.index);
             if next == b'\n' || next == 0 {
+                self.flow_boundary = self.index + 1;
                 return self.make_token(Tag::Hr, start);
             }
         }
@@ -336,8 +772,7 @@ impl<'a> Tokenizer<'a> {
         if first_char == b'-' || first_char == b'*' {
             if self.buf(self.index) == b' ' {
                 self.index += 1; // advance past the space
-                self.try_checkbox();
-                return self.make_token(Tag::ListItemUnordered, start);
+                return self.list_marker(Tag::ListItemUnordered, start);
             }
         }
 
@@ -355,6 +790,17 @@ impl<'a> Tokenizer<'a> {
         let c = self.buf(self.index);
 
         match c {
+            _ if self.in_autolink => {
+                if c == b'>' {
+                    self.index += 1;
+                    self.in_autolink = false;
+                    return self.make_token(Tag::AutolinkEnd, start);
+                }
+                while !matches!(self.buf(self.index), b'>' | 0) {
+                    self.index += 1;
+                }
+                self.make_token(Tag::Text, start)
+            }
             0 => self.make_token(Tag::Eof, start),
             b'\n' => {
                 self.index += 1;
@@ -395,7 +841,14 @@ impl<'a> Tokenizer<'a> {
                 self.make_token(Tag::ExprStart, start)
             }
             b'<' => {
-                if self.is_jsx_start() {
+                if let Some(end) = self.html_comment_end() {
+                    self.index = end;
+                    self.make_token(Tag::HtmlComment, start)
+                } else if self.is_autolink_start() {
+                    self.index += 1;
+                    self.in_autolink = true;
+                    self.make_token(Tag::AutolinkStart, start)
+                } else if self.is_jsx_start() {
                     self.push_mode(Mode::Jsx);
                     self.next_jsx()
                 } else {
@@ -419,6 +872,9 @@ impl<'a> Tokenizer<'a> {
                     self.text(start)
                 }
             }
+            b'|' | b'=' | b'+' | b'^' | b'~' if self.chat_delimiter_at(start).is_some() => {
+                self.chat_delimiter(start)
+            }
             b'~' => {
                 if self.buf(self.index + 1) == b'~' {
                     self.index += 2;
@@ -432,20 +888,29 @@ impl<'a> Tokenizer<'a> {
                 self.push_mode(Mode::InlineCode);
                 self.make_token(Tag::CodeInlineStart, start)
             }
+            b'$' => {
+                if let Some(end) = self.inline_math_end(start) {
+                    self.index = end;
+                    self.make_token(Tag::MathInline, start)
+                } else {
+                    self.text(start)
+                }
+            }
             b'[' => {
+                if let Some(end) = self.footnote_label_end(start)
+                    && self.buf(end) != b'('
+                {
+                    self.index = end;
+                    return self.make_token(Tag::FootnoteReference, start);
+                }
                 self.index += 1;
                 self.after_link_text = false;
                 self.make_token(Tag::LinkStart, start)
             }
             b']' => {
                 self.index += 1;
-                if self.buf(self.index) == b'(' {
-                    self.after_link_text = true;
-                    self.make_token(Tag::LinkEnd, start)
-                } else {
-                    self.after_link_text = false;
-                    self.text(start)
-                }
+                self.after_link_text = self.buf(self.index) == b'(';
+                self.make_token(Tag::LinkEnd, start)
             }
             b'(' => {
                 if self.after_link_text {
@@ -522,6 +987,125 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    fn chat_delimiter(&mut self, start: u32) -> Token {
+        let Some((len, open_tag)) = self.chat_delimiter_at(start) else {
+            return self.text(start);
+        };
+        self.index = start + len;
+        let (depth, close_tag) = match open_tag {
+            Tag::SpoilerStart => (&mut self.spoiler_depth, Tag::SpoilerEnd),
+            Tag::HighlightStart => (&mut self.highlight_depth, Tag::HighlightEnd),
+            Tag::UnderlineStart => (&mut self.underline_depth, Tag::UnderlineEnd),
+            Tag::SuperscriptStart => (&mut self.superscript_depth, Tag::SuperscriptEnd),
+            _ => (&mut self.subscript_depth, Tag::SubscriptEnd),
+        };
+        if *depth > 0 {
+            *depth -= 1;
+            self.make_token(close_tag, start)
+        } else {
+            *depth += 1;
+            self.make_token(open_tag, start)
+        }
+    }
+
+    /// Length and start tag of a chat formatting delimiter (`||`, `==`, `++`, `^` or `~`)
+    /// at `i` that opens or closes a span there. An opener needs a closer later on the
+    /// same line; the text inside may not start or end with whitespace, and sub- and
+    /// superscripts may not contain any. The doubled delimiters do not work inside words,
+    /// so `a==b` and `C++` stay text.
+    fn chat_delimiter_at(&mut self, i: u32) -> Option<(u32, Tag)> {
+        if !self.chat_inlines || self.in_link_url {
+            return None;
+        }
+        let c = self.buf(i);
+        let doubled = self.buf(i + 1) == c;
+        let (len, tag, depth) = match c {
+            b'|' if doubled && !self.in_table => (2, Tag::SpoilerStart, self.spoiler_depth),
+            b'=' if doubled => (2, Tag::HighlightStart, self.highlight_depth),
+            b'+' if doubled => (2, Tag::UnderlineStart, self.underline_depth),
+            b'^' if !doubled => (1, Tag::SuperscriptStart, self.superscript_depth),
+            b'~' if !doubled => (1, Tag::SubscriptStart, self.subscript_depth),
+            _ => return None,
+        };
+        let before = if i == 0 { b'\n' } else { self.buf(i - 1) };
+        let after = self.buf(i + len);
+        let found = if depth > 0 {
+            self.is_chat_closer(i, c, len)
+        } else {
+            (len == 1 || !before.is_ascii_alphanumeric())
+                && !matches!(after, b' ' | b'\t' | b'\n' | 0)
+                && after != c
+                && self.chat_closer_after(i + len, c, len)
+        };
+        found.then_some((len, tag))
+    }
+
+    /// Whether the `c` delimiter of `len` bytes at `i` can close a span.
+    fn is_chat_closer(&self, i: u32, c: u8, len: u32) -> bool {
+        let after = self.buf(i + len);
+        i > 0
+            && !matches!(self.buf(i - 1), b' ' | b'\t' | b'\n')
+            && self.buf(i) == c
+            && (len == 1 || self.buf(i + 1) == c)
+            && after != c
+            && !(len == 2 && after.is_ascii_alphanumeric())
+    }
+
+    /// Whether a closing `c` delimiter follows the span content starting at `from`.
+    fn chat_closer_after(&mut self, from: u32, c: u8, len: u32) -> bool {
+        let closer = self.next_chat_closer(from + 1, c, len);
+        self.buf(closer) == c && (len == 2 || closer < self.next_word_end(from + 1))
+    }
+
+    /// The first unescaped `c` delimiter at or after `from` that can close a span, or the
+    /// end of the line if there is none.
+    fn next_chat_closer(&mut self, from: u32, c: u8, len: u32) -> u32 {
+        let kind = match c {
+            b'|' => 0,
+            b'=' => 1,
+            b'+' => 2,
+            b'^' => 3,
+            _ => 4,
+        };
+        let (searched_from, found) = self.chat_closers[kind];
+        if (searched_from..=found).contains(&from) {
+            return found;
+        }
+        let mut j = from;
+        while !matches!(self.buf(j), 0 | b'\n') {
+            if self.is_chat_closer(j, c, len) && !self.is_escaped(j) {
+                break;
+            }
+            j += 1;
+        }
+        self.chat_closers[kind] = (from, j);
+        j
+    }
+
+    /// The first space, tab or line end at or after `from`.
+    fn next_word_end(&mut self, from: u32) -> u32 {
+        let (searched_from, found) = self.chat_word_end;
+        if (searched_from..=found).contains(&from) {
+            return found;
+        }
+        let mut j = from;
+        while !matches!(self.buf(j), b' ' | b'\t' | b'\n' | 0) {
+            j += 1;
+        }
+        self.chat_word_end = (from, j);
+        j
+    }
+
+    /// Whether the byte at `i` is escaped by an odd run of backslashes before it.
+    fn is_escaped(&self, i: u32) -> bool {
+        let backslashes = self.buffer[..i as usize]
+            .iter()
+            .rev()
+            .take_while(|&&b| b == b'\\')
+            .count();
+        backslashes % 2 == 1
+    }
+
     fn should_parse_underscore_delimiter(&self, start: u32) -> bool {
         let prev = if start == 0 { 0 } else { self.buf(start - 1) };
         let next = self.buf(start + 1);
@@ -539,10 +1123,50 @@ impl<'a> Tokenizer<'a> {
     }
 
     fn text(&mut self, start: u32) -> Token {
+        if let Some(end) = self.nostr_uri_at(start) {
+            self.index = end;
+            return self.make_token(Tag::NostrUri, start);
+        }
+        if let Some(end) = self.custom_emoji_at(start) {
+            self.index = end;
+            return self.make_token(Tag::CustomEmoji, start);
+        }
+        if let Some(end) = self.hashtag_at(start) {
+            self.index = end;
+            return self.make_token(Tag::Hashtag, start);
+        }
+        if let Some(end) = self.mention_at(start) {
+            self.index = end;
+            return self.make_token(Tag::Mention, start);
+        }
+        if let Some(end) = self.autolink_literal_at(start) {
+            self.index = end;
+            let link = self.make_token(Tag::AutolinkLiteral, start);
+            let tail_end = self.autolink_text_tail_end(end);
+            if tail_end > end {
+                self.pending_token = Some(Token {
+                    tag: Tag::Text,
+                    loc: Loc {
+                        start: end,
+                        end: tail_end,
+                    },
+                });
+                self.index = tail_end;
+            }
+            return link;
+        }
         while (self.index as usize) < self.buffer.len() {
             let ch = self.buf(self.index);
             match ch {
-                0 | b'\n' | b'{' | b'<' | b'`' | b'[' => break,
+                // A backslash escape keeps the punctuation after it literal.
+                b'\\' if self.buf(self.index + 1).is_ascii_punctuation() => self.index += 2,
+                _ if self.index != start && self.autolink_literal_at(self.index).is_some() => break,
+                b'n' if self.index != start && self.nostr_uri_at(self.index).is_some() => break,
+                b'#' if self.index != start && self.hashtag_at(self.index).is_some() => break,
+                b':' if self.index != start && self.custom_emoji_at(self.index).is_some() => break,
+                b'@' if self.index != start && self.mention_at(self.index).is_some() => break,
+                b'$' if self.index != start && self.inline_math_end(self.index).is_some() => break,
+                0 | b'\n' | b'{' | b'<' | b'`' | b'[' | b']' => break,
                 b'|' if self.in_table => break,
                 b'*' => {
                     if self.is_keycap_emoji_start(self.index) {
@@ -557,20 +1181,17 @@ impl<'a> Tokenizer<'a> {
                     }
                     self.index += 1;
                 }
+                b'|' | b'=' | b'+' | b'^' | b'~'
+                    if self.index != start && self.chat_delimiter_at(self.index).is_some() =>
+                {
+                    break;
+                }
                 b'~' => {
                     if self.buf(self.index + 1) == b'~' {
                         break;
                     }
                     self.index += 1;
                 }
-                b']' => {
-                    if self.index as usize + 1 < self.buffer.len()
-                        && self.buf(self.index + 1) == b'('
-                    {
-                        break;
-                    }
-                    self.index += 1;
-                }
                 b'(' => {
                     if self.after_link_text {
                         break;
@@ -601,10 +1222,26 @@ impl<'a> Tokenizer<'a> {
             self.index += 1;
         }
 
+        // Whitespace before a `{#id}` heading attribute is a token of its own, so the
+        // heading text does not end in it.
+        if self.heading_id_attribute_at(self.index) {
+            let mut end_idx = self.index;
+            while end_idx > start && matches!(self.buf(end_idx - 1), b' ' | b'\t') {
+                end_idx -= 1;
+            }
+            if end_idx > start {
+                self.index = end_idx;
+            }
+        }
+
         // Check if we have a hard break pattern at the end
         if (self.index as usize) < self.buffer.len() && self.buf(self.index) == b'\n' {
-            // Check for backslash immediately before newline
-            if self.index > start && self.buf(self.index - 1) == b'\\' {
+            // Check for an unescaped backslash immediately before newline
+            let backslashes = (start..self.index)
+                .rev()
+                .take_while(|&i| self.buf(i) == b'\\')
+                .count();
+            if backslashes % 2 == 1 {
                 self.index -= 1;
                 if self.index == start {
                     self.index += 2;
@@ -648,17 +1285,34 @@ impl<'a> Tokenizer<'a> {
                 self.index += 1;
                 if self.buf(self.index) == b'/' {
                     self.index += 1;
+                    self.in_jsx_close_tag = true;
                     self.make_token(Tag::JsxCloseTag, start)
                 } else if self.buf(self.index) == b'>' {
                     self.index += 1;
+                    self.jsx_depth = (self.jsx_depth + 1).min(MAX_NESTING_DEPTH);
                     self.make_token(Tag::JsxFragmentStart, start)
                 } else {
+                    self.in_jsx_close_tag = false;
+                    let name_end = self.index
+                        + self.buffer[self.index as usize..]
+                            .iter()
+                            .take_while(|b| b.is_ascii_alphanumeric())
+                            .count() as u32;
+                    let name = &self.buffer[self.index as usize..name_end as usize];
+                    self.in_void_jsx_tag =
+                        std::str::from_utf8(name).is_ok_and(crate::semantic::is_void_html_element);
                     self.make_token(Tag::JsxTagStart, start)
                 }
             }
             b'>' => {
                 self.index += 1;
                 self.pop_mode();
+                if self.in_jsx_close_tag {
+                    self.jsx_depth = self.jsx_depth.saturating_sub(1);
+                    self.in_jsx_close_tag = false;
+                } else if !self.in_void_jsx_tag {
+                    self.jsx_depth = (self.jsx_depth + 1).min(MAX_NESTING_DEPTH);
+                }
                 self.make_token(Tag::JsxTagEnd, start)
             }
             b'/' => {
@@ -702,7 +1356,10 @@ impl<'a> Tokenizer<'a> {
             b'0'..=b'9' | b'-' => self.next_jsx_bare_value(),
             b'a'..=b'z' | b'A'..=b'Z' | b'_' => self.next_jsx_identifier(),
             _ => {
-                self.index += 1;
+                // Keep a stray multi-byte character in one token.
+                self.index += self
+                    .char_at(self.index)
+                    .map_or(1, |ch| ch.len_utf8() as u32);
                 self.make_token(Tag::Invalid, start)
             }
         }
@@ -741,7 +1398,8 @@ impl<'a> Tokenizer<'a> {
                 return self.make_token(Tag::JsxString, start);
             }
             if c == b'\\' {
-                self.index += 2;
+                // A backslash at the end of the input escapes nothing.
+                self.index = (self.index + 2).min(self.buffer.len() as u32);
             } else {
                 self.index += 1;
             }
@@ -759,6 +1417,15 @@ impl<'a> Tokenizer<'a> {
 
         let c = self.buf(self.index);
 
+        // A JSX attribute value is read as one token up to its closing brace, so object
+        // literals like `style={{ a: 1 }}` reach the parser whole.
+        if c != b'}' && self.mode_stack.last() == Some(&Mode::Jsx) {
+            self.index = self
+                .js_block_end(self.index)
+                .unwrap_or(self.buffer.len() as u32);
+            return self.make_token(Tag::Text, start);
+        }
+
         match c {
             0 => self.make_token(Tag::Eof, start),
             b'}' => {
@@ -772,7 +1439,12 @@ impl<'a> Tokenizer<'a> {
                 self.make_token(Tag::ExprStart, start)
             }
             _ => {
+                // Braces inside strings, template literals and comments are not delimiters.
                 while (self.index as usize) < self.buffer.len() {
+                    if let Some(end) = self.js_literal_end(self.index) {
+                        self.index = end;
+                        continue;
+                    }
                     let ch = self.buf(self.index);
                     if ch == b'{' || ch == b'}' || ch == 0 {
                         break;
@@ -822,10 +1494,14 @@ impl<'a> Tokenizer<'a> {
 
         let c = self.buf(self.index);
 
-        // Check for closing fence at start of line
-        if self.index == self.line_start && c == b'`' && self.peek_ahead("```") {
-            self.index += 3;
+        // Check for closing fence at start of line. It may be indented like the opening
+        // fence (code under a list item); the indentation belongs to the fence token.
+        if self.index == self.line_start
+            && let Some(fence_len) = self.closing_fence_len()
+        {
+            self.index += fence_len;
             self.pop_mode();
+            self.flow_boundary = self.index + 1;
             return self.make_token(Tag::CodeFenceEnd, start);
         }
 
@@ -842,9 +1518,6 @@ impl<'a> Tokenizer<'a> {
                     if ch == b'\n' || ch == 0 {
                         break;
                     }
-                    if self.index == self.line_start && ch == b'`' && self.peek_ahead("```") {
-                        break;
-                    }
                     self.index += 1;
                 }
                 self.make_token(Tag::Text, start)
@@ -852,6 +1525,247 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// Length of a closing fence at the current line start: a run of the opening fence
+    /// character at least as long as the opening one, with up to `code_fence_indent + 3`
+    /// columns of leading whitespace. Trailing whitespace belongs to the fence.
+    fn closing_fence_len(&self) -> Option<u32> {
+        let mut i = self.index;
+        while matches!(self.buf(i), b' ' | b'\t') {
+            i += 1;
+        }
+        let indent = i - self.index;
+        if indent > self.code_fence_indent + 3 {
+            return None;
+        }
+        let run = self.run_len(i, self.code_fence_char);
+        if run < self.code_fence_len {
+            return None;
+        }
+        let mut end = i + run;
+        while matches!(self.buf(end), b' ' | b'\t') {
+            end += 1;
+        }
+        matches!(self.buf(end), b'\n' | 0).then_some(end - self.index)
+    }
+
+    /// Length of an opening fence at `start`: three or more backticks or tildes. Backtick
+    /// fences cannot have a backtick in their info string (that is inline code instead).
+    fn opening_fence_len(&self, start: u32) -> Option<u32> {
+        let fence_char = self.buf(start);
+        let len = self.run_len(start, fence_char);
+        if len < 3 {
+            return None;
+        }
+        if fence_char == b'`' {
+            let mut i = start + len;
+            while !matches!(self.buf(i), b'\n' | 0) {
+                if self.buf(i) == b'`' {
+                    return None;
+                }
+                i += 1;
+            }
+        }
+        Some(len)
+    }
+
+    fn code_fence_start(&mut self, start: u32, len: u32) -> Token {
+        self.code_fence_indent = self.indent_before(start);
+        self.code_fence_char = self.buf(start);
+        self.code_fence_len = len;
+        self.index = start + len;
+        self.push_mode(Mode::CodeBlock);
+        self.make_token(Tag::CodeFenceStart, start)
+    }
+
+    /// End offset (just past `-->`) of an HTML comment opening at the current position.
+    /// Comments may span lines; an unterminated `<!--` is not a comment.
+    fn html_comment_end(&self) -> Option<u32> {
+        if !self.peek_ahead("<!--") {
+            return None;
+        }
+        let body = &self.buffer[self.index as usize + 4..];
+        let close = body.windows(3).position(|w| w == b"-->")?;
+        Some(self.index + 4 + close as u32 + 3)
+    }
+
+    /// End of an inline math span opening at `start`: `$...$` or `$$...$$` on one line.
+    /// Like Pandoc, the opening delimiter cannot be followed by whitespace, the closing
+    /// one cannot follow whitespace, and a single `$` cannot close before a digit, so
+    /// amounts such as `$5 and $10` stay text.
+    fn inline_math_end(&mut self, start: u32) -> Option<u32> {
+        if !self.math || self.in_link_url {
+            return None;
+        }
+        let len = self.run_len(start, b'$');
+        if len > 2 || matches!(self.buf(start + len), b' ' | b'\t' | b'\n' | 0) {
+            return None;
+        }
+        let closer = self.next_math_closer(start + len, len);
+        (self.buf(closer) == b'$').then_some(closer + len)
+    }
+
+    /// The first `$` run at or after `from` that closes inline math opened with `len`
+    /// dollars, or the end of the line if there is none. `from` follows a `$`.
+    fn next_math_closer(&mut self, from: u32, len: u32) -> u32 {
+        let kind = len as usize - 1;
+        let (searched_from, found) = self.math_closers[kind];
+        if (searched_from..=found).contains(&from) {
+            return found;
+        }
+        let mut j = from;
+        while !matches!(self.buf(j), b'\n' | 0) {
+            if self.is_math_closer(j, len) {
+                break;
+            }
+            j += 1;
+        }
+        self.math_closers[kind] = (from, j);
+        j
+    }
+
+    /// Whether an unescaped run of exactly `len` dollars starts at `i` and can close
+    /// inline math. A run whose first `$` is escaped starts again after it.
+    fn is_math_closer(&self, i: u32, len: u32) -> bool {
+        if self.buf(i) != b'$' {
+            return false;
+        }
+        let run_start = if self.buf(i - 1) == b'$' {
+            self.buf(i - 2) != b'$' && self.is_escaped(i - 1)
+        } else {
+            !self.is_escaped(i)
+        };
+        run_start
+            && self.run_len(i, b'$') == len
+            && !matches!(self.buf(i - 1), b' ' | b'\t')
+            && !(len == 1 && self.buf(i + 1).is_ascii_digit())
+    }
+
+    /// End of a math block whose opening `$$` line starts at `start`: the offset just past
+    /// the closing `$$` line's content. Unclosed blocks are not math.
+    fn math_block_end(&mut self, start: u32) -> Option<u32> {
+        if !self.math || self.run_len(start, b'$') != 2 {
+            return None;
+        }
+        let line_end = self.blank_line_end(start + 2)?;
+        self.next_math_block_closer(line_end)
+    }
+
+    /// The end of the first closing `$$` line after the line ending at `from`.
+    fn next_math_block_closer(&mut self, from: u32) -> Option<u32> {
+        let (searched_from, found) = self.math_block_closer;
+        if searched_from <= from && found.is_none_or(|end| from < end) {
+            return found;
+        }
+        let mut line = from;
+        let mut found = None;
+        while self.buf(line) == b'\n' {
+            line += 1;
+            let mut i = line;
+            while matches!(self.buf(i), b' ' | b'\t') {
+                i += 1;
+            }
+            if self.run_len(i, b'$') == 2
+                && let Some(end) = self.blank_line_end(i + 2)
+            {
+                found = Some(end);
+                break;
+            }
+            while !matches!(self.buf(line), b'\n' | 0) {
+                line += 1;
+            }
+        }
+        self.math_block_closer = (from, found);
+        found
+    }
+
+    /// The line end at `i` if only spaces and tabs come before it.
+    fn blank_line_end(&self, mut i: u32) -> Option<u32> {
+        while matches!(self.buf(i), b' ' | b'\t') {
+            i += 1;
+        }
+        matches!(self.buf(i), b'\n' | 0).then_some(i)
+    }
+
+    /// Number of consecutive `ch` bytes starting at `idx`.
+    fn run_len(&self, idx: u32, ch: u8) -> u32 {
+        let mut i = idx;
+        while self.buf(i) == ch {
+            i += 1;
+        }
+        i - idx
+    }
+
+    /// End of an indented code block starting on the line at `start`: the line is indented
+    /// by four or more columns past `code_margin` and sits at a flow boundary outside JSX
+    /// children. The block runs over following lines indented as far and blank lines, but
+    /// not trailing blank ones.
+    fn indented_code_end(&self, start: u32) -> Option<u32> {
+        if self.jsx_depth > 0 || !self.at_flow_boundary(start) {
+            return None;
+        }
+        let margin = self.code_margin();
+        if !self.buffer[start as usize..]
+            .iter()
+            .take(margin as usize)
+            .all(|&b| b == b' ')
+        {
+            return None;
+        }
+
+        let mut end = None;
+        let mut line = start;
+        while (line as usize) < self.buffer.len() {
+            let mut i = line;
+            let mut columns = 0;
+            while matches!(self.buf(i), b' ' | b'\t') {
+                columns = if self.buf(i) == b'\t' {
+                    columns + 4 - columns % 4
+                } else {
+                    columns + 1
+                };
+                i += 1;
+            }
+            let blank = matches!(self.buf(i), b'\n' | 0);
+            if (!blank && columns < margin as usize + INDENTED_CODE_COLUMNS)
+                || (blank && line == start)
+            {
+                break;
+            }
+            while !matches!(self.buf(i), b'\n' | 0) {
+                i += 1;
+            }
+            if !blank {
+                end = Some(i);
+            }
+            line = i + 1;
+        }
+        end
+    }
+
+    /// Whether a `{#id}` heading attribute starts at `idx` and ends the line.
+    fn heading_id_attribute_at(&self, idx: u32) -> bool {
+        if self.buf(idx) != b'{' {
+            return false;
+        }
+        let rest = &self.buffer[idx as usize + 1..];
+        let line = rest.split(|&b| b == b'\n').next().unwrap_or(rest);
+        let Some(close) = line.iter().position(|&b| b == b'}') else {
+            return false;
+        };
+        line[close + 1..].iter().all(|&b| matches!(b, b' ' | b'\t'))
+            && std::str::from_utf8(&line[..close])
+                .is_ok_and(|id| crate::semantic::heading_id_attribute(id).is_some())
+    }
+
+    /// Number of whitespace bytes between the start of the physical line and `idx`.
+    fn indent_before(&self, idx: u32) -> u32 {
+        let mut i = idx;
+        while i > 0 && matches!(self.buf(i - 1), b' ' | b'\t') {
+            i -= 1;
+        }
+        idx - i
+    }
+
     fn is_keycap_emoji_start(&self, idx: u32) -> bool {
         let base = self.buf(idx);
         if !matches!(base, b'0'..=b'9' | b'#' | b'*') {
@@ -891,6 +1805,177 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// Check for a CommonMark autolink at the current `<`: `<scheme:...>` or `<user@host>`,
+    /// with no spaces inside.
+    fn is_autolink_start(&self) -> bool {
+        let rest = &self.buffer[self.index as usize + 1..];
+        let Some(len) = rest.iter().position(|&b| {
+            b == b'>' || b == b'<' || b.is_ascii_whitespace() || b.is_ascii_control()
+        }) else {
+            return false;
+        };
+        if rest[len] != b'>' {
+            return false;
+        }
+        let inner = &rest[..len];
+        if let Some(colon) = inner.iter().position(|&b| b == b':') {
+            let scheme = &inner[..colon];
+            if (2..=32).contains(&scheme.len())
+                && scheme[0].is_ascii_alphabetic()
+                && scheme
+                    .iter()
+                    .all(|&b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'.' | b'-'))
+            {
+                return true;
+            }
+        }
+        email_autolink_len(inner) == Some(inner.len())
+    }
+
+    /// End of a GFM autolink literal (`https://...`, `www....` or an email address)
+    /// starting at `i`, when literals are enabled and `i` begins a word.
+    fn autolink_literal_at(&self, i: u32) -> Option<u32> {
+        if !self.autolink_literals || self.in_link_url {
+            return None;
+        }
+        if !self.starts_word(i) {
+            return None;
+        }
+        let rest = &self.buffer[i as usize..];
+        let domain_start = if rest.starts_with(b"https://") {
+            8
+        } else if rest.starts_with(b"http://") {
+            7
+        } else if rest.starts_with(b"www.") {
+            0
+        } else {
+            return email_autolink_len(rest).map(|len| i + len as u32);
+        };
+
+        let mut end = domain_start;
+        while end < rest.len()
+            && !rest[end].is_ascii_whitespace()
+            && rest[end] != b'<'
+            && !(self.in_table && rest[end] == b'|')
+        {
+            end += 1;
+        }
+        let len = trim_autolink_trailing(&rest[..end]);
+        // Trimming must leave a valid domain behind.
+        autolink_domain_len(&rest[domain_start..len])?;
+        Some(i + len as u32)
+    }
+
+    /// End of the trailing punctuation GFM leaves off an autolink literal ending at `i`,
+    /// when it holds `*`, `_` or `~` that cannot close a span already open. Those would
+    /// otherwise open emphasis or strikethrough that never closes, so the link claims
+    /// them as text instead. Returns `i` when there is nothing to claim.
+    fn autolink_text_tail_end(&self, i: u32) -> u32 {
+        let mut end = i;
+        let mut has_delimiter = false;
+        loop {
+            match self.buf(end) {
+                b'*' if self.star_emphasis_depth > 0 || self.star_strong_depth > 0 => return i,
+                b'_' if self.underscore_emphasis_depth > 0 || self.underscore_strong_depth > 0 => {
+                    return i;
+                }
+                b'~' if self.strikethrough_depth > 0 || self.subscript_depth > 0 => return i,
+                b'*' | b'_' | b'~' => has_delimiter = true,
+                b'?' | b'!' | b'.' | b',' | b':' => {}
+                _ => break,
+            }
+            end += 1;
+        }
+        if has_delimiter { end } else { i }
+    }
+
+    /// End of a NIP-21 `nostr:` URI starting at `i`, when enabled, `i` begins a word and
+    /// the bech32 entity decodes.
+    fn nostr_uri_at(&self, i: u32) -> Option<u32> {
+        if !self.nostr || self.in_link_url {
+            return None;
+        }
+        if !self.starts_word(i) {
+            return None;
+        }
+        let rest = self.buffer[i as usize..].strip_prefix(b"nostr:")?;
+        let len = rest
+            .iter()
+            .position(|b| !b.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let bech32 = std::str::from_utf8(&rest[..len]).ok()?;
+        crate::nostr::decode(bech32)?;
+        Some(i + (b"nostr:".len() + len) as u32)
+    }
+
+    /// End of a `#hashtag` starting at `i`: word characters after a single `#`, not all
+    /// of them digits, so `#1` and `# Heading` are left alone.
+    fn hashtag_at(&self, i: u32) -> Option<u32> {
+        if !self.hashtags || self.in_link_url || self.buf(i) != b'#' || !self.starts_word(i) {
+            return None;
+        }
+        let mut end = i + 1;
+        let mut has_non_digit = false;
+        while let Some(ch) = self.char_at(end)
+            && (ch.is_alphanumeric() || ch == '_')
+        {
+            has_non_digit |= !ch.is_ascii_digit();
+            end += ch.len_utf8() as u32;
+        }
+        has_non_digit.then_some(end)
+    }
+
+    /// End of an `@mention` starting at `i`: word characters, `.` and `-` after the `@`,
+    /// without trailing punctuation. Email addresses do not start a word at the `@`.
+    fn mention_at(&self, i: u32) -> Option<u32> {
+        if !self.mentions || self.in_link_url || self.buf(i) != b'@' || !self.starts_word(i) {
+            return None;
+        }
+        let mut end = i + 1;
+        let mut word_end = end;
+        while let Some(ch) = self.char_at(end)
+            && (ch.is_alphanumeric() || matches!(ch, '_' | '.' | '-'))
+        {
+            end += ch.len_utf8() as u32;
+            if !matches!(ch, '.' | '-') {
+                word_end = end;
+            }
+        }
+        (word_end > i + 1 && !matches!(self.buf(i + 1), b'.' | b'-')).then_some(word_end)
+    }
+
+    /// End of a `:shortcode:` starting at `i` that names one of the caller's custom emoji.
+    fn custom_emoji_at(&self, i: u32) -> Option<u32> {
+        let table = self.custom_emoji?;
+        if self.in_link_url || self.buf(i) != b':' {
+            return None;
+        }
+        let (shortcode, end) = parse_shortcode(self.buffer, i as usize)?;
+        table.contains_key(shortcode).then_some(end as u32)
+    }
+
+    /// Whether `i` is at the start of a word, where autolink literals and other inline
+    /// extensions may begin.
+    fn starts_word(&self, i: u32) -> bool {
+        i == 0
+            || matches!(
+                self.buf(i - 1),
+                b' ' | b'\t' | b'\n' | b'*' | b'_' | b'~' | b'('
+            )
+    }
+
+    /// The UTF-8 character starting at byte `i`, if any.
+    fn char_at(&self, i: u32) -> Option<char> {
+        let rest = self.buffer.get(i as usize..)?;
+        let len = match *rest.first()? {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            _ => 4,
+        };
+        std::str::from_utf8(rest.get(..len)?).ok()?.chars().next()
+    }
+
     fn peek_ahead(&self, needle: &str) -> bool {
         let needle = needle.as_bytes();
         let idx = self.index as usize;
@@ -920,7 +2005,9 @@ impl<'a> Tokenizer<'a> {
     }
 
     fn push_mode(&mut self, mode: Mode) {
-        self.mode_stack.push(self.mode);
+        if self.mode_stack.len() < MAX_NESTING_DEPTH as usize {
+            self.mode_stack.push(self.mode);
+        }
         self.mode = mode;
     }
 
@@ -929,6 +2016,142 @@ impl<'a> Tokenizer<'a> {
     }
 }
 
+/// Whether JavaScript `source` ends inside a `//` comment, in which case a closing
+/// brace written right after it would be commented out.
+pub(crate) fn ends_in_line_comment(source: &str) -> bool {
+    let tokenizer = Tokenizer::new(source);
+    let len = source.len() as u32;
+    let mut i = 0;
+    while i < len {
+        if let Some(end) = tokenizer.js_literal_end(i) {
+            if end == len && source[i as usize..].starts_with("//") {
+                return true;
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    false
+}
+
+/// Length of the domain at the start of `bytes`: segments of alphanumerics, `_` and `-`
+/// separated by periods, with at least one period and no `_` in the last two segments.
+fn autolink_domain_len(bytes: &[u8]) -> Option<usize> {
+    let len = bytes
+        .iter()
+        .position(|&b| !(b.is_ascii_alphanumeric() || b >= 0x80 || matches!(b, b'_' | b'-' | b'.')))
+        .unwrap_or(bytes.len());
+    let domain = &bytes[..bytes[..len]
+        .iter()
+        .rposition(|&b| b != b'.')
+        .map_or(0, |p| p + 1)];
+    let segments: Vec<&[u8]> = domain.split(|&b| b == b'.').collect();
+    if segments.len() < 2 || segments.iter().any(|s| s.is_empty()) {
+        return None;
+    }
+    if segments[segments.len() - 2..]
+        .iter()
+        .any(|s| s.contains(&b'_'))
+    {
+        return None;
+    }
+    Some(len)
+}
+
+/// Apply GFM's trailing punctuation rules to an autolink literal candidate and return
+/// the trimmed length: drop trailing `?!.,:*_~`, unbalanced `)`, and entity-like `&x;`.
+fn trim_autolink_trailing(link: &[u8]) -> usize {
+    let mut len = link.len();
+    while len > 0 {
+        match link[len - 1] {
+            b'?' | b'!' | b'.' | b',' | b':' | b'*' | b'_' | b'~' => len -= 1,
+            b')' => {
+                let opens = link[..len].iter().filter(|&&b| b == b'(').count();
+                let closes = link[..len].iter().filter(|&&b| b == b')').count();
+                if closes <= opens {
+                    break;
+                }
+                len -= 1;
+            }
+            b';' => {
+                let name_start = link[..len - 1]
+                    .iter()
+                    .rposition(|b| !b.is_ascii_alphanumeric())
+                    .map_or(0, |p| p + 1);
+                if name_start == 0 || name_start == len - 1 || link[name_start - 1] != b'&' {
+                    break;
+                }
+                len = name_start - 1;
+            }
+            _ => break,
+        }
+    }
+    len
+}
+
+/// Length of an email address at the start of `bytes`: a local part of alphanumerics and
+/// `.+-_`, `@`, then a domain with at least one period not ending in `-` or `_`.
+fn email_autolink_len(bytes: &[u8]) -> Option<usize> {
+    let at = bytes
+        .iter()
+        .position(|&b| !(b.is_ascii_alphanumeric() || matches!(b, b'.' | b'+' | b'-' | b'_')))?;
+    if at == 0 || bytes[at] != b'@' {
+        return None;
+    }
+    let domain = &bytes[at + 1..];
+    let mut len = domain
+        .iter()
+        .position(|&b| !(b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_')))
+        .unwrap_or(domain.len());
+    while len > 0 && domain[len - 1] == b'.' {
+        len -= 1;
+    }
+    let domain = &domain[..len];
+    if !domain.contains(&b'.')
+        || domain.split(|&b| b == b'.').any(|s| s.is_empty())
+        || matches!(domain.last(), Some(b'-' | b'_'))
+    {
+        return None;
+    }
+    Some(at + 1 + len)
+}
+
+/// Reject prose that merely starts with the word `import` or `export`: an import needs a
+/// quoted module specifier, an export a declaration keyword or a `{`/`*` list.
+fn esm_body_is_plausible(tag: Tag, body: &[u8]) -> bool {
+    let body = body.trim_ascii_start();
+    match tag {
+        Tag::EsmImport => {
+            matches!(body.first(), Some(b'"' | b'\''))
+                || body.windows(4).enumerate().any(|(i, window)| {
+                    window == b"from"
+                        && (i == 0 || matches!(body[i - 1], b' ' | b'\t' | b'\n' | b'}'))
+                        && matches!(body[i + 4..].trim_ascii_start().first(), Some(b'"' | b'\''))
+                })
+        }
+        _ => {
+            if matches!(body.first(), Some(b'{' | b'*')) {
+                return true;
+            }
+            let word_len = body.iter().take_while(|b| b.is_ascii_alphabetic()).count();
+            matches!(
+                &body[..word_len],
+                b"const"
+                    | b"let"
+                    | b"var"
+                    | b"function"
+                    | b"class"
+                    | b"default"
+                    | b"async"
+                    | b"type"
+                    | b"interface"
+                    | b"enum"
+            )
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -990,6 +2213,22 @@ mod tests {
         assert_eq!(Tag::ExprEnd, tok3.tag);
     }
 
+    #[test]
+    fn tokenize_expression_skips_braces_in_literals() {
+        let source = "{f(\"}\", `${a}}`) /* { */}";
+        let mut tokenizer = Tokenizer::new(source);
+
+        assert_eq!(Tag::ExprStart, tokenizer.next().tag);
+        let text = tokenizer.next();
+        assert_eq!(Tag::Text, text.tag);
+        assert_eq!(
+            "f(\"}\", `${a}}`) /* { */",
+            &source[text.loc.start as usize..text.loc.end as usize]
+        );
+        assert_eq!(Tag::ExprEnd, tokenizer.next().tag);
+        assert_eq!(Tag::Eof, tokenizer.next().tag);
+    }
+
     #[test]
     fn tokenize_frontmatter() {
         let source = "---\ntitle: Hello\n---\n";
@@ -999,6 +2238,51 @@ mod tests {
         assert_eq!(Tag::FrontmatterStart, tok1.tag);
     }
 
+    #[test]
+    fn tokenize_multi_line_esm_import() {
+        let source = "import {\n  A,\n  B,\n} from './x'\n";
+        let mut tokenizer = Tokenizer::new(source);
+
+        let tok1 = tokenizer.next();
+        assert_eq!(Tag::EsmImport, tok1.tag);
+        assert_eq!(source.len() as u32 - 1, tok1.loc.end);
+        assert_eq!(Tag::Newline, tokenizer.next().tag);
+        assert_eq!(Tag::Eof, tokenizer.next().tag);
+    }
+
+    #[test]
+    fn tokenize_consecutive_link_definitions() {
+        let source = "[a]: /a \"A\"\n[b]: <./b c>\n";
+        let mut tokenizer = Tokenizer::new(source);
+
+        let tok1 = tokenizer.next();
+        assert_eq!(Tag::LinkDefinition, tok1.tag);
+        assert_eq!(11, tok1.loc.end);
+        assert_eq!(Tag::Newline, tokenizer.next().tag);
+        assert_eq!(Tag::LinkDefinition, tokenizer.next().tag);
+        assert_eq!(Tag::Newline, tokenizer.next().tag);
+        assert_eq!(Tag::Eof, tokenizer.next().tag);
+    }
+
+    #[test]
+    fn tokenize_long_fence_with_inner_backticks() {
+        let source = "````\n```\n````  \n";
+        let mut tokenizer = Tokenizer::new(source);
+
+        assert_eq!(Tag::CodeFenceStart, tokenizer.next().tag);
+        assert_eq!(Tag::Newline, tokenizer.next().tag);
+        let inner = tokenizer.next();
+        assert_eq!(Tag::Text, inner.tag);
+        assert_eq!(
+            "```",
+            &source[inner.loc.start as usize..inner.loc.end as usize]
+        );
+        assert_eq!(Tag::Newline, tokenizer.next().tag);
+        let close = tokenizer.next();
+        assert_eq!(Tag::CodeFenceEnd, close.tag);
+        assert_eq!(15, close.loc.end);
+    }
+
     #[test]
     fn keycap_emoji_not_tokenized_as_markdown_syntax() {
         let source = "#️⃣ heading keycap\n*️⃣ star keycap\n";
@@ -1,8 +1,11 @@
 use crate::ast::*;
 use crate::semantic::{
-    JsxAttributeValue, code_block_info, expression_info, frontmatter_view, image_view,
-    jsx_attribute_type_name, jsx_element_view, link_view,
+    EsmImportKind, FootnoteTable, HeadingTable, JsxAttributeValue, blockquote_view,
+    code_block_info, decode_markdown_text, definition_view, esm_view, expression_info,
+    footnote_table, frontmatter_view, heading_table, image_view, jsx_attribute_type_name,
+    jsx_element_view, link_view, math_info, nostr_reference_view,
 };
+use std::borrow::Cow;
 use std::fmt::Write;
 
 /// Write a JSON-escaped string
@@ -64,6 +67,13 @@ impl Default for SerializeOptions {
     }
 }
 
+/// Document-wide state computed once per serialization.
+struct Pass<'a> {
+    options: &'a SerializeOptions,
+    footnotes: FootnoteTable<'a>,
+    headings: HeadingTable<'a>,
+}
+
 /// Serialize the AST as a nested tree structure to JSON
 pub fn serialize_tree(ast: &Ast) -> String {
     serialize_tree_with_options(ast, &SerializeOptions::default())
@@ -72,6 +82,11 @@ pub fn serialize_tree(ast: &Ast) -> String {
 /// Serialize the AST with options
 pub fn serialize_tree_with_options(ast: &Ast, options: &SerializeOptions) -> String {
     let mut output = String::with_capacity(estimated_serialized_capacity(ast));
+    let pass = Pass {
+        options,
+        footnotes: footnote_table(ast),
+        headings: heading_table(ast),
+    };
 
     output.push_str("{\"schema\":{\"name\":");
     write_json_string(&mut output, AST_SCHEMA_NAME);
@@ -94,7 +109,7 @@ pub fn serialize_tree_with_options(ast: &Ast, options: &SerializeOptions) -> Str
             if i > 0 {
                 output.push(',');
             }
-            serialize_node(ast, child_idx, &mut output, options);
+            serialize_node(ast, child_idx, &mut output, &pass);
         }
     }
 
@@ -124,7 +139,7 @@ pub fn serialize_tree_with_options(ast: &Ast, options: &SerializeOptions) -> Str
     output
 }
 
-fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options: &SerializeOptions) {
+fn serialize_node<'a>(ast: &'a Ast, node_idx: NodeIndex, output: &mut String, pass: &Pass<'a>) {
     let node = &ast.nodes[node_idx as usize];
 
     output.push('{');
@@ -132,7 +147,7 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
     output.push_str(node.tag.name());
     output.push('"');
 
-    if options.include_positions {
+    if pass.options.include_positions {
         let span = ast.node_span(node_idx);
         write!(
             output,
@@ -142,11 +157,23 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
         .expect("writing node position into a String cannot fail");
     }
 
+    if ast.is_incomplete(node_idx) {
+        output.push_str(",\"incomplete\":true");
+    }
+
     match node.tag {
         NodeTag::Heading => {
             let info = ast.heading_info(node_idx);
             write!(output, ",\"level\":{}", info.level)
                 .expect("writing heading level into a String cannot fail");
+            if let Some(view) = pass.headings.view(node_idx) {
+                if let Some(id) = view.id {
+                    output.push_str(",\"id\":");
+                    write_json_string(output, id);
+                }
+                output.push_str(",\"slug\":");
+                write_json_string(output, &view.slug);
+            }
             output.push_str(",\"children\":[");
             let children =
                 &ast.extra_data[info.children_start as usize..info.children_end as usize];
@@ -154,28 +181,28 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
                 if i > 0 {
                     output.push(',');
                 }
-                serialize_node(ast, child_raw, output, options);
+                serialize_node(ast, child_raw, output, pass);
             }
             output.push(']');
         }
 
         NodeTag::Text => {
-            let text = ast.token_slice(node.main_token);
+            let text = decode_markdown_text(ast.token_slice(node.main_token));
             output.push_str(",\"value\":");
-            write_json_string(output, text);
+            write_json_string(output, &text);
         }
 
         NodeTag::CodeBlock => {
             let info = code_block_info(ast, node_idx);
             output.push_str(",\"lang\":");
-            if let Some(l) = info.and_then(|value| value.lang) {
+            if let Some(l) = info.as_ref().and_then(|value| value.lang) {
                 write_json_string(output, l);
             } else {
                 output.push_str("null");
             }
 
             output.push_str(",\"value\":");
-            write_json_string(output, info.map(|value| value.code).unwrap_or(""));
+            write_json_string(output, info.as_ref().map_or("", |value| &value.code));
         }
 
         NodeTag::CodeInline => {
@@ -187,17 +214,40 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
         }
 
         NodeTag::Link | NodeTag::Image => {
-            output.push_str(",\"url\":");
-            let url = if node.tag == NodeTag::Link {
-                link_view(ast, node_idx)
-                    .map(|value| value.url)
-                    .unwrap_or("")
+            let (url, title, reference, label) = if node.tag == NodeTag::Link {
+                link_view(ast, node_idx).map(|value| {
+                    (
+                        value.url,
+                        value.title,
+                        value.reference,
+                        value.reference_label,
+                    )
+                })
             } else {
-                image_view(ast, node_idx)
-                    .map(|value| value.url)
-                    .unwrap_or("")
-            };
-            write_json_string(output, url);
+                image_view(ast, node_idx).map(|value| {
+                    (
+                        value.url,
+                        value.title,
+                        value.reference,
+                        value.reference_label,
+                    )
+                })
+            }
+            .unwrap_or((Cow::Borrowed(""), None, LinkReference::Inline, None));
+            output.push_str(",\"url\":");
+            write_json_string(output, &url);
+            if let Some(title) = title {
+                output.push_str(",\"title\":");
+                write_json_string(output, &title);
+            }
+            if reference != LinkReference::Inline {
+                output.push_str(",\"reference\":");
+                write_json_string(output, reference.name());
+            }
+            if let Some(label) = label {
+                output.push_str(",\"label\":");
+                write_json_string(output, label);
+            }
 
             output.push_str(",\"children\":[");
             let children = if node.tag == NodeTag::Link {
@@ -213,7 +263,7 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
                 if i > 0 {
                     output.push(',');
                 }
-                serialize_node(ast, child_idx, output, options);
+                serialize_node(ast, child_idx, output, pass);
             }
             output.push(']');
         }
@@ -226,6 +276,10 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
                 output,
                 element.as_ref().map(|value| value.name).unwrap_or(""),
             );
+            // Only HTML elements are flagged, so component payloads keep their shape.
+            if element.as_ref().is_some_and(|value| value.is_intrinsic) {
+                output.push_str(",\"intrinsic\":true");
+            }
 
             // Serialize attributes
             output.push_str(",\"attributes\":[");
@@ -285,7 +339,7 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
                     if i > 0 {
                         output.push(',');
                     }
-                    serialize_node(ast, child_idx, output, options);
+                    serialize_node(ast, child_idx, output, pass);
                 }
             }
             output.push(']');
@@ -330,7 +384,7 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
                 if i > 0 {
                     output.push(',');
                 }
-                serialize_node(ast, child_idx, output, options);
+                serialize_node(ast, child_idx, output, pass);
             }
             output.push(']');
         }
@@ -358,7 +412,7 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
                 if i > 0 {
                     output.push(',');
                 }
-                serialize_node(ast, child_idx, output, options);
+                serialize_node(ast, child_idx, output, pass);
             }
             output.push(']');
         }
@@ -370,7 +424,7 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
                 if i > 0 {
                     output.push(',');
                 }
-                serialize_node(ast, child_idx, output, options);
+                serialize_node(ast, child_idx, output, pass);
             }
             output.push(']');
         }
@@ -388,7 +442,7 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
                 if i > 0 {
                     output.push(',');
                 }
-                serialize_node(ast, child_idx, output, options);
+                serialize_node(ast, child_idx, output, pass);
             }
             output.push(']');
         }
@@ -400,19 +454,181 @@ fn serialize_node(ast: &Ast, node_idx: NodeIndex, output: &mut String, options:
         | NodeTag::Strong
         | NodeTag::Emphasis
         | NodeTag::Strikethrough
+        | NodeTag::Spoiler
+        | NodeTag::Highlight
+        | NodeTag::Underline
+        | NodeTag::Superscript
+        | NodeTag::Subscript
         | NodeTag::MdxJsxFragment => {
+            if let Some(alert) = blockquote_view(ast, node_idx).and_then(|view| view.alert) {
+                output.push_str(",\"alert\":\"");
+                output.push_str(alert.name());
+                output.push('"');
+            }
             output.push_str(",\"children\":[");
             let children = ast.children(node_idx);
             for (i, &child_idx) in children.iter().enumerate() {
                 if i > 0 {
                     output.push(',');
                 }
-                serialize_node(ast, child_idx, output, options);
+                serialize_node(ast, child_idx, output, pass);
             }
             output.push(']');
         }
 
-        NodeTag::Hr | NodeTag::HardBreak => {
+        NodeTag::FootnoteReference => {
+            let view = pass.footnotes.reference(ast, node_idx);
+            output.push_str(",\"label\":");
+            write_json_string(output, view.map_or("", |value| value.label));
+            output.push_str(",\"number\":");
+            match view {
+                Some(value) => write!(output, "{}", value.number)
+                    .expect("writing footnote number into a String cannot fail"),
+                None => output.push_str("null"),
+            }
+        }
+
+        NodeTag::FootnoteDefinition => {
+            let label = ast.footnote_label(node_idx).unwrap_or("");
+            output.push_str(",\"label\":");
+            write_json_string(output, label);
+            output.push_str(",\"number\":");
+            match pass.footnotes.definition(node_idx) {
+                Some(value) => write!(output, "{}", value.number)
+                    .expect("writing footnote number into a String cannot fail"),
+                None => output.push_str("null"),
+            }
+            output.push_str(",\"children\":[");
+            for (i, &child_idx) in ast.children(node_idx).iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                serialize_node(ast, child_idx, output, pass);
+            }
+            output.push(']');
+        }
+
+        NodeTag::Definition => {
+            if let Some(view) = definition_view(ast, node_idx) {
+                output.push_str(",\"label\":");
+                write_json_string(output, view.label);
+                output.push_str(",\"url\":");
+                write_json_string(output, &view.url);
+                if let Some(title) = view.title {
+                    output.push_str(",\"title\":");
+                    write_json_string(output, &title);
+                }
+            }
+        }
+
+        NodeTag::MdxEsmImport | NodeTag::MdxEsmExport => {
+            if let Some(view) = esm_view(ast, node_idx) {
+                output.push_str(",\"value\":");
+                write_json_string(output, view.value);
+
+                output.push_str(",\"imports\":[");
+                for (i, binding) in view.imports.iter().enumerate() {
+                    if i > 0 {
+                        output.push(',');
+                    }
+                    output.push_str("{\"kind\":\"");
+                    output.push_str(match binding.kind {
+                        EsmImportKind::Default => "default",
+                        EsmImportKind::Named => "named",
+                        EsmImportKind::Namespace => "namespace",
+                    });
+                    output.push_str("\",\"imported\":");
+                    write_json_string(output, binding.imported);
+                    output.push_str(",\"local\":");
+                    write_json_string(output, binding.local);
+                    output.push_str(",\"source\":");
+                    write_json_string(output, binding.source);
+                    output.push('}');
+                }
+                output.push(']');
+
+                output.push_str(",\"exports\":[");
+                for (i, name) in view.exports.iter().enumerate() {
+                    if i > 0 {
+                        output.push(',');
+                    }
+                    write_json_string(output, name);
+                }
+                output.push(']');
+            }
+        }
+
+        NodeTag::MathInline | NodeTag::MathBlock => {
+            output.push_str(",\"value\":");
+            write_json_string(output, math_info(ast, node_idx).map_or("", |info| info.tex));
+        }
+
+        NodeTag::NostrReference => {
+            if let Some(view) = nostr_reference_view(ast, node_idx) {
+                let entity = &view.entity;
+                output.push_str(",\"entity\":");
+                write_json_string(output, entity.kind.prefix());
+                output.push_str(",\"value\":");
+                write_json_string(output, view.bech32);
+                if let Some(pubkey) = &entity.pubkey {
+                    output.push_str(",\"pubkey\":");
+                    write_json_string(output, pubkey);
+                }
+                if let Some(event_id) = &entity.event_id {
+                    output.push_str(",\"event_id\":");
+                    write_json_string(output, event_id);
+                }
+                if let Some(identifier) = &entity.identifier {
+                    output.push_str(",\"identifier\":");
+                    write_json_string(output, identifier);
+                }
+                if let Some(kind) = entity.event_kind {
+                    write!(output, ",\"kind\":{kind}")
+                        .expect("writing an event kind into a String cannot fail");
+                }
+                if !entity.relays.is_empty() {
+                    output.push_str(",\"relays\":[");
+                    for (i, relay) in entity.relays.iter().enumerate() {
+                        if i > 0 {
+                            output.push(',');
+                        }
+                        write_json_string(output, relay);
+                    }
+                    output.push(']');
+                }
+            }
+        }
+
+        NodeTag::Hashtag => {
+            output.push_str(",\"value\":");
+            write_json_string(output, ast.hashtag_name(node_idx).unwrap_or(""));
+        }
+
+        NodeTag::Mention => {
+            output.push_str(",\"value\":");
+            write_json_string(output, ast.mention_name(node_idx).unwrap_or(""));
+        }
+
+        NodeTag::CustomEmoji => {
+            if let Some(view) = ast.custom_emoji_view(node_idx) {
+                output.push_str(",\"shortcode\":");
+                write_json_string(output, view.shortcode);
+                output.push_str(",\"url\":");
+                write_json_string(output, view.url);
+            }
+        }
+
+        NodeTag::HtmlComment => {
+            output.push_str(",\"value\":");
+            write_json_string(output, ast.html_comment_text(node_idx).unwrap_or(""));
+        }
+
+        NodeTag::Recovery => {
+            output.push_str(",\"value\":");
+            write_json_string(output, ast.recovery_source(node_idx).unwrap_or(""));
+        }
+
+        NodeTag::Hr | NodeTag::HardBreak | NodeTag::SoftBreak => {
             // No additional data
         }
 
@@ -0,0 +1,83 @@
+use hypernote_mdx::ast::{Ast, NodeIndex, NodeTag};
+use hypernote_mdx::semantic::AlertKind;
+use hypernote_mdx::{parse, render, serialize_tree};
+use serde_json::Value;
+
+fn blockquotes(ast: &Ast) -> Vec<NodeIndex> {
+    ast.nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| n.tag == NodeTag::Blockquote)
+        .map(|(idx, _)| idx as NodeIndex)
+        .collect()
+}
+
+#[test]
+fn alert_marker_sets_the_kind_and_is_not_content() {
+    let source = "> [!WARNING]\n> Payments are **final**.\n>\n> Double-check the amount.\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let quote = ast.blockquote_view(blockquotes(&ast)[0]).unwrap();
+    assert_eq!(Some(AlertKind::Warning), quote.alert);
+    assert_eq!(2, quote.children.len());
+    assert_eq!(
+        Some("Payments are final.".to_string()),
+        ast.plain_text(quote.children[0])
+    );
+    assert!(!ast.nodes.iter().any(|n| n.tag == NodeTag::Link));
+}
+
+#[test]
+fn every_gfm_kind_is_recognized_in_any_case() {
+    let cases = [
+        ("NOTE", AlertKind::Note),
+        ("tip", AlertKind::Tip),
+        ("Important", AlertKind::Important),
+        ("WARNING", AlertKind::Warning),
+        ("caution", AlertKind::Caution),
+    ];
+    for (marker, kind) in cases {
+        let ast = parse(&format!("> [!{marker}]\n> Body\n"));
+        let quote = ast.blockquote_view(blockquotes(&ast)[0]).unwrap();
+        assert_eq!(Some(kind), quote.alert, "for {marker:?}");
+    }
+}
+
+#[test]
+fn other_blockquotes_are_not_alerts() {
+    let cases = [
+        "> Just a quote\n",
+        "> [!NOTE] with text after\n",
+        "> [!UNKNOWN]\n> Body\n",
+        "> Intro\n> [!NOTE]\n",
+    ];
+    for source in cases {
+        let ast = parse(source);
+        let quote = ast.blockquote_view(blockquotes(&ast)[0]).unwrap();
+        assert_eq!(None, quote.alert, "for {source:?}");
+        assert_eq!(1, quote.children.len(), "for {source:?}");
+    }
+
+    let paragraph = parse("Paragraph\n");
+    assert!(paragraph.blockquote_view(0).is_none());
+}
+
+#[test]
+fn alerts_round_trip_through_render_and_serialize() {
+    let source = "> [!tip]\n> Use the **fast** path.\n\nAfter\n";
+    let ast = parse(source);
+
+    let rendered = render(&ast);
+    assert_eq!("> [!TIP]\n> Use the **fast** path.\n\nAfter\n", rendered);
+    assert_eq!(rendered, render(&parse(&rendered)));
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let quote = &json["children"][0];
+    assert_eq!("blockquote", quote["type"]);
+    assert_eq!("tip", quote["alert"]);
+    assert_eq!(1, quote["children"].as_array().unwrap().len());
+
+    let plain: Value = serde_json::from_str(&serialize_tree(&parse("> Quote\n"))).unwrap();
+    assert!(plain["children"][0].get("alert").is_none());
+}
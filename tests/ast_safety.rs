@@ -3,11 +3,12 @@ use hypernote_mdx::ast::{
 };
 use hypernote_mdx::token::Tag as TokenTag;
 use hypernote_mdx::{render, serialize_tree};
+use std::collections::HashMap;
 use std::panic::{AssertUnwindSafe, catch_unwind};
 
-fn malformed_ast() -> Ast {
+fn malformed_ast() -> Ast<'static> {
     Ast {
-        source: "ok".to_string(),
+        source: "ok".into(),
         token_tags: vec![TokenTag::Text, TokenTag::Eof],
         token_starts: vec![0, 2],
         nodes: vec![
@@ -47,8 +48,12 @@ fn malformed_ast() -> Ast {
                 data: NodeData::None,
             },
         ],
+        node_spans: Vec::new(),
         extra_data: vec![1, 2, 3, 4, 5, 6],
         errors: vec![],
+        custom_emoji: HashMap::new(),
+        source_edits: Vec::new(),
+        incomplete: Vec::new(),
     }
 }
 
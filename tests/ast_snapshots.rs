@@ -15,13 +15,14 @@ fn markdown_ast_snapshot_is_stable() {
     let expected = json!({
         "schema": {
             "name": "hypernote-mdx-ast",
-            "version": 1
+            "version": 2
         },
         "type": "root",
         "children": [
             {
                 "type": "heading",
                 "level": 1,
+                "slug": "launch",
                 "children": [
                     {
                         "type": "text",
@@ -78,7 +79,7 @@ fn jsx_ast_snapshot_is_stable() {
     let expected = json!({
         "schema": {
             "name": "hypernote-mdx-ast",
-            "version": 1
+            "version": 2
         },
         "type": "root",
         "children": [
@@ -134,7 +135,7 @@ fn table_ast_snapshot_is_stable() {
     let expected = json!({
         "schema": {
             "name": "hypernote-mdx-ast",
-            "version": 1
+            "version": 2
         },
         "type": "root",
         "children": [
@@ -0,0 +1,170 @@
+use hypernote_mdx::ast::{Ast, LinkReference, NodeIndex, NodeTag};
+use hypernote_mdx::{ParseOptions, parse, parse_with_options, render, serialize_tree};
+use serde_json::Value;
+
+fn link_urls(ast: &Ast) -> Vec<String> {
+    links(ast)
+        .into_iter()
+        .map(|idx| ast.link_view(idx).unwrap().url.into_owned())
+        .collect()
+}
+
+fn links(ast: &Ast) -> Vec<NodeIndex> {
+    ast.nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| n.tag == NodeTag::Link)
+        .map(|(idx, _)| idx as NodeIndex)
+        .collect()
+}
+
+#[test]
+fn angle_bracket_autolinks_are_links_not_jsx() {
+    let source = "See <https://example.com/a?b=1> or <team@example.com>.\n";
+    let ast = parse(source);
+
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    assert!(!ast.nodes.iter().any(|n| n.tag == NodeTag::MdxJsxElement));
+    assert_eq!(
+        vec!["https://example.com/a?b=1", "mailto:team@example.com"],
+        link_urls(&ast)
+    );
+    let link = ast.link_view(links(&ast)[0]).unwrap();
+    assert_eq!(LinkReference::Autolink, link.reference);
+    assert!(!link.auto_detected);
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn bare_urls_and_emails_are_linkified() {
+    let source =
+        "ping https://example.com/x, www.commonmark.org/help or ops@example.org about it\n";
+    let ast = parse(source);
+
+    assert_eq!(
+        vec![
+            "https://example.com/x",
+            "http://www.commonmark.org/help",
+            "mailto:ops@example.org"
+        ],
+        link_urls(&ast)
+    );
+    for idx in links(&ast) {
+        let link = ast.link_view(idx).unwrap();
+        assert!(link.auto_detected);
+        assert_eq!(LinkReference::Literal, link.reference);
+        let label = ast.token_slice(ast.nodes[link.label_children[0] as usize].main_token);
+        assert!(link.url.ends_with(label), "{} for {label}", link.url);
+    }
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn trailing_punctuation_is_trimmed() {
+    let cases = [
+        ("Go to www.example.com.", "http://www.example.com"),
+        (
+            "(see https://example.com/a_(b))",
+            "https://example.com/a_(b)",
+        ),
+        ("https://example.com/?q=1&x;", "https://example.com/?q=1"),
+        ("**https://example.com/**", "https://example.com/"),
+        (
+            "really? https://example.com/path!?",
+            "https://example.com/path",
+        ),
+    ];
+    for (source, url) in cases {
+        let ast = parse(source);
+        assert_eq!(vec![url], link_urls(&ast), "for {source:?}");
+        assert_eq!(format!("{source}\n"), render(&ast));
+    }
+}
+
+#[test]
+fn non_links_stay_text() {
+    let cases = [
+        "http://localhost:3000",
+        "file.txt and www. alone",
+        "mid-wordhttps://example.com",
+        "`https://example.com` in code",
+        "www.bad_domain.com",
+    ];
+    for source in cases {
+        let ast = parse(source);
+        assert!(link_urls(&ast).is_empty(), "unexpected link in {source:?}");
+    }
+
+    let ast = parse("[https://shown.example](https://target.example)\n");
+    assert_eq!(vec!["https://target.example"], link_urls(&ast));
+}
+
+#[test]
+fn literal_linkification_can_be_disabled() {
+    let source = "https://example.com and <https://example.org>\n";
+    let options = ParseOptions {
+        autolink_literals: false,
+        ..ParseOptions::default()
+    };
+    let ast = parse_with_options(source, &options);
+
+    assert_eq!(vec!["https://example.org"], link_urls(&ast));
+    assert_eq!(source, render(&ast));
+
+    let json: Value = serde_json::from_str(&serialize_tree(&parse(source))).unwrap();
+    let link = &json["children"][0]["children"][0];
+    assert_eq!("link", link["type"]);
+    assert_eq!("literal", link["reference"]);
+    assert_eq!("https://example.com", link["children"][0]["value"]);
+}
+
+#[test]
+fn www_and_email_links_get_a_scheme() {
+    let source = "Visit www.example.com or mail me@x.org and <you@y.org>.\n";
+    let ast = parse(source);
+
+    assert_eq!(
+        vec![
+            "http://www.example.com",
+            "mailto:me@x.org",
+            "mailto:you@y.org"
+        ],
+        link_urls(&ast)
+    );
+    assert_eq!(
+        Some("Visit www.example.com or mail me@x.org and you@y.org.".to_string()),
+        ast.plain_text(ast.children(ast.nodes.len() as NodeIndex - 1)[0])
+    );
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let www = &json["children"][0]["children"][1];
+    assert_eq!("http://www.example.com", www["url"]);
+    assert_eq!("www.example.com", www["children"][0]["value"]);
+    let email = &json["children"][0]["children"][3];
+    assert_eq!("mailto:me@x.org", email["url"]);
+    assert_eq!("me@x.org", email["children"][0]["value"]);
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn delimiters_trimmed_off_a_bare_url_stay_text() {
+    let cases = [
+        ("see https://x.com/foo_bar_ ok\n", "https://x.com/foo_bar"),
+        ("https://x.com/*a* b\n", "https://x.com/*a"),
+        ("www.x.com~ and more\n", "http://www.x.com"),
+    ];
+    for (source, url) in cases {
+        let ast = parse(source);
+        assert!(ast.errors.is_empty(), "{source:?}: {:?}", ast.errors);
+        assert!(!ast.nodes.iter().any(|n| n.tag == NodeTag::Recovery));
+        assert_eq!(vec![url], link_urls(&ast), "{source:?}");
+        assert_eq!(source, render(&ast));
+    }
+
+    // A delimiter after the URL still closes a span opened before it.
+    let ast = parse("*see https://x.com* and ~~https://y.com~~\n");
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    assert_eq!(vec!["https://x.com", "https://y.com"], link_urls(&ast));
+    assert!(ast.nodes.iter().any(|n| n.tag == NodeTag::Emphasis));
+    assert!(ast.nodes.iter().any(|n| n.tag == NodeTag::Strikethrough));
+}
@@ -0,0 +1,106 @@
+use hypernote_mdx::ast::{Ast, NodeTag};
+use hypernote_mdx::{ParseOptions, parse, parse_with_options, render, serialize_tree};
+use serde_json::Value;
+
+mod common;
+use common::nodes_by_tag;
+
+fn parse_chat(source: &str) -> Ast<'static> {
+    let options = ParseOptions {
+        chat_inlines: true,
+        ..ParseOptions::default()
+    };
+    parse_with_options(source, &options)
+}
+
+#[test]
+fn chat_inlines_are_off_by_default() {
+    let source = "||secret|| ==hi== ++u++ x^2^ H~2~O\n";
+    let ast = parse(source);
+
+    for tag in [
+        NodeTag::Spoiler,
+        NodeTag::Highlight,
+        NodeTag::Underline,
+        NodeTag::Superscript,
+        NodeTag::Subscript,
+    ] {
+        assert!(nodes_by_tag(&ast, tag).is_empty(), "unexpected {tag:?}");
+    }
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn each_delimiter_becomes_its_node() {
+    let source = "||secret|| and ==hi== and ++u++ x^2^ H~2~O\n";
+    let ast = parse_chat(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    for (tag, text) in [
+        (NodeTag::Spoiler, "secret"),
+        (NodeTag::Highlight, "hi"),
+        (NodeTag::Underline, "u"),
+        (NodeTag::Superscript, "2"),
+        (NodeTag::Subscript, "2"),
+    ] {
+        let nodes = nodes_by_tag(&ast, tag);
+        assert_eq!(1, nodes.len(), "{tag:?}");
+        assert_eq!(Some(text.to_string()), ast.plain_text(nodes[0]));
+    }
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        Some("secret and hi and u x2 H2O".to_string()),
+        ast.plain_text(paragraph)
+    );
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn chat_spans_nest_and_serialize() {
+    let source = "<Note>||**bold** spoiler|| ==a ~~b~~==</Note>\n";
+    let ast = parse_chat(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let tree: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let children = &tree["children"][0]["children"];
+    assert_eq!("spoiler", children[0]["type"]);
+    assert_eq!("strong", children[0]["children"][0]["type"]);
+    assert_eq!("highlight", children[2]["type"]);
+    assert_eq!("strikethrough", children[2]["children"][1]["type"]);
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn ordinary_punctuation_stays_text() {
+    let source = "a || b, x == y, C++/Rust C++, 2^10, ~/a ~/b, a==b==\n";
+    let ast = parse_chat(source);
+
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(1, ast.children(paragraph).len());
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn escaped_delimiters_do_not_close_a_span() {
+    let ast = parse_chat("||a\\|| and ++b\\++\n");
+    assert!(nodes_by_tag(&ast, NodeTag::Spoiler).is_empty());
+    assert!(nodes_by_tag(&ast, NodeTag::Underline).is_empty());
+
+    // An escaped backslash leaves the delimiter after it free to close.
+    let ast = parse_chat("H~2\\\\~O\n");
+    let subscript = nodes_by_tag(&ast, NodeTag::Subscript)[0];
+    assert_eq!(Some("2\\".to_string()), ast.plain_text(subscript));
+}
+
+#[test]
+fn block_syntax_keeps_its_delimiters() {
+    let ast = parse_chat("Title\n===\n\n~~gone~~ ~kept~\n\n| a | b |\n|---|---|\n");
+
+    assert_eq!(1, nodes_by_tag(&ast, NodeTag::Heading).len());
+    assert_eq!(1, nodes_by_tag(&ast, NodeTag::Strikethrough).len());
+    assert_eq!(1, nodes_by_tag(&ast, NodeTag::Subscript).len());
+    assert_eq!(1, nodes_by_tag(&ast, NodeTag::Table).len());
+    assert!(nodes_by_tag(&ast, NodeTag::Highlight).is_empty());
+    assert!(nodes_by_tag(&ast, NodeTag::Spoiler).is_empty());
+}
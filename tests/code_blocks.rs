@@ -0,0 +1,105 @@
+use hypernote_mdx::ast::NodeTag;
+use hypernote_mdx::semantic::CodeFence;
+use hypernote_mdx::{parse, render};
+
+mod common;
+use common::nodes_by_tag;
+
+#[test]
+fn setext_headings_become_headings() {
+    let source = "Title\n=====\n\nSub *title*\n---\n\nA paragraph\nover two lines\n===\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let headings = nodes_by_tag(&ast, NodeTag::Heading);
+    assert_eq!(
+        vec![1, 2, 1],
+        headings
+            .iter()
+            .map(|&idx| ast.heading_info(idx).level)
+            .collect::<Vec<_>>()
+    );
+    assert!(nodes_by_tag(&ast, NodeTag::Hr).is_empty());
+    assert!(nodes_by_tag(&ast, NodeTag::Paragraph).is_empty());
+    assert_eq!(Some("Title".to_string()), ast.plain_text(headings[0]));
+
+    let rendered = render(&ast);
+    assert!(rendered.starts_with("Title\n=====\n\nSub *title*\n-----------\n"));
+    assert_eq!(rendered, render(&parse(&rendered)));
+}
+
+#[test]
+fn thematic_breaks_still_follow_blocks() {
+    let ast = parse("# Heading\n---\n\n---\n\n- item\n---\n");
+    assert_eq!(1, nodes_by_tag(&ast, NodeTag::Heading).len());
+    assert_eq!(3, nodes_by_tag(&ast, NodeTag::Hr).len());
+
+    let ast = parse("Not = a heading\n= =\n");
+    assert!(nodes_by_tag(&ast, NodeTag::Heading).is_empty());
+}
+
+#[test]
+fn indented_code_blocks_keep_their_content() {
+    let source = "Intro\n\n    fn main() {\n\n        <Card>{x}</Card>\n    }\n\nAfter\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let blocks = nodes_by_tag(&ast, NodeTag::CodeBlock);
+    assert_eq!(1, blocks.len());
+    let info = ast.code_block_info(blocks[0]).unwrap();
+    assert_eq!(CodeFence::Indented, info.fence);
+    assert_eq!(None, info.lang);
+    assert_eq!("fn main() {\n\n    <Card>{x}</Card>\n}\n", info.code);
+    assert!(nodes_by_tag(&ast, NodeTag::MdxJsxElement).is_empty());
+    assert_eq!(
+        Some("fn main() {\n\n    <Card>{x}</Card>\n}".to_string()),
+        ast.plain_text(blocks[0])
+    );
+    assert_eq!(source, render(&ast));
+
+    let ast = parse("\tlet x = 1;\n\t\tnested\n  \tmixed\n");
+    let block = nodes_by_tag(&ast, NodeTag::CodeBlock)[0];
+    let expected = "let x = 1;\n\tnested\nmixed";
+    assert_eq!(
+        format!("{expected}\n"),
+        ast.code_block_info(block).unwrap().code
+    );
+    assert_eq!(Some(expected.to_string()), ast.plain_text(block));
+}
+
+#[test]
+fn indentation_inside_containers_is_not_code() {
+    let cases = [
+        "Paragraph\n    continued line\n",
+        "- item\n\n    continued item\n",
+        "[^n]: Note\n\n    more note\n\nText[^n]\n",
+        "<Card>\n\n    indented child\n\n</Card>\n",
+    ];
+    for source in cases {
+        let ast = parse(source);
+        assert!(
+            nodes_by_tag(&ast, NodeTag::CodeBlock).is_empty(),
+            "unexpected code block in {source:?}"
+        );
+    }
+}
+
+#[test]
+fn tilde_and_long_fences_round_trip() {
+    let source = "~~~py\nprint(\"```\")\n~~~\n\n````md\n```js\nx\n```\n````\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let infos: Vec<_> = nodes_by_tag(&ast, NodeTag::CodeBlock)
+        .into_iter()
+        .map(|idx| ast.code_block_info(idx).unwrap())
+        .collect();
+    assert_eq!(2, infos.len());
+    assert_eq!(CodeFence::Tilde(3), infos[0].fence);
+    assert_eq!(Some("py"), infos[0].lang);
+    assert_eq!("print(\"```\")\n", infos[0].code);
+    assert_eq!(CodeFence::Backtick(4), infos[1].fence);
+    assert_eq!("```js\nx\n```\n", infos[1].code);
+
+    assert_eq!(source, render(&ast));
+}
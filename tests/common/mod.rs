@@ -0,0 +1,160 @@
+//! Helpers shared by the integration tests. Each test binary uses only some of them.
+#![allow(dead_code)]
+
+use hypernote_mdx::ast::{Ast, NodeIndex, NodeTag};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Indices of every node with the given tag, in document order.
+pub fn nodes_by_tag(ast: &Ast, tag: NodeTag) -> Vec<NodeIndex> {
+    ast.nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| n.tag == tag)
+        .map(|(idx, _)| idx as NodeIndex)
+        .collect()
+}
+
+/// Counts the allocations and bytes the current thread allocates, so parallel tests do
+/// not interfere. A test binary opts in with `#[global_allocator]`.
+pub struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        ALLOCATED.with(|allocated| allocated.set(allocated.get() + layout.size()));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+/// Number of allocations `f` makes on the current thread.
+pub fn allocations_in(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+/// The value `f` returns and the bytes it allocates on the current thread.
+pub fn allocated_by<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.with(Cell::get);
+    let value = f();
+    (value, ALLOCATED.with(Cell::get) - before)
+}
+
+/// A chat history of 512 messages, mostly short markdown with some tables and MDX cards.
+pub fn build_chat_corpus() -> Vec<String> {
+    let mut corpus = Vec::with_capacity(512);
+
+    for i in 0..512usize {
+        let message = match i % 10 {
+            0 => tiny_message_status(i),
+            1 => tiny_message_follow_up(i),
+            2 => tiny_message_link(i),
+            3 => tiny_message_checklist(i),
+            4 => tiny_message_quote(i),
+            5 => tiny_message_inline_code(i),
+            6 => tiny_message_reply(i),
+            7 => medium_message_release_note(i),
+            8 => medium_message_table(i),
+            _ => mdx_message_card(i),
+        };
+        corpus.push(message);
+    }
+
+    corpus
+}
+
+fn tiny_message_status(i: usize) -> String {
+    format!(
+        "status update {i}: user-{owner} finished review batch {batch} and queued deploy window {window}\n",
+        owner = i % 19,
+        batch = (i % 7) + 1,
+        window = (i % 5) + 1,
+    )
+}
+
+fn tiny_message_follow_up(i: usize) -> String {
+    format!(
+        "- [ ] follow up with user-{owner}\n- [x] attach note-{i}\n",
+        owner = i % 23,
+    )
+}
+
+fn tiny_message_link(i: usize) -> String {
+    format!("can someone review [ticket-{i}](https://example.com/tickets/{i}) before standup?\n")
+}
+
+fn tiny_message_checklist(i: usize) -> String {
+    format!(
+        "1. sync branch-{i}\n2. rerun `cargo test --test chat_workload`\n3. post summary for room-{room}\n",
+        room = i % 11,
+    )
+}
+
+fn tiny_message_quote(i: usize) -> String {
+    format!(
+        "> note from ops-{ops}: keep rollout group {group} under threshold {threshold}\n",
+        ops = i % 9,
+        group = i % 6,
+        threshold = (i % 4) + 2,
+    )
+}
+
+fn tiny_message_inline_code(i: usize) -> String {
+    format!(
+        "captured failure in `worker_{i}` after retry count `{retries}`\n",
+        retries = (i % 5) + 1,
+    )
+}
+
+fn tiny_message_reply(i: usize) -> String {
+    format!(
+        "replying to note-{parent}: markdown is fine here, but keep body under {limit} lines for mobile\n",
+        parent = i.saturating_sub(1),
+        limit = (i % 3) + 2,
+    )
+}
+
+fn medium_message_release_note(i: usize) -> String {
+    format!(
+        "# release note {i}\n\nowner: user-{owner}\n\nThe chat payload for room-{room} now includes richer markdown sections, nested lists, and stable links for audit-{audit}.\n\n- status: ready\n- reviewer: user-{reviewer}\n- docs: [runbook-{i}](https://example.com/runbooks/{i})\n\n> ship after the final smoke pass for batch-{batch}\n",
+        owner = i % 17,
+        room = i % 13,
+        audit = i % 29,
+        reviewer = (i + 3) % 17,
+        batch = (i % 5) + 1,
+    )
+}
+
+fn medium_message_table(i: usize) -> String {
+    format!(
+        "| item | state | owner |\n| :--- | ---: | :--- |\n| alpha-{i} | {alpha_state} | user-{alpha_owner} |\n| beta-{i} | {beta_state} | user-{beta_owner} |\n| gamma-{i} | {gamma_state} | user-{gamma_owner} |\n",
+        alpha_state = (i % 9) + 1,
+        alpha_owner = i % 14,
+        beta_state = (i % 7) + 2,
+        beta_owner = (i + 1) % 14,
+        gamma_state = (i % 5) + 3,
+        gamma_owner = (i + 2) % 14,
+    )
+}
+
+fn mdx_message_card(i: usize) -> String {
+    let checked = if i % 2 == 0 { "true" } else { "false" };
+    format!(
+        "<Card>\n<Heading>room {room} digest {i}</Heading>\n<Body>message batch {batch} is ready for review by user-{owner}</Body>\n<VStack gap={gap}>\n<ChecklistItem name=\"confirm_{i}\" checked={checked} />\n<SubmitButton action=\"approve_{i}\" variant=\"primary\" />\n</VStack>\n</Card>\n",
+        room = i % 21,
+        batch = (i % 8) + 1,
+        owner = i % 16,
+        gap = (i % 4) + 4,
+        checked = checked,
+    )
+}
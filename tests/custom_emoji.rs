@@ -0,0 +1,97 @@
+use hypernote_mdx::ast::NodeTag;
+use hypernote_mdx::{ParseOptions, parse_with_options, render, serialize_tree};
+use serde_json::Value;
+use std::collections::HashMap;
+
+mod common;
+use common::nodes_by_tag;
+
+fn emoji_options(normalize: bool) -> ParseOptions {
+    let custom_emoji = HashMap::from([
+        (
+            "pepe".to_string(),
+            "https://cdn.example/pepe.png".to_string(),
+        ),
+        (
+            "fire".to_string(),
+            "https://cdn.example/fire.gif".to_string(),
+        ),
+        (
+            "unused".to_string(),
+            "https://cdn.example/unused.png".to_string(),
+        ),
+    ]);
+    ParseOptions {
+        normalize_emoji_shortcodes: normalize,
+        custom_emoji,
+        ..ParseOptions::default()
+    }
+}
+
+#[test]
+fn built_in_shortcodes_cover_the_gemoji_set() {
+    let options = ParseOptions {
+        normalize_emoji_shortcodes: true,
+        ..ParseOptions::default()
+    };
+    let ast = parse_with_options(
+        ":unicorn: :sweat_smile: :+1: :heart: :not_an_emoji:\n",
+        &options,
+    );
+
+    assert_eq!("🦄 😅 👍 ❤️ :not_an_emoji:\n", ast.source);
+    assert!(nodes_by_tag(&ast, NodeTag::CustomEmoji).is_empty());
+}
+
+#[test]
+fn custom_shortcodes_become_nodes_with_their_url() {
+    let ast = parse_with_options(
+        "Nice :pepe::pepe: but not `:pepe:` or :nope:\n",
+        &emoji_options(false),
+    );
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let emoji = nodes_by_tag(&ast, NodeTag::CustomEmoji);
+    assert_eq!(2, emoji.len());
+    let view = ast.custom_emoji_view(emoji[0]).unwrap();
+    assert_eq!("pepe", view.shortcode);
+    assert_eq!("https://cdn.example/pepe.png", view.url);
+
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        Some("Nice :pepe::pepe: but not :pepe: or :nope:".to_string()),
+        ast.plain_text(paragraph)
+    );
+    assert!(ast.custom_emoji_view(paragraph).is_none());
+}
+
+#[test]
+fn custom_emoji_take_precedence_over_built_in_shortcodes() {
+    let ast = parse_with_options("Hot :fire: and :tada:\n", &emoji_options(true));
+
+    assert_eq!("Hot :fire: and 🎉\n", ast.source);
+    let emoji = nodes_by_tag(&ast, NodeTag::CustomEmoji);
+    assert_eq!(1, emoji.len());
+    assert_eq!("fire", ast.custom_emoji_view(emoji[0]).unwrap().shortcode);
+}
+
+#[test]
+fn custom_emoji_round_trip_through_render_and_serialize() {
+    let source = "# Party :pepe:\n\n- [link :pepe:](https://example.com)\n";
+    let ast = parse_with_options(source, &emoji_options(false));
+
+    assert_eq!(source, render(&ast));
+    assert_eq!(
+        vec!["pepe"],
+        ast.custom_emoji
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+    );
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let emoji = &json["children"][0]["children"][1];
+    assert_eq!("custom_emoji", emoji["type"]);
+    assert_eq!("pepe", emoji["shortcode"]);
+    assert_eq!("https://cdn.example/pepe.png", emoji["url"]);
+}
@@ -0,0 +1,151 @@
+use hypernote_mdx::ast::{Ast, ErrorTag, NodeIndex, NodeTag};
+use hypernote_mdx::{parse, render, serialize_tree};
+use serde_json::Value;
+
+fn root_child_types(ast: &Ast) -> Vec<&'static str> {
+    let doc = ast
+        .nodes
+        .iter()
+        .position(|n| n.tag == NodeTag::Document)
+        .expect("expected document node") as NodeIndex;
+    ast.children(doc)
+        .iter()
+        .map(|&idx| ast.nodes[idx as usize].tag.name())
+        .collect()
+}
+
+fn first_node_by_tag(ast: &Ast, tag: NodeTag) -> NodeIndex {
+    ast.nodes
+        .iter()
+        .enumerate()
+        .find_map(|(idx, node)| (node.tag == tag).then_some(idx as NodeIndex))
+        .expect("expected node tag")
+}
+
+#[test]
+fn malformed_jsx_does_not_drop_later_blocks() {
+    let source = "# Title\n\n<Button label=>\n\nStill here.\n\n- item\n";
+    let ast = parse(source);
+
+    assert!(
+        ast.errors
+            .iter()
+            .any(|e| e.tag == ErrorTag::InvalidJsxAttribute)
+    );
+    assert_eq!(
+        vec!["heading", "recovery", "paragraph", "list_unordered"],
+        root_child_types(&ast)
+    );
+
+    let recovery = first_node_by_tag(&ast, NodeTag::Recovery);
+    assert_eq!(Some("<Button label=>"), ast.recovery_source(recovery));
+}
+
+#[test]
+fn unclosed_expression_does_not_swallow_the_rest_of_the_document() {
+    let source = "Total: {items.length\n\n## Next\n\nbody **bold**\n";
+    let ast = parse(source);
+
+    assert!(
+        ast.errors
+            .iter()
+            .any(|e| e.tag == ErrorTag::UnclosedExpression)
+    );
+    assert_eq!(
+        vec!["recovery", "heading", "paragraph"],
+        root_child_types(&ast)
+    );
+    assert!(ast.nodes.iter().any(|n| n.tag == NodeTag::Strong));
+}
+
+#[test]
+fn unclosed_jsx_element_resyncs_at_next_block_start() {
+    let source = "<Card>\n# Inside\n- one\n- two\n";
+    let ast = parse(source);
+
+    assert!(!ast.errors.is_empty());
+    assert_eq!(
+        vec!["recovery", "heading", "list_unordered"],
+        root_child_types(&ast)
+    );
+    let recovery = first_node_by_tag(&ast, NodeTag::Recovery);
+    assert_eq!(Some("<Card>"), ast.recovery_source(recovery));
+}
+
+#[test]
+fn failed_table_row_ends_the_table_and_keeps_later_blocks() {
+    let source = "| a | b |\n|---|---|\n| 1 | 2 |\n| {x |\n\nok\n";
+    let ast = parse(source);
+
+    assert!(
+        ast.errors
+            .iter()
+            .any(|e| e.tag == ErrorTag::UnclosedExpression)
+    );
+    assert_eq!(
+        vec!["table", "recovery", "paragraph"],
+        root_child_types(&ast)
+    );
+    let table = first_node_by_tag(&ast, NodeTag::Table);
+    assert_eq!(2, ast.table_info(table).num_rows);
+    let recovery = first_node_by_tag(&ast, NodeTag::Recovery);
+    assert_eq!(Some("| {x |"), ast.recovery_source(recovery));
+    let paragraph = first_node_by_tag(&ast, NodeTag::Paragraph);
+    assert_eq!(Some("ok".to_string()), ast.plain_text(paragraph));
+    assert!(render(&ast).ends_with("\n| {x |\n\nok\n"));
+}
+
+#[test]
+fn recovery_error_offsets_point_into_the_original_source() {
+    let source = "intro\n\n<Foo>\ntext\n</Bar>\n\nafter\n";
+    let ast = parse(source);
+
+    let mismatch = ast
+        .errors
+        .iter()
+        .find(|e| e.tag == ErrorTag::MismatchedTags)
+        .expect("expected mismatched tag error");
+    assert_eq!(source.find("</Bar>").unwrap() as u32, mismatch.byte_offset);
+    assert_eq!(
+        mismatch.byte_offset,
+        ast.token_starts[mismatch.token as usize]
+    );
+    assert_eq!(
+        vec!["paragraph", "recovery", "paragraph"],
+        root_child_types(&ast)
+    );
+}
+
+#[test]
+fn recovered_blocks_appear_in_render_and_serialized_tree() {
+    let source = "~~broken\n\n**fine**\n";
+    let ast = parse(source);
+
+    let rendered = render(&ast);
+    assert_eq!("~~broken\n\n**fine**\n", rendered);
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let children = json["children"].as_array().unwrap();
+    assert_eq!("recovery", children[0]["type"]);
+    assert_eq!("~~broken", children[0]["value"]);
+    assert_eq!("paragraph", children[1]["type"]);
+    assert_eq!("strong", children[1]["children"][0]["type"]);
+    assert!(!json["errors"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn repeated_failures_report_each_error_once() {
+    let source = "<A x=>\n<B y=>\n";
+    let ast = parse(source);
+
+    let invalid: Vec<u32> = ast
+        .errors
+        .iter()
+        .filter(|e| e.tag == ErrorTag::InvalidJsxAttribute)
+        .map(|e| e.byte_offset)
+        .collect();
+    let mut deduped = invalid.clone();
+    deduped.dedup();
+    assert_eq!(deduped, invalid);
+    assert_eq!("<A x=>\n<B y=>\n", render(&ast));
+}
@@ -44,3 +44,82 @@ fn inline_code_preserves_literal_htmlish_text() {
     let root: serde_json::Value = serde_json::from_str(&json).unwrap();
     assert_eq!("<tag>&value", root["children"][0]["children"][0]["value"]);
 }
+
+fn structure(source: &str) -> serde_json::Value {
+    let json: serde_json::Value = serde_json::from_str(&serialize_tree(&parse(source))).unwrap();
+    json["children"].clone()
+}
+
+#[test]
+fn backslash_escapes_keep_markdown_and_mdx_syntax_literal() {
+    let source = "2 \\* 3 \\* 4 is \\_not\\_ \\{an expression\\} or \\<Tag\\> \\[link\\](x)\n";
+    let ast = parse(source);
+
+    assert!(ast.errors.is_empty(), "errors: {:?}", ast.errors);
+    let root: serde_json::Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let paragraph = &root["children"][0]["children"];
+    assert_eq!(1, paragraph.as_array().unwrap().len());
+    assert_eq!(
+        "2 * 3 * 4 is _not_ {an expression} or <Tag> [link](x)",
+        paragraph[0]["value"]
+    );
+    let paragraph_idx = ast
+        .nodes
+        .iter()
+        .position(|n| n.tag == hypernote_mdx::ast::NodeTag::Paragraph)
+        .unwrap() as u32;
+    assert_eq!(
+        Some("2 * 3 * 4 is _not_ {an expression} or <Tag> [link](x)".to_string()),
+        ast.plain_text(paragraph_idx)
+    );
+}
+
+#[test]
+fn escaped_block_markers_and_pipes_stay_text() {
+    assert_eq!("paragraph", structure("\\# not a heading\n")[0]["type"]);
+    assert_eq!("paragraph", structure("\\- not a list\n")[0]["type"]);
+    assert_eq!("paragraph", structure("\\> not a quote\n")[0]["type"]);
+
+    let table = structure("| a \\| b | c |\n|---|---|\n| 1 | 2 |\n");
+    let header = &table[0]["children"][0]["children"];
+    assert_eq!(2, header.as_array().unwrap().len());
+    assert_eq!(" a | b ", header[0]["children"][0]["value"]);
+}
+
+#[test]
+fn character_references_decode_in_text() {
+    let source = "Fish &amp; Chips&nbsp;&copy; &#128512; &#x1F600; &#0; &madeup; AT&T\n";
+    let ast = parse(source);
+    let root: serde_json::Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+
+    assert_eq!(
+        "Fish & Chips\u{a0}\u{a9} \u{1F600} \u{1F600} \u{FFFD} &madeup; AT&T",
+        root["children"][0]["children"][0]["value"]
+    );
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn escaped_backslash_before_newline_is_not_a_hard_break() {
+    let ast = parse("one\\\\\ntwo\\\nthree\n");
+    let root: serde_json::Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let children = &root["children"][0]["children"];
+
+    assert_eq!("one\\", children[0]["value"]);
+    assert_eq!("soft_break", children[1]["type"]);
+    assert_eq!("two", children[2]["value"]);
+    assert_eq!("hard_break", children[3]["type"]);
+}
+
+#[test]
+fn escapes_and_references_survive_render_round_trip() {
+    for source in [
+        "Price: \\*\\*not bold\\*\\* \\~~x\\~~ &lt;b&gt;\n",
+        "# Heading with \\{braces\\} &amp; more\n",
+        "- item \\_one\\_\n- item &quot;two&quot;\n",
+        "<Note>literal \\<br\\> &amp;</Note>\n",
+    ] {
+        let rendered = render(&parse(source));
+        assert_eq!(structure(source), structure(&rendered), "{rendered}");
+    }
+}
@@ -0,0 +1,168 @@
+use hypernote_mdx::ast::{Ast, NodeIndex, NodeTag};
+use hypernote_mdx::semantic::{EsmImportBinding, EsmImportKind, EsmKind};
+use hypernote_mdx::{parse, render, serialize_tree};
+use serde_json::Value;
+
+fn root_child_types(ast: &Ast) -> Vec<&'static str> {
+    let doc = ast
+        .nodes
+        .iter()
+        .position(|n| n.tag == NodeTag::Document)
+        .expect("expected document node") as NodeIndex;
+    ast.children(doc)
+        .iter()
+        .map(|&idx| ast.nodes[idx as usize].tag.name())
+        .collect()
+}
+
+fn esm_nodes(ast: &Ast) -> Vec<NodeIndex> {
+    ast.nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| matches!(n.tag, NodeTag::MdxEsmImport | NodeTag::MdxEsmExport))
+        .map(|(idx, _)| idx as NodeIndex)
+        .collect()
+}
+
+#[test]
+fn import_lines_become_esm_nodes() {
+    let source =
+        "import { Chart } from './chart.js'\nimport Layout from \"./layout\"\n\n# Report\n";
+    let ast = parse(source);
+
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    assert_eq!(
+        vec!["mdx_esm_import", "mdx_esm_import", "heading"],
+        root_child_types(&ast)
+    );
+
+    let first = ast.esm_view(esm_nodes(&ast)[0]).unwrap();
+    assert_eq!(EsmKind::Import, first.kind);
+    assert_eq!("import { Chart } from './chart.js'", first.value);
+    assert_eq!(vec!["./chart.js"], first.sources);
+}
+
+#[test]
+fn esm_view_lists_imported_bindings() {
+    let source = "import Chart, { Bar as B, type Props, \"x-y\" as xy } from './chart'\nimport * as utils from 'utils'\nimport './styles.css'\n";
+    let ast = parse(source);
+    let nodes = esm_nodes(&ast);
+    assert_eq!(3, nodes.len());
+
+    let chart = ast.esm_view(nodes[0]).unwrap();
+    let binding = |kind, imported, local| EsmImportBinding {
+        kind,
+        imported,
+        local,
+        source: "./chart",
+    };
+    assert_eq!(
+        vec![
+            binding(EsmImportKind::Default, "default", "Chart"),
+            binding(EsmImportKind::Named, "Bar", "B"),
+            binding(EsmImportKind::Named, "Props", "Props"),
+            binding(EsmImportKind::Named, "x-y", "xy"),
+        ],
+        chart.imports
+    );
+
+    let utils = ast.esm_view(nodes[1]).unwrap();
+    assert_eq!(EsmImportKind::Namespace, utils.imports[0].kind);
+    assert_eq!("*", utils.imports[0].imported);
+    assert_eq!("utils", utils.imports[0].local);
+
+    let styles = ast.esm_view(nodes[2]).unwrap();
+    assert!(styles.imports.is_empty());
+    assert_eq!(vec!["./styles.css"], styles.sources);
+}
+
+#[test]
+fn esm_view_lists_exported_names() {
+    let source = "export const meta = { title: 'Hi', tags: [1, 2] }, { a, b: [c, ...d] } = obj\nexport function Greeting({ name }) {\n  return <p>Hello {name}</p>\n}\nexport { x, y as z } from './re'\nexport * as ns from './ns'\nexport default Layout\n";
+    let ast = parse(source);
+    let exports: Vec<Vec<&str>> = esm_nodes(&ast)
+        .into_iter()
+        .map(|idx| ast.esm_view(idx).unwrap().exports)
+        .collect();
+
+    assert_eq!(
+        vec![
+            vec!["meta", "a", "c", "d"],
+            vec!["Greeting"],
+            vec!["x", "z"],
+            vec!["ns"],
+            vec!["default"],
+        ],
+        exports
+    );
+}
+
+#[test]
+fn multi_line_statements_stay_in_one_node() {
+    let source = "import {\n  Alpha,\n  // the second one\n  Beta,\n} from \"./greek\"\n\nexport const config = {\n  title: \"}\",\n}\n\nText after.\n";
+    let ast = parse(source);
+
+    assert_eq!(
+        vec!["mdx_esm_import", "mdx_esm_export", "paragraph"],
+        root_child_types(&ast)
+    );
+    let nodes = esm_nodes(&ast);
+    let import = ast.esm_view(nodes[0]).unwrap();
+    assert_eq!(
+        vec!["Alpha", "Beta"],
+        import.imports.iter().map(|b| b.local).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        "export const config = {\n  title: \"}\",\n}",
+        ast.esm_view(nodes[1]).unwrap().value
+    );
+}
+
+#[test]
+fn prose_and_nested_lines_are_not_esm() {
+    let cases = [
+        "import the data from the spreadsheet\n",
+        "export the report as PDF\n",
+        "Intro line\nimport x from 'y'\n",
+        "- item\n  import x from 'y'\n",
+        "> import x from 'y'\n",
+    ];
+    for source in cases {
+        let ast = parse(source);
+        assert!(esm_nodes(&ast).is_empty(), "unexpected ESM in {source:?}");
+    }
+}
+
+#[test]
+fn esm_round_trips_through_render_and_serialize() {
+    let source = "---\ntitle: Demo\n---\nimport { A } from './a'\nexport const n = 1\n\n# Title\n\n<A n={n} />\n";
+    let ast = parse(source);
+    assert_eq!(
+        vec![
+            "frontmatter",
+            "mdx_esm_import",
+            "mdx_esm_export",
+            "heading",
+            "mdx_jsx_self_closing"
+        ],
+        root_child_types(&ast)
+    );
+
+    let rendered = render(&ast);
+    assert_eq!(
+        "---\ntitle: Demo\n---\n\nimport { A } from './a'\nexport const n = 1\n\n# Title\n\n<A n={n} />\n",
+        rendered
+    );
+    assert_eq!(rendered, render(&parse(&rendered)));
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let import = &json["children"][1];
+    assert_eq!("mdx_esm_import", import["type"]);
+    assert_eq!("import { A } from './a'", import["value"]);
+    assert_eq!("named", import["imports"][0]["kind"]);
+    assert_eq!("A", import["imports"][0]["local"]);
+    assert_eq!("./a", import["imports"][0]["source"]);
+    let export = &json["children"][2];
+    assert_eq!("mdx_esm_export", export["type"]);
+    assert_eq!(serde_json::json!(["n"]), export["exports"]);
+}
@@ -0,0 +1,101 @@
+use hypernote_mdx::ast::{Ast, NodeIndex, NodeTag};
+use hypernote_mdx::semantic::JsxAttributeValue;
+use hypernote_mdx::{parse, render};
+
+fn expression_values<'a>(ast: &'a Ast) -> Vec<&'a str> {
+    ast.nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, _)| ast.expression_info(idx as NodeIndex))
+        .map(|info| info.value)
+        .collect()
+}
+
+fn first_node_by_tag(ast: &Ast, tag: NodeTag) -> NodeIndex {
+    ast.nodes
+        .iter()
+        .position(|n| n.tag == tag)
+        .expect("expected node tag") as NodeIndex
+}
+
+fn assert_stable(source: &str) {
+    let rendered = render(&parse(source));
+    assert_eq!(rendered, render(&parse(&rendered)), "render is not stable");
+}
+
+#[test]
+fn braces_inside_strings_do_not_close_expressions() {
+    let source = "Open {\"}\"} and {'{'} then {a + \"}}\"} done\n";
+    let ast = parse(source);
+
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    assert_eq!(vec!["\"}\"", "'{'", "a + \"}}\""], expression_values(&ast));
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn template_literals_with_nested_substitutions() {
+    let source = "Total: {`${items.map(i => `${i.name}: {${i.qty}}`).join(\", \")}`}\n";
+    let ast = parse(source);
+
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    assert_eq!(
+        vec!["`${items.map(i => `${i.name}: {${i.qty}}`).join(\", \")}`"],
+        expression_values(&ast)
+    );
+    assert_stable(source);
+}
+
+#[test]
+fn comments_and_jsx_comments_are_skipped() {
+    let source = "{/* } not the end */}\n\nValue {x /* } */ + 1} and {y // }\n}\n";
+    let ast = parse(source);
+
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    assert_eq!(
+        vec!["/* } not the end */", "x /* } */ + 1", "y // }"],
+        expression_values(&ast)
+    );
+    assert_stable(source);
+}
+
+#[test]
+fn attribute_expressions_are_read_whole() {
+    let source = "<Chart style={{ color: \"}\", pad: [1, 2] }} label={`${n} {items}`} note={/* } */ null} />\n";
+    let ast = parse(source);
+
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    let chart = first_node_by_tag(&ast, NodeTag::MdxJsxSelfClosing);
+    let values: Vec<JsxAttributeValue> = ast
+        .jsx_attribute_views(chart)
+        .unwrap()
+        .into_iter()
+        .map(|attr| attr.value)
+        .collect();
+    assert_eq!(
+        vec![
+            JsxAttributeValue::Expression("{ color: \"}\", pad: [1, 2] }"),
+            JsxAttributeValue::Expression("`${n} {items}`"),
+            JsxAttributeValue::Expression("/* } */ null"),
+        ],
+        values
+    );
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn unterminated_literals_still_report_unclosed_expression() {
+    for source in [
+        "Value {`oops ${x}\n",
+        "Value {/* never closed }\n",
+        "<A b={{a: 1} />\n",
+    ] {
+        let ast = parse(source);
+        assert!(
+            ast.errors
+                .iter()
+                .any(|e| e.tag == hypernote_mdx::ast::ErrorTag::UnclosedExpression),
+            "expected unclosed expression for {source:?}"
+        );
+    }
+}
@@ -2,7 +2,7 @@ use hypernote_mdx::ast::NodeTag;
 use hypernote_mdx::{parse, render, serialize_tree};
 use serde_json::Value;
 
-fn parsed(source: &str) -> (hypernote_mdx::ast::Ast, Value) {
+fn parsed(source: &str) -> (hypernote_mdx::ast::Ast<'static>, Value) {
     let ast = parse(source);
     assert!(
         ast.errors.is_empty(),
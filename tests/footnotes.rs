@@ -0,0 +1,161 @@
+use hypernote_mdx::ast::NodeTag;
+use hypernote_mdx::{parse, render, serialize_tree};
+use serde_json::Value;
+
+mod common;
+use common::nodes_by_tag;
+
+#[test]
+fn footnotes_are_numbered_by_first_reference() {
+    let source = "First[^b], then[^a] and[^b] again.\n\n[^a]: Alpha.\n\n[^b]: Beta.\n\n[^unused]: Never cited.\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let footnotes = ast.footnotes();
+    assert_eq!(
+        vec![("b", 1), ("a", 2)],
+        footnotes
+            .iter()
+            .map(|f| (f.label, f.number))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(2, footnotes[0].references.len());
+    assert_eq!(1, footnotes[1].references.len());
+    assert_eq!(Some("b"), ast.footnote_label(footnotes[0].definition));
+    assert_eq!(3, nodes_by_tag(&ast, NodeTag::FootnoteDefinition).len());
+}
+
+#[test]
+fn references_link_back_to_their_definition() {
+    let source = "One[^Note] two[^note].\n\n[^note]: The note.\n";
+    let ast = parse(source);
+
+    let references = nodes_by_tag(&ast, NodeTag::FootnoteReference);
+    assert_eq!(2, references.len());
+    let definition = nodes_by_tag(&ast, NodeTag::FootnoteDefinition)[0];
+    for (occurrence, &idx) in references.iter().enumerate() {
+        let view = ast.footnote_reference_view(idx).unwrap();
+        assert_eq!(1, view.number);
+        assert_eq!(definition, view.definition);
+        assert_eq!(occurrence as u32 + 1, view.occurrence);
+    }
+    assert_eq!(
+        "Note",
+        ast.footnote_reference_view(references[0]).unwrap().label
+    );
+    assert_eq!(references, ast.footnotes()[0].references);
+}
+
+#[test]
+fn undefined_references_stay_text() {
+    let source = "Cite [^missing] and [^] or [^a b].\n";
+    let ast = parse(source);
+
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    assert!(nodes_by_tag(&ast, NodeTag::FootnoteReference).is_empty());
+    assert!(ast.footnotes().is_empty());
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        Some("Cite [^missing] and [^] or [^a b].".to_string()),
+        ast.plain_text(paragraph)
+    );
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn definitions_hold_indented_blocks() {
+    let source = "Text[^long].\n\n[^long]: First paragraph.\n\n    Second paragraph.\n\n    - a list\n\nAfter the note.\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let definition = nodes_by_tag(&ast, NodeTag::FootnoteDefinition)[0];
+    let children: Vec<_> = ast
+        .children(definition)
+        .iter()
+        .map(|&idx| ast.nodes[idx as usize].tag)
+        .collect();
+    assert_eq!(
+        vec![
+            NodeTag::Paragraph,
+            NodeTag::Paragraph,
+            NodeTag::ListUnordered
+        ],
+        children
+    );
+    assert_eq!(
+        Some("First paragraph.\nSecond paragraph.\na list".to_string()),
+        ast.plain_text(definition)
+    );
+
+    let document = ast.children(nodes_by_tag(&ast, NodeTag::Document)[0]);
+    assert_eq!(
+        NodeTag::Paragraph,
+        ast.nodes[*document.last().unwrap() as usize].tag
+    );
+}
+
+#[test]
+fn footnotes_round_trip_through_render_and_serialize() {
+    let source = "Claim[^1] here.\n\n[^1]: Source.\n\n    More detail.\n";
+    let ast = parse(source);
+
+    let rendered = render(&ast);
+    assert_eq!(source, rendered);
+    assert_eq!(rendered, render(&parse(&rendered)));
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let reference = &json["children"][0]["children"][1];
+    assert_eq!("footnote_reference", reference["type"]);
+    assert_eq!("1", reference["label"]);
+    assert_eq!(1, reference["number"]);
+
+    let definition = &json["children"][1];
+    assert_eq!("footnote_definition", definition["type"]);
+    assert_eq!("1", definition["label"]);
+    assert_eq!(1, definition["number"]);
+    assert_eq!(2, definition["children"].as_array().unwrap().len());
+}
+
+#[test]
+fn footnote_table_matches_per_node_views() {
+    let source = "A[^x] b[^y] c[^X] d[^missing].\n\n[^y]: Why.\n\n[^x]: Ex.\n\n[^z]: Unused.\n";
+    let ast = parse(source);
+    let table = ast.footnote_table();
+
+    assert_eq!(ast.footnotes(), table.footnotes());
+    for idx in nodes_by_tag(&ast, NodeTag::FootnoteReference) {
+        assert_eq!(ast.footnote_reference_view(idx), table.reference(&ast, idx));
+    }
+    let definitions = nodes_by_tag(&ast, NodeTag::FootnoteDefinition);
+    assert_eq!(
+        vec![Some(2), Some(1), None],
+        definitions
+            .iter()
+            .map(|&idx| table.definition(idx).map(|f| f.number))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn serializing_many_footnotes_stays_fast() {
+    let count = 2000;
+    let mut source = String::new();
+    for i in 0..count {
+        source.push_str(&format!("Claim {i}[^n{i}].\n\n"));
+    }
+    for i in 0..count {
+        source.push_str(&format!("[^n{i}]: Source {i}.\n\n"));
+    }
+    let ast = parse(&source);
+
+    let start = std::time::Instant::now();
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    assert!(
+        start.elapsed() < std::time::Duration::from_secs(2),
+        "serializing {count} footnotes took {:?}",
+        start.elapsed()
+    );
+    let last = &json["children"][count * 2 - 1];
+    assert_eq!("footnote_definition", last["type"]);
+    assert_eq!(count, last["number"].as_u64().unwrap() as usize);
+}
@@ -0,0 +1,86 @@
+use hypernote_mdx::ast::{Ast, NodeTag};
+use hypernote_mdx::{ParseOptions, parse, parse_with_options, render, serialize_tree};
+use serde_json::Value;
+
+mod common;
+use common::nodes_by_tag;
+
+fn parse_social(source: &str) -> Ast<'static> {
+    let options = ParseOptions {
+        hashtags: true,
+        mentions: true,
+        ..ParseOptions::default()
+    };
+    parse_with_options(source, &options)
+}
+
+#[test]
+fn hashtags_and_mentions_are_off_by_default() {
+    let ast = parse("Ping @alice about #rust\n");
+
+    assert!(nodes_by_tag(&ast, NodeTag::Hashtag).is_empty());
+    assert!(nodes_by_tag(&ast, NodeTag::Mention).is_empty());
+    assert!(ast.collect_hashtags().is_empty());
+}
+
+#[test]
+fn words_become_hashtag_and_mention_nodes() {
+    let ast = parse_social("Ping @alice and @bob.smith. about (#Rust) and **#日本**.\n");
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let hashtags: Vec<_> = nodes_by_tag(&ast, NodeTag::Hashtag)
+        .into_iter()
+        .filter_map(|idx| ast.hashtag_name(idx))
+        .collect();
+    assert_eq!(vec!["Rust", "日本"], hashtags);
+
+    let mentions: Vec<_> = nodes_by_tag(&ast, NodeTag::Mention)
+        .into_iter()
+        .filter_map(|idx| ast.mention_name(idx))
+        .collect();
+    assert_eq!(vec!["alice", "bob.smith"], mentions);
+
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        Some("Ping @alice and @bob.smith. about (#Rust) and #日本.".to_string()),
+        ast.plain_text(paragraph)
+    );
+}
+
+#[test]
+fn urls_code_emails_and_numbers_are_left_alone() {
+    let source = "See https://example.com/#intro, [docs](#setup), `#code`, me@example.com, issue #42 and a#b.\n";
+    let ast = parse_social(source);
+
+    assert!(nodes_by_tag(&ast, NodeTag::Hashtag).is_empty());
+    assert!(nodes_by_tag(&ast, NodeTag::Mention).is_empty());
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn headings_keep_their_space_and_line_start_tags_are_hashtags() {
+    let ast = parse_social("# Release #notes\n\n#nostr is live\n");
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let document = ast.children(nodes_by_tag(&ast, NodeTag::Document)[0]);
+    let tags: Vec<_> = document
+        .iter()
+        .map(|&idx| ast.nodes[idx as usize].tag)
+        .collect();
+    assert_eq!(vec![NodeTag::Heading, NodeTag::Paragraph], tags);
+    assert_eq!(vec!["notes", "nostr"], ast.collect_hashtags());
+}
+
+#[test]
+fn collected_hashtags_are_lowercased_and_unique() {
+    let source = "#Rust and #rust, then #MDX.\n\n- #rust again\n";
+    let ast = parse_social(source);
+
+    assert_eq!(vec!["rust", "mdx"], ast.collect_hashtags());
+    assert_eq!(source, render(&ast));
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let hashtag = &json["children"][0]["children"][0];
+    assert_eq!("hashtag", hashtag["type"]);
+    assert_eq!("Rust", hashtag["value"]);
+}
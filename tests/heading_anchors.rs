@@ -0,0 +1,146 @@
+use hypernote_mdx::ast::NodeTag;
+use hypernote_mdx::{parse, render, serialize_tree};
+use serde_json::Value;
+
+mod common;
+use common::nodes_by_tag;
+
+#[test]
+fn custom_id_is_split_off_the_heading_text() {
+    let source = "## Setup {#setup}\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let heading = nodes_by_tag(&ast, NodeTag::Heading)[0];
+    let view = ast.heading_view(heading).unwrap();
+    assert_eq!(2, view.level);
+    assert_eq!(Some("setup"), view.id);
+    assert_eq!("setup", view.anchor());
+    assert_eq!(1, view.children.len());
+    assert_eq!(Some("Setup".into()), ast.text_value(view.children[0]));
+    assert_eq!(Some("Setup".to_string()), ast.plain_text(heading));
+    assert!(nodes_by_tag(&ast, NodeTag::MdxTextExpression).is_empty());
+    assert_eq!(source, render(&ast));
+
+    let ast = parse("# **Bold** title\t {#bold}\n");
+    let heading = nodes_by_tag(&ast, NodeTag::Heading)[0];
+    assert_eq!(Some("Bold title".to_string()), ast.plain_text(heading));
+    assert_eq!("# **Bold** title {#bold}\n", render(&ast));
+}
+
+#[test]
+fn slugs_follow_github_and_are_deduplicated() {
+    let ast = parse("# Intro\n\n## Intro\n\n## Intro\n\n### Hello, World! `code` 🎉\n");
+
+    let slugs: Vec<String> = nodes_by_tag(&ast, NodeTag::Heading)
+        .into_iter()
+        .map(|idx| ast.heading_view(idx).unwrap().slug)
+        .collect();
+    assert_eq!(
+        vec!["intro", "intro-1", "intro-2", "hello-world-code-"],
+        slugs
+    );
+}
+
+#[test]
+fn slugs_avoid_custom_ids_and_setext_headings_take_ids() {
+    let source = "Overview {#faq}\n===============\n\n## FAQ\n";
+    let ast = parse(source);
+
+    let headings = nodes_by_tag(&ast, NodeTag::Heading);
+    let first = ast.heading_view(headings[0]).unwrap();
+    assert_eq!((1, Some("faq")), (first.level, first.id));
+    assert_eq!("faq", first.slug);
+    let second = ast.heading_view(headings[1]).unwrap();
+    assert_eq!(None, second.id);
+    assert_eq!("faq-1", second.anchor());
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn fragments_resolve_to_headings() {
+    let ast = parse("# Guide\n\n## Install {#setup}\n\n## Usage Notes\n\nSee [setup](#setup).\n");
+
+    let headings = nodes_by_tag(&ast, NodeTag::Heading);
+    assert_eq!(Some(headings[1]), ast.resolve_fragment("#setup"));
+    assert_eq!(Some(headings[2]), ast.resolve_fragment("#usage-notes"));
+    assert_eq!(Some(headings[0]), ast.resolve_fragment("guide"));
+    assert_eq!(None, ast.resolve_fragment("#missing"));
+
+    let link = nodes_by_tag(&ast, NodeTag::Link)[0];
+    let url = ast.link_view(link).unwrap().url;
+    assert_eq!(Some(headings[1]), ast.resolve_fragment(&url));
+}
+
+#[test]
+fn serialize_tree_emits_ids_and_slugs() {
+    let ast = parse("## Setup {#setup}\n\n## Next Steps\n\n## Bad {#not an id}\n");
+    let tree: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+
+    let children = tree["children"].as_array().unwrap();
+    assert_eq!("setup", children[0]["id"]);
+    assert_eq!("setup", children[0]["slug"]);
+    assert!(children[1].get("id").is_none());
+    assert_eq!("next-steps", children[1]["slug"]);
+    // Braces that do not hold a valid id stay an expression.
+    assert!(children[2].get("id").is_none());
+    assert_eq!("mdx_text_expression", children[2]["children"][1]["type"]);
+}
+
+#[test]
+fn custom_ids_do_not_use_up_auto_slugs() {
+    let ast = parse("# Setup {#setup}\n\n## Setup\n\n## Setup\n");
+
+    let headings = nodes_by_tag(&ast, NodeTag::Heading);
+    let slugs: Vec<String> = headings
+        .iter()
+        .map(|&idx| ast.heading_view(idx).unwrap().slug)
+        .collect();
+    assert_eq!(vec!["setup", "setup-1", "setup-2"], slugs);
+    assert_eq!(Some(headings[0]), ast.resolve_fragment("#setup"));
+    assert_eq!(Some(headings[1]), ast.resolve_fragment("#setup-1"));
+}
+
+#[test]
+fn duplicate_custom_ids_are_deduplicated() {
+    let ast = parse("# a {#dup}\n\n# b {#dup}\n\n# dup\n");
+
+    let headings = nodes_by_tag(&ast, NodeTag::Heading);
+    let table = ast.heading_table();
+    let views: Vec<_> = headings
+        .iter()
+        .map(|&idx| table.view(idx).unwrap())
+        .collect();
+    assert_eq!(
+        vec![
+            (Some("dup"), "dup"),
+            (Some("dup"), "dup-1"),
+            (None, "dup-2")
+        ],
+        views
+            .iter()
+            .map(|view| (view.id, view.anchor()))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(Some(headings[0]), table.resolve_fragment("#dup"));
+    assert_eq!(Some(headings[1]), table.resolve_fragment("#dup-1"));
+    assert_eq!(Some(headings[2]), table.resolve_fragment("dup-2"));
+}
+
+#[test]
+fn serializing_many_headings_stays_fast() {
+    let count = 4000;
+    let source: String = (0..count)
+        .map(|i| format!("## Step {}\n\nDone.\n\n", i % 10))
+        .collect();
+    let ast = parse(&source);
+
+    let start = std::time::Instant::now();
+    let tree: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    assert!(
+        start.elapsed() < std::time::Duration::from_secs(2),
+        "serializing {count} headings took {:?}",
+        start.elapsed()
+    );
+    assert_eq!("step-9-399", tree["children"][(count - 1) * 2]["slug"]);
+}
@@ -0,0 +1,92 @@
+use hypernote_mdx::ast::{NodeIndex, NodeTag};
+use hypernote_mdx::semantic::JsxElementKind;
+use hypernote_mdx::{parse, render, serialize_tree};
+use serde_json::Value;
+
+mod common;
+use common::nodes_by_tag;
+
+#[test]
+fn comments_become_comment_nodes() {
+    let source = "<!-- draft -->\n\nText <!-- inline --> more\n\n<!--\nmulti\nline\n-->\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let comments = nodes_by_tag(&ast, NodeTag::HtmlComment);
+    assert_eq!(
+        vec![Some(" draft "), Some(" inline "), Some("\nmulti\nline\n")],
+        comments
+            .iter()
+            .map(|&idx| ast.html_comment_text(idx))
+            .collect::<Vec<_>>()
+    );
+
+    let document = ast.children(nodes_by_tag(&ast, NodeTag::Document)[0]);
+    assert_eq!(NodeTag::HtmlComment, ast.nodes[document[0] as usize].tag);
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(Some("Text  more".to_string()), ast.plain_text(paragraph));
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn unterminated_comment_stays_text() {
+    let source = "a <!-- never closed\n";
+    let ast = parse(source);
+
+    assert!(nodes_by_tag(&ast, NodeTag::HtmlComment).is_empty());
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn void_elements_need_no_closing_tag() {
+    let source =
+        "Line one<br>line two and <img src=\"a.png\" alt=\"A\"> inline.\n\n<hr>\n\nAfter\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let elements: Vec<_> = nodes_by_tag(&ast, NodeTag::MdxJsxSelfClosing)
+        .into_iter()
+        .map(|idx| ast.jsx_element_view(idx).unwrap())
+        .collect();
+    assert_eq!(
+        vec!["br", "img", "hr"],
+        elements.iter().map(|e| e.name).collect::<Vec<_>>()
+    );
+    for element in &elements {
+        assert_eq!(JsxElementKind::SelfClosing, element.kind);
+        assert!(element.is_intrinsic);
+    }
+    assert_eq!(2, elements[1].attrs.len());
+    assert_eq!(2, nodes_by_tag(&ast, NodeTag::Paragraph).len());
+
+    let rendered = render(&ast);
+    assert_eq!(
+        "Line one<br />line two and <img src=\"a.png\" alt=\"A\" /> inline.\n\n<hr />\n\nAfter\n",
+        rendered
+    );
+    assert_eq!(rendered, render(&parse(&rendered)));
+}
+
+#[test]
+fn intrinsic_elements_are_distinguished_from_components() {
+    let source = "<details>\n<summary>More</summary>\n\n<Card>Body</Card>\n\n</details>\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let intrinsic: Vec<_> = ast
+        .nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, _)| ast.jsx_element_view(idx as NodeIndex))
+        .map(|view| (view.name, view.is_intrinsic))
+        .collect();
+    assert_eq!(
+        vec![("summary", true), ("Card", false), ("details", true)],
+        intrinsic
+    );
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let details = &json["children"][0];
+    assert_eq!(true, details["intrinsic"]);
+    assert!(details["children"][1].get("intrinsic").is_none());
+}
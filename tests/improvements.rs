@@ -14,6 +14,7 @@ fn shortcode_normalization_option_converts_known_codes() {
     let source = ":thumbsup:\n";
     let options = ParseOptions {
         normalize_emoji_shortcodes: true,
+        ..ParseOptions::default()
     };
     let ast = parse_with_options(source, &options);
     let rendered = render(&ast);
@@ -108,7 +109,7 @@ fn serializer_emits_schema_version() {
     let root: serde_json::Value = serde_json::from_str(&json).unwrap();
 
     assert_eq!("hypernote-mdx-ast", root["schema"]["name"]);
-    assert_eq!(1, root["schema"]["version"]);
+    assert_eq!(2, root["schema"]["version"]);
 }
 
 #[test]
@@ -0,0 +1,126 @@
+use hypernote_mdx::ast::{Ast, NodeTag, Reparse, Span};
+use hypernote_mdx::{ParseOptions, parse, parse_with_options, serialize_tree};
+
+fn arrays(ast: &Ast) -> String {
+    format!(
+        "{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}",
+        ast.token_tags, ast.token_starts, ast.nodes, ast.extra_data, ast.errors, ast.source_edits
+    )
+}
+
+fn edit(source: &str, old: &str, new_text: &str) -> (Ast<'static>, Reparse) {
+    let start = source.find(old).unwrap() as u32;
+    let span = Span {
+        start,
+        end: start + old.len() as u32,
+    };
+    let reparse = parse(source).reparse_with_edit(span, new_text);
+    let edited = source.replacen(old, new_text, 1);
+    assert_eq!(edited, reparse.ast.source);
+    let fresh = parse(&edited);
+    assert_eq!(serialize_tree(&fresh), serialize_tree(&reparse.ast));
+    assert_eq!(arrays(&fresh), arrays(&reparse.ast));
+    (fresh, reparse)
+}
+
+#[test]
+fn edit_inside_a_paragraph_reparses_only_that_block() {
+    let source =
+        "# Title\n\nFirst line\nwith *one* word.\n\nSecond [link](https://x.com).\n\n- a\n- b\n";
+    let old = parse(source);
+    let (ast, reparse) = edit(source, "*one*", "*one* and `two`");
+
+    // The heading keeps its nodes, the paragraph is parsed again, and the blocks after
+    // it are moved over.
+    assert_eq!(2, reparse.old_nodes.start);
+    assert_eq!(2, reparse.new_nodes.start);
+    assert_eq!(NodeTag::Paragraph, ast.nodes[2].tag);
+    assert_eq!(9, reparse.old_nodes.end);
+    assert_eq!(11, reparse.new_nodes.end);
+    let tail = |ast: &Ast, from: u32| -> Vec<NodeTag> {
+        ast.nodes[from as usize..ast.nodes.len() - 1]
+            .iter()
+            .map(|node| node.tag)
+            .collect()
+    };
+    assert_eq!(
+        tail(&old, reparse.old_nodes.end),
+        tail(&ast, reparse.new_nodes.end)
+    );
+}
+
+#[test]
+fn edits_that_join_or_split_blocks_match_a_full_parse() {
+    let source = "Alpha line\n\nBeta line\n\n> quote\n\n- item\n\nOmega\n";
+    edit(source, "\n\nBeta", "\nBeta");
+    edit(source, "Beta line", "Beta\n\nGamma");
+    edit(source, "\n\n- item", "\n- item");
+    edit(source, "> quote\n\n", "");
+}
+
+#[test]
+fn constructs_that_reach_across_blocks_match_a_full_parse() {
+    let source = "Intro\n\nMiddle *text*\n\n<Card>\n\nBody\n\n</Card>\n\nTail `code`\n";
+    edit(source, "Middle", "```\nMiddle");
+    edit(source, "Middle", "<!--\n\nMiddle");
+    edit(source, "<Card>", "<Card title=\"a\">");
+    edit(source, "</Card>", "</Cards>");
+    edit(source, "Intro", "---\ntitle: x");
+}
+
+#[test]
+fn tokens_around_the_edited_blocks_match_a_full_parse() {
+    let source = "Intro\n\nMiddle text\n\nSee [a] here.\n\nTail *end*\n";
+    edit(source, "Middle", "Mid");
+    edit(source, "Middle", "Middle *open");
+    edit(source, "Middle", "<Card>\n\nMiddle");
+    edit(source, "Intro", "Intro [b]");
+    // Unclosed frontmatter takes in the blocks after it without parsing their tokens.
+    edit("---\ntitle: x\n---\n\nSee [a] here.\n", "x\n---", "x");
+
+    // Text before the edited block that looked ahead for a closer, or frontmatter that
+    // leaves an emphasis open, changes what the tokens after it are.
+    edit("<!--\n\nIntro\n\nMiddle\n\nTail\n", "Tail", "Tail -->");
+    edit(
+        "---\ntitle: *x\n---\n\nIntro\n\nMiddle *a*\n\nTail\n",
+        "Middle",
+        "Mid",
+    );
+}
+
+#[test]
+fn edits_at_the_ends_of_the_document_match_a_full_parse() {
+    let source = "# Start\n\nSome text\n\nEnd";
+    edit(source, "End", "End of *file*\n\nMore");
+    edit(source, "# Start", "");
+    edit("", "", "Hello **world**\n");
+
+    // The heading before the edited paragraphs is kept.
+    let (_, reparse) = edit(source, "End", "Ending");
+    assert_eq!(2, reparse.old_nodes.start);
+}
+
+#[test]
+fn definition_edits_and_options_match_a_full_parse() {
+    let source =
+        "See [docs][ref] and note[^1].\n\nMore text\n\n[ref]: https://a.com\n\n[^1]: Note.\n";
+    edit(source, "https://a.com", "https://b.com");
+    edit(source, "[ref]: https://a.com", "[other]: https://a.com");
+    edit(source, "More text", "[new]: https://c.com");
+
+    let options = ParseOptions {
+        math: true,
+        normalize_emoji_shortcodes: true,
+        ..ParseOptions::default()
+    };
+    let ast = parse_with_options("Hi :wave:\n\n$$\nx\n$$\n\nBye :tada:\n", &options);
+    let start = ast.source.find('x').unwrap() as u32;
+    let span = Span {
+        start,
+        end: start + 1,
+    };
+    let reparse = ast.reparse_with_edit_and_options(span, "y :smile:", &options);
+    let fresh = parse_with_options(&ast.source.replacen('x', "y :smile:", 1), &options);
+    assert_eq!(serialize_tree(&fresh), serialize_tree(&reparse.ast));
+    assert_eq!(arrays(&fresh), arrays(&reparse.ast));
+}
@@ -0,0 +1,117 @@
+use hypernote_mdx::ast::{Ast, NodeIndex, NodeTag};
+use hypernote_mdx::{ParseOptions, parse, parse_with_options, render};
+
+mod common;
+use common::nodes_by_tag;
+
+fn parse_chat(source: &str) -> Ast<'static> {
+    let options = ParseOptions {
+        breaks: true,
+        ..ParseOptions::default()
+    };
+    parse_with_options(source, &options)
+}
+
+fn child_tags(ast: &Ast, idx: NodeIndex) -> Vec<&'static str> {
+    ast.children(idx)
+        .iter()
+        .map(|&child| ast.nodes[child as usize].tag.name())
+        .collect()
+}
+
+#[test]
+fn single_newlines_are_soft_breaks_by_default() {
+    let source = "Line one\nLine two\nLine three\n";
+    let ast = parse(source);
+
+    let paragraphs = nodes_by_tag(&ast, NodeTag::Paragraph);
+    assert_eq!(1, paragraphs.len());
+    assert_eq!(
+        vec!["text", "soft_break", "text", "soft_break", "text"],
+        child_tags(&ast, paragraphs[0])
+    );
+    assert!(nodes_by_tag(&ast, NodeTag::HardBreak).is_empty());
+    assert_eq!(
+        Some("Line one Line two Line three".to_string()),
+        ast.plain_text(paragraphs[0])
+    );
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn breaks_option_turns_newlines_into_hard_breaks() {
+    let source = "Line one\nLine two\n";
+    let ast = parse_chat(source);
+
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        vec!["text", "hard_break", "text"],
+        child_tags(&ast, paragraph)
+    );
+    assert!(nodes_by_tag(&ast, NodeTag::SoftBreak).is_empty());
+    assert_eq!(
+        Some("Line one\nLine two".to_string()),
+        ast.plain_text(paragraph)
+    );
+    // A newline hard break renders back as the bare newline it came from.
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn explicit_hard_breaks_survive_both_modes() {
+    let source = "Trailing  \nspaces\nand *more*\n";
+
+    let ast = parse(source);
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        vec![
+            "text",
+            "hard_break",
+            "text",
+            "soft_break",
+            "text",
+            "emphasis"
+        ],
+        child_tags(&ast, paragraph)
+    );
+    assert_eq!(
+        Some("Trailing\nspaces and more".to_string()),
+        ast.plain_text(paragraph)
+    );
+
+    let chat = parse_chat(source);
+    assert_eq!(2, nodes_by_tag(&chat, NodeTag::HardBreak).len());
+    assert_eq!(render(&ast), render(&chat));
+}
+
+#[test]
+fn breaks_apply_inside_blockquotes_and_list_items() {
+    let source = "> quoted\n> lines\n\n- item\n  continued\n";
+
+    let ast = parse(source);
+    assert_eq!(2, nodes_by_tag(&ast, NodeTag::SoftBreak).len());
+    let quote = nodes_by_tag(&ast, NodeTag::Blockquote)[0];
+    assert_eq!(Some("quoted lines".to_string()), ast.plain_text(quote));
+    let item = nodes_by_tag(&ast, NodeTag::ListItem)[0];
+    assert_eq!(Some("item continued".to_string()), ast.plain_text(item));
+
+    let chat = parse_chat(source);
+    assert!(nodes_by_tag(&chat, NodeTag::SoftBreak).is_empty());
+    let quote = nodes_by_tag(&chat, NodeTag::Blockquote)[0];
+    assert_eq!(Some("quoted\nlines".to_string()), chat.plain_text(quote));
+    let item = nodes_by_tag(&chat, NodeTag::ListItem)[0];
+    assert_eq!(Some("item\ncontinued".to_string()), chat.plain_text(item));
+}
+
+#[test]
+fn blank_lines_still_separate_paragraphs_in_chat_mode() {
+    let ast = parse_chat("first\n\nsecond\nthird\n");
+
+    let paragraphs = nodes_by_tag(&ast, NodeTag::Paragraph);
+    assert_eq!(2, paragraphs.len());
+    assert_eq!(vec!["text"], child_tags(&ast, paragraphs[0]));
+    assert_eq!(
+        vec!["text", "hard_break", "text"],
+        child_tags(&ast, paragraphs[1])
+    );
+}
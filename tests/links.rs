@@ -0,0 +1,178 @@
+use hypernote_mdx::ast::{LinkReference, NodeTag};
+use hypernote_mdx::token::Tag as TokenTag;
+use hypernote_mdx::{parse, render, serialize_tree};
+use serde_json::Value;
+
+mod common;
+use common::nodes_by_tag;
+
+#[test]
+fn inline_links_and_images_split_off_titles() {
+    let source = "[docs](https://example.com \"The docs\") and ![logo](<./my logo.png> 'Logo')\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let link = ast.link_view(nodes_by_tag(&ast, NodeTag::Link)[0]).unwrap();
+    assert_eq!("https://example.com", link.url);
+    assert_eq!(Some("The docs"), link.title.as_deref());
+    assert_eq!(LinkReference::Inline, link.reference);
+    assert_eq!(None, link.reference_label);
+
+    let image = ast
+        .image_view(nodes_by_tag(&ast, NodeTag::Image)[0])
+        .unwrap();
+    assert_eq!("./my logo.png", image.url);
+    assert_eq!(Some("Logo"), image.title.as_deref());
+
+    let plain = parse("[a](https://example.com)\n");
+    let link = plain
+        .link_view(nodes_by_tag(&plain, NodeTag::Link)[0])
+        .unwrap();
+    assert_eq!("https://example.com", link.url);
+    assert_eq!(None, link.title);
+}
+
+#[test]
+fn reference_links_resolve_through_definitions() {
+    let source = "Read [the guide][Guide], [guide][] or just [GUIDE]. ![Logo]\n\n[guide]: https://example.com/guide \"User guide\"\n[logo]: ./logo.png\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let links: Vec<_> = nodes_by_tag(&ast, NodeTag::Link)
+        .into_iter()
+        .map(|idx| ast.link_view(idx).unwrap())
+        .collect();
+    assert_eq!(
+        vec![
+            LinkReference::Full,
+            LinkReference::Collapsed,
+            LinkReference::Shortcut
+        ],
+        links.iter().map(|l| l.reference).collect::<Vec<_>>()
+    );
+    for link in &links {
+        assert_eq!("https://example.com/guide", link.url);
+        assert_eq!(Some("User guide"), link.title.as_deref());
+        assert_eq!(Some("guide"), link.reference_label);
+    }
+
+    let image = ast
+        .image_view(nodes_by_tag(&ast, NodeTag::Image)[0])
+        .unwrap();
+    assert_eq!("./logo.png", image.url);
+    assert_eq!(LinkReference::Shortcut, image.reference);
+
+    let definitions = nodes_by_tag(&ast, NodeTag::Definition);
+    assert_eq!(2, definitions.len());
+    let guide = ast.definition_view(definitions[0]).unwrap();
+    assert_eq!("guide", guide.label);
+    assert_eq!("https://example.com/guide", guide.url);
+    assert_eq!(Some("User guide"), guide.title.as_deref());
+}
+
+#[test]
+fn unresolved_brackets_stay_literal_text() {
+    let source = "See [1] and [x][nope] or [ ] here.\n";
+    let ast = parse(source);
+
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    assert!(nodes_by_tag(&ast, NodeTag::Link).is_empty());
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        Some("See [1] and [x][nope] or [ ] here.".to_string()),
+        ast.plain_text(paragraph)
+    );
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn literal_brackets_inside_a_link_label_stay_in_the_label() {
+    let source = "[see [1] and [^2] here](https://example.com)\n";
+    let ast = parse(source);
+
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    let links = nodes_by_tag(&ast, NodeTag::Link);
+    assert_eq!(1, links.len());
+    assert_eq!(
+        Some("see [1] and [^2] here".to_string()),
+        ast.plain_text(links[0])
+    );
+    assert_eq!("https://example.com", ast.link_view(links[0]).unwrap().url);
+
+    // The literal brackets keep their tokens; only the tree reads them as text.
+    let count = |tag| ast.token_tags.iter().filter(|&&t| t == tag).count();
+    assert_eq!(2, count(TokenTag::LinkStart));
+    assert_eq!(1, count(TokenTag::FootnoteReference));
+}
+
+#[test]
+fn definitions_do_not_interrupt_paragraphs() {
+    let cases = [
+        "Intro\n[a]: https://example.com\n",
+        "[a]: https://example.com trailing words\n",
+        "[]: https://example.com\n",
+        "> [a]: https://example.com\n",
+    ];
+    for source in cases {
+        let ast = parse(source);
+        assert!(
+            nodes_by_tag(&ast, NodeTag::Definition).is_empty(),
+            "unexpected definition in {source:?}"
+        );
+    }
+
+    let ast = parse("[a]: /first\n[A]: /second\n\n[a]\n");
+    assert_eq!(2, nodes_by_tag(&ast, NodeTag::Definition).len());
+    let link = ast.link_view(nodes_by_tag(&ast, NodeTag::Link)[0]).unwrap();
+    assert_eq!("/first", link.url);
+}
+
+#[test]
+fn references_round_trip_through_render_and_serialize() {
+    let source =
+        "[text][ref], [ref][] and [ref]\n\n[ref]: https://example.com \"Title\"\n[other]: /other\n";
+    let ast = parse(source);
+
+    let rendered = render(&ast);
+    assert_eq!(source, rendered);
+    assert_eq!(rendered, render(&parse(&rendered)));
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let paragraph = &json["children"][0]["children"];
+    let link = &paragraph[0];
+    assert_eq!("link", link["type"]);
+    assert_eq!("https://example.com", link["url"]);
+    assert_eq!("Title", link["title"]);
+    assert_eq!("full", link["reference"]);
+    assert_eq!("ref", link["label"]);
+    assert_eq!("shortcut", paragraph[4]["reference"]);
+
+    let definition = &json["children"][1];
+    assert_eq!("definition", definition["type"]);
+    assert_eq!("ref", definition["label"]);
+    assert_eq!("https://example.com", definition["url"]);
+    assert_eq!("Title", definition["title"]);
+    assert!(json["children"][2].get("title").is_none());
+}
+
+#[test]
+fn destinations_and_titles_decode_escapes_and_references() {
+    let source = "[a](/u \"T &amp; \\\"q\\\"\") and [r]\n\n[r]: /x\\_y 'it&#39;s'\n";
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let links = nodes_by_tag(&ast, NodeTag::Link);
+    let inline = ast.link_view(links[0]).unwrap();
+    assert_eq!("/u", inline.url);
+    assert_eq!(Some("T & \"q\""), inline.title.as_deref());
+    let reference = ast.link_view(links[1]).unwrap();
+    assert_eq!("/x_y", reference.url);
+    assert_eq!(Some("it's"), reference.title.as_deref());
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let paragraph = &json["children"][0]["children"];
+    assert_eq!("T & \"q\"", paragraph[0]["title"]);
+    assert_eq!("/x_y", paragraph[2]["url"]);
+    assert_eq!("/x_y", json["children"][1]["url"]);
+    assert_eq!(source, render(&ast));
+}
@@ -2,7 +2,7 @@ use hypernote_mdx::ast::ErrorTag;
 use hypernote_mdx::{parse, render, serialize_tree};
 use std::panic::{AssertUnwindSafe, catch_unwind};
 
-fn parse_render_serialize_without_panicking(source: &str) -> hypernote_mdx::ast::Ast {
+fn parse_render_serialize_without_panicking(source: &str) -> hypernote_mdx::ast::Ast<'static> {
     let result = catch_unwind(AssertUnwindSafe(|| {
         let ast = parse(source);
         let json = serialize_tree(&ast);
@@ -86,3 +86,45 @@ fn malformed_input_error_offsets_survive_render_and_serialize_paths() {
 
     assert_eq!(source.len() as u32, err.byte_offset);
 }
+
+#[test]
+fn trailing_backslash_in_jsx_strings_does_not_panic() {
+    for source in [
+        "<b \"\\",
+        "<b '\\",
+        "<b x='\\",
+        "<b x=\"\\",
+        "[a]: /u\n\n<b \"\\",
+    ] {
+        let ast = parse_render_serialize_without_panicking(source);
+        assert!(!ast.errors.is_empty(), "expected errors for {source:?}");
+        assert!(
+            ast.token_starts
+                .iter()
+                .all(|&start| start as usize <= source.len()),
+            "token starts past the end of {source:?}: {:?}",
+            ast.token_starts
+        );
+    }
+}
+
+#[test]
+fn nesting_past_the_limit_is_reported() {
+    let nested = |depth: usize| format!("{}x{}\n", "<A>".repeat(depth), "</A>".repeat(depth));
+    assert!(parse(&nested(64)).errors.is_empty());
+    let ast = parse_render_serialize_without_panicking(&nested(65));
+    assert!(
+        ast.errors
+            .iter()
+            .any(|err| err.tag == ErrorTag::NestingTooDeep)
+    );
+
+    let braces = |depth: usize| format!("{}{}\n", "{".repeat(depth), "}".repeat(depth));
+    assert!(parse(&braces(64)).errors.is_empty());
+    let ast = parse_render_serialize_without_panicking(&braces(65));
+    assert!(
+        ast.errors
+            .iter()
+            .any(|err| err.tag == ErrorTag::NestingTooDeep)
+    );
+}
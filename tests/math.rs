@@ -0,0 +1,106 @@
+use hypernote_mdx::ast::{Ast, NodeTag};
+use hypernote_mdx::{ParseOptions, parse, parse_with_options, render, serialize_tree};
+use serde_json::Value;
+
+mod common;
+use common::nodes_by_tag;
+
+fn parse_math(source: &str) -> Ast<'static> {
+    let options = ParseOptions {
+        math: true,
+        ..ParseOptions::default()
+    };
+    parse_with_options(source, &options)
+}
+
+#[test]
+fn math_is_off_by_default() {
+    let source = "Inline $x^2$ here.\n\n$$\ny = mx + b\n$$\n";
+    let ast = parse(source);
+
+    assert!(nodes_by_tag(&ast, NodeTag::MathInline).is_empty());
+    assert!(nodes_by_tag(&ast, NodeTag::MathBlock).is_empty());
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        Some("Inline $x^2$ here.".to_string()),
+        ast.plain_text(paragraph)
+    );
+}
+
+#[test]
+fn inline_math_is_verbatim() {
+    let ast = parse_math("Let $a_1 * b_1$ and $\\{x\\}$ hold.\n");
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let spans: Vec<_> = nodes_by_tag(&ast, NodeTag::MathInline)
+        .into_iter()
+        .map(|idx| ast.math_info(idx).unwrap())
+        .collect();
+    assert_eq!(
+        vec!["a_1 * b_1", "\\{x\\}"],
+        spans.iter().map(|m| m.tex).collect::<Vec<_>>()
+    );
+    assert!(spans.iter().all(|m| !m.display));
+    assert!(nodes_by_tag(&ast, NodeTag::Emphasis).is_empty());
+
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        Some("Let a_1 * b_1 and \\{x\\} hold.".to_string()),
+        ast.plain_text(paragraph)
+    );
+}
+
+#[test]
+fn dollar_amounts_stay_text() {
+    let source = "It costs $5 and $10, or $ 3 $ at most.\n";
+    let ast = parse_math(source);
+
+    assert!(nodes_by_tag(&ast, NodeTag::MathInline).is_empty());
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn inline_math_does_not_continue_past_a_hard_break() {
+    let source = "Cost $a\\\nb$ here, but \\$x\\$ and $y\\$z$ still are.\n";
+    let ast = parse_math(source);
+
+    let math = nodes_by_tag(&ast, NodeTag::MathInline);
+    assert_eq!(1, math.len());
+    assert_eq!("$y\\$z$", ast.node_source(math[0]));
+}
+
+#[test]
+fn math_blocks_are_display_math() {
+    let source = "Before\n\n$$\n\\sum_{i=1}^n i\n= \\frac{n(n+1)}{2}\n$$\n\nAfter\n";
+    let ast = parse_math(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let block = nodes_by_tag(&ast, NodeTag::MathBlock)[0];
+    let info = ast.math_info(block).unwrap();
+    assert!(info.display);
+    assert_eq!("\\sum_{i=1}^n i\n= \\frac{n(n+1)}{2}", info.tex);
+
+    let document = ast.children(nodes_by_tag(&ast, NodeTag::Document)[0]);
+    assert_eq!(3, document.len());
+    assert_eq!(block, document[1]);
+    assert!(ast.math_info(document[0]).is_none());
+}
+
+#[test]
+fn math_round_trips_through_render_and_serialize() {
+    let source = "Energy $E = mc^2$.\n\n$$\na^2 + b^2 = c^2\n$$\n";
+    let ast = parse_math(source);
+
+    let rendered = render(&ast);
+    assert_eq!(source, rendered);
+    assert_eq!(rendered, render(&parse_math(&rendered)));
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let inline = &json["children"][0]["children"][1];
+    assert_eq!("math_inline", inline["type"]);
+    assert_eq!("E = mc^2", inline["value"]);
+
+    let block = &json["children"][1];
+    assert_eq!("math_block", block["type"]);
+    assert_eq!("a^2 + b^2 = c^2", block["value"]);
+}
@@ -0,0 +1,251 @@
+use hypernote_mdx::ast::NodeTag;
+use hypernote_mdx::{parse, render, serialize_tree};
+use serde_json::Value;
+
+fn parsed(source: &str) -> (hypernote_mdx::ast::Ast<'static>, Value) {
+    let ast = parse(source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    (ast, json)
+}
+
+fn children(node: &Value) -> &Vec<Value> {
+    node["children"]
+        .as_array()
+        .expect("expected children array")
+}
+
+fn types(nodes: &[Value]) -> Vec<&str> {
+    nodes.iter().map(|n| n["type"].as_str().unwrap()).collect()
+}
+
+/// Structure of a parse, without the raw source or positions.
+fn structure(source: &str) -> Value {
+    let json: Value = serde_json::from_str(&serialize_tree(&parse(source))).unwrap();
+    json["children"].clone()
+}
+
+fn assert_render_round_trips(source: &str) {
+    let rendered = render(&parse(source));
+    assert_eq!(
+        structure(source),
+        structure(&rendered),
+        "structure changed after render:\n{rendered}"
+    );
+    assert_eq!(rendered, render(&parse(&rendered)), "render is not stable");
+}
+
+#[test]
+fn nested_checklist_becomes_sub_list() {
+    let source = "- [ ] ship release\n  - [x] tag build\n  - [ ] publish notes\n- [x] announce\n";
+    let (_, json) = parsed(source);
+
+    let root = children(&json);
+    assert_eq!(vec!["list_unordered"], types(root));
+    let items = children(&root[0]);
+    assert_eq!(2, items.len());
+
+    let first = children(&items[0]);
+    assert_eq!(vec!["paragraph", "list_unordered"], types(first));
+    let sub_items = children(&first[1]);
+    assert_eq!(2, sub_items.len());
+    assert_eq!(true, sub_items[0]["checked"]);
+    assert_eq!("tag build", children(&sub_items[0])[0]["value"]);
+    assert_eq!(false, sub_items[1]["checked"]);
+
+    assert_eq!(true, items[1]["checked"]);
+    assert_eq!("announce", children(&items[1])[0]["value"]);
+}
+
+#[test]
+fn code_block_under_numbered_step_is_dedented() {
+    let source = "1. Install:\n   ```sh\n   npm install\n     --save-dev\n   ```\n2. Run it\n";
+    let (ast, json) = parsed(source);
+
+    let items = children(&children(&json)[0]);
+    assert_eq!(2, items.len());
+    let blocks = children(&items[0]);
+    assert_eq!(vec!["paragraph", "code_block"], types(blocks));
+    assert_eq!("sh", blocks[1]["lang"]);
+    assert_eq!("npm install\n  --save-dev\n", blocks[1]["value"]);
+
+    let code = ast
+        .nodes
+        .iter()
+        .position(|n| n.tag == NodeTag::CodeBlock)
+        .unwrap() as u32;
+    assert_eq!(
+        "npm install\n  --save-dev\n",
+        ast.code_block_info(code).unwrap().code
+    );
+}
+
+#[test]
+fn quotes_and_jsx_nest_under_items() {
+    let source =
+        "- note\n  > first\n  > second\n- card\n  <Card title=\"Hi\">\n    body\n  </Card>\n";
+    let (_, json) = parsed(source);
+
+    let items = children(&children(&json)[0]);
+    let quote_item = children(&items[0]);
+    assert_eq!(vec!["paragraph", "blockquote"], types(quote_item));
+    assert_eq!(1, children(&quote_item[1]).len());
+
+    let jsx_item = children(&items[1]);
+    assert_eq!(vec!["paragraph", "mdx_jsx_element"], types(jsx_item));
+    assert_eq!("Card", jsx_item[1]["name"]);
+}
+
+#[test]
+fn indented_lines_continue_the_item_paragraph() {
+    let source = "- first line\n  second line\nafter\n";
+    let (_, json) = parsed(source);
+
+    let root = children(&json);
+    assert_eq!(vec!["list_unordered", "paragraph"], types(root));
+    let item = &children(&root[0])[0];
+    assert_eq!(vec!["text", "soft_break", "text"], types(children(item)));
+}
+
+#[test]
+fn unindented_item_closes_nested_list() {
+    let source = "1. outer\n   - inner\n2. next\n   1. deep\n      - deeper\n3. last\n";
+    let (_, json) = parsed(source);
+
+    let items = children(&children(&json)[0]);
+    assert_eq!(3, items.len());
+    let second = children(&items[1]);
+    assert_eq!(vec!["paragraph", "list_ordered"], types(second));
+    let deep = &children(&second[1])[0];
+    assert_eq!(vec!["paragraph", "list_unordered"], types(children(deep)));
+}
+
+#[test]
+fn list_can_interrupt_a_paragraph() {
+    let source = "Steps:\n- one\n- two\n";
+    let (_, json) = parsed(source);
+
+    assert_eq!(vec!["paragraph", "list_unordered"], types(children(&json)));
+}
+
+#[test]
+fn ordered_list_interrupts_a_paragraph_only_from_one() {
+    let (_, json) = parsed("para\n2. x\n");
+    let root = children(&json);
+    assert_eq!(vec!["paragraph"], types(root));
+    assert_eq!(
+        vec!["text", "soft_break", "text", "text"],
+        types(children(&root[0]))
+    );
+
+    let (_, json) = parsed("para\n1. x\n");
+    assert_eq!(vec!["paragraph", "list_ordered"], types(children(&json)));
+
+    let (_, json) = parsed("- a\n  2. b\n");
+    let item = &children(&children(&json)[0])[0];
+    assert_eq!(
+        vec!["text", "soft_break", "text", "text"],
+        types(children(item))
+    );
+}
+
+#[test]
+fn blocks_open_on_the_marker_line() {
+    let (ast, json) = parsed("- ```js\n  code\n  ```\n- > quote\n  > more\n- # head\n");
+    let items = children(&children(&json)[0]);
+    assert_eq!(3, items.len());
+    assert_eq!(vec!["code_block"], types(children(&items[0])));
+    assert_eq!("js", children(&items[0])[0]["lang"]);
+    assert_eq!(vec!["blockquote"], types(children(&items[1])));
+    assert_eq!(1, children(&children(&items[1])[0]).len());
+    assert_eq!(vec!["heading"], types(children(&items[2])));
+
+    let code = ast
+        .nodes
+        .iter()
+        .position(|n| n.tag == NodeTag::CodeBlock)
+        .unwrap() as u32;
+    assert_eq!("code\n", ast.code_block_info(code).unwrap().code);
+
+    let (_, json) = parsed("- - a\n  - b\n- c\n");
+    let items = children(&children(&json)[0]);
+    assert_eq!(2, items.len());
+    let sub_items = children(&children(&items[0])[0]);
+    assert_eq!(vec!["list_item", "list_item"], types(sub_items));
+
+    // A task item's text stays inline.
+    let (_, json) = parsed("- [ ] # not a heading\n");
+    let item = &children(&children(&json)[0])[0];
+    assert_eq!(vec!["text"], types(children(item)));
+}
+
+#[test]
+fn indented_code_in_an_item_counts_from_the_content_column() {
+    let (ast, json) = parsed("- a\n\n      code\n        more\n");
+    let item = &children(&children(&json)[0])[0];
+    assert_eq!(vec!["paragraph", "code_block"], types(children(item)));
+
+    let code = ast
+        .nodes
+        .iter()
+        .position(|n| n.tag == NodeTag::CodeBlock)
+        .unwrap() as u32;
+    assert_eq!("code\n  more\n", ast.code_block_info(code).unwrap().code);
+
+    // Short of four columns past the content column it is another paragraph.
+    let (_, json) = parsed("- a\n\n     text\n");
+    let item = &children(&children(&json)[0])[0];
+    assert_eq!(vec!["paragraph", "paragraph"], types(children(item)));
+}
+
+#[test]
+fn nested_blocks_round_trip_through_render() {
+    assert_render_round_trips("- a\n  - b\n    - c\n- d\n");
+    assert_render_round_trips("1. Install\n   ```sh\n   npm i\n\n   npm test\n   ```\n2. Done\n");
+    assert_render_round_trips("- item\n\n  second paragraph\n\n  > quoted\n  >\n  > more\n");
+    assert_render_round_trips("- [x] card\n  <Card>\n    text\n  </Card>\n- after\n");
+    assert_render_round_trips("10. wide marker\n    - nested\n");
+    assert_render_round_trips("- ```js\n  code\n  ```\n- > quote\n- - a\n  - b\n");
+    assert_render_round_trips("- a\n\n      code\n");
+
+    let rendered = render(&parse("1. step\n   - sub\n   ```js\n   x()\n   ```\n"));
+    assert_eq!("1. step\n   - sub\n\n   ```js\n   x()\n   ```\n", rendered);
+}
+
+#[test]
+fn blank_lines_between_items_keep_one_list() {
+    for (source, list_type) in [
+        ("- a\n\n- b\n", "list_unordered"),
+        ("1. a\n\n2. b\n", "list_ordered"),
+    ] {
+        let (ast, json) = parsed(source);
+
+        let root = children(&json);
+        assert_eq!(vec![list_type], types(root), "for {source:?}");
+        let items = children(&root[0]);
+        assert_eq!(vec!["list_item", "list_item"], types(items));
+        assert_eq!("b", children(&items[1])[0]["value"]);
+        assert_eq!(source, render(&ast));
+    }
+
+    let (_, json) = parsed("1. a\n\n2. b\n\n  \n3. c\n\nafter\n");
+    let root = children(&json);
+    assert_eq!(vec!["list_ordered", "paragraph"], types(root));
+    assert_eq!(3, children(&root[0]).len());
+    assert_render_round_trips("- a\n  - b\n\n  - c\n\n- d\n");
+}
+
+#[test]
+fn marker_short_of_the_content_column_is_a_sibling() {
+    let (_, json) = parsed("- a\n - b\n");
+
+    let root = children(&json);
+    assert_eq!(vec!["list_unordered"], types(root));
+    let items = children(&root[0]);
+    assert_eq!(2, items.len());
+    assert_eq!(vec!["text"], types(children(&items[0])));
+
+    let (_, json) = parsed("- a\n  - b\n");
+    let item = &children(&children(&json)[0])[0];
+    assert_eq!(vec!["paragraph", "list_unordered"], types(children(item)));
+}
@@ -0,0 +1,183 @@
+use hypernote_mdx::ast::{Ast, NodeIndex, NodeTag, Span};
+use hypernote_mdx::tree_builder::{SerializeOptions, serialize_tree_with_options};
+use hypernote_mdx::{ParseOptions, StreamingParser, parse, parse_with_options};
+use serde_json::Value;
+
+mod common;
+use common::nodes_by_tag;
+
+fn source_of<'a>(ast: &'a Ast, tag: NodeTag) -> Vec<&'a str> {
+    nodes_by_tag(ast, tag)
+        .into_iter()
+        .map(|idx| ast.node_source(idx))
+        .collect()
+}
+
+/// Every child lies inside its parent, after its previous sibling.
+fn assert_spans_nest(ast: &Ast) {
+    for idx in 0..ast.nodes.len() as NodeIndex {
+        let span = ast.node_span(idx);
+        let mut previous_end = span.start;
+        for &child in ast.children(idx) {
+            let child_span = ast.node_span(child);
+            assert!(
+                previous_end <= child_span.start && child_span.end <= span.end,
+                "{:?} {span:?} does not hold {:?} {child_span:?}",
+                ast.nodes[idx as usize].tag,
+                ast.nodes[child as usize].tag,
+            );
+            previous_end = child_span.end;
+        }
+    }
+}
+
+const MIXED: &str = "# Title **bold**\n\nSetext\n======\n\nSee [docs](https://x.com \"t\") and *more*\nacross `two\nlines`.\n\n> quote\n> more\n\n- a\n- [x] b\n\n  loose\n\n<Card title=\"x\">\n\nBody ~~old~~\n\n</Card>\n\n```rust\nfn main() {}\n```\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+
+#[test]
+fn spans_include_closing_delimiters() {
+    let ast = parse(MIXED);
+
+    assert_eq!(vec!["**bold**"], source_of(&ast, NodeTag::Strong));
+    assert_eq!(vec!["*more*"], source_of(&ast, NodeTag::Emphasis));
+    assert_eq!(vec!["~~old~~"], source_of(&ast, NodeTag::Strikethrough));
+    assert_eq!(vec!["`two\nlines`"], source_of(&ast, NodeTag::CodeInline));
+    assert_eq!(
+        vec!["[docs](https://x.com \"t\")"],
+        source_of(&ast, NodeTag::Link)
+    );
+    assert_eq!(
+        vec!["<Card title=\"x\">\n\nBody ~~old~~\n\n</Card>"],
+        source_of(&ast, NodeTag::MdxJsxElement)
+    );
+    assert_eq!(
+        vec!["```rust\nfn main() {}\n```"],
+        source_of(&ast, NodeTag::CodeBlock)
+    );
+}
+
+#[test]
+fn block_spans_cover_the_whole_construct() {
+    let ast = parse(MIXED);
+
+    assert_eq!(
+        vec!["# Title **bold**", "Setext\n======"],
+        source_of(&ast, NodeTag::Heading)
+    );
+    assert_eq!(
+        vec!["- a", "- [x] b\n\n  loose"],
+        source_of(&ast, NodeTag::ListItem)
+    );
+    assert_eq!(
+        vec!["> quote\n> more"],
+        source_of(&ast, NodeTag::Blockquote)
+    );
+    assert_eq!(
+        vec!["| a | b |\n|---|---|\n| 1 | 2 |"],
+        source_of(&ast, NodeTag::Table)
+    );
+    // Paragraphs in containers start at their text, not at the marker.
+    let paragraphs = source_of(&ast, NodeTag::Paragraph);
+    assert!(paragraphs.contains(&"quote\n> more"));
+    assert!(paragraphs.contains(&"b"));
+
+    let document = *nodes_by_tag(&ast, NodeTag::Document).last().unwrap();
+    assert_eq!(MIXED, ast.node_source(document));
+}
+
+#[test]
+fn node_at_offset_finds_the_deepest_node() {
+    let source = "Intro\n\nSome **bold** and [a link](https://x.com).\n\n<Card>\n\nHi\n\n</Card>\n";
+    let ast = parse(source);
+    let at = |needle: &str, delta: usize| {
+        let offset = (source.find(needle).unwrap() + delta) as u32;
+        ast.node_at_offset(offset)
+            .map(|idx| ast.nodes[idx as usize].tag)
+    };
+
+    assert_eq!(Some(NodeTag::Text), at("bold", 0));
+    assert_eq!(Some(NodeTag::Strong), at("** and", 1));
+    assert_eq!(Some(NodeTag::Link), at("https://x.com", 3));
+    assert_eq!(Some(NodeTag::Link), at(").", 0));
+    assert_eq!(Some(NodeTag::MdxJsxElement), at("</Card>", 2));
+    assert_eq!(Some(NodeTag::Text), at("Hi", 1));
+    assert_eq!(Some(NodeTag::Document), at("\n\nSome", 1));
+    assert_eq!(None, ast.node_at_offset(source.len() as u32 + 1));
+}
+
+#[test]
+fn spans_nest_for_parsed_streamed_and_reparsed_documents() {
+    let ast = parse(MIXED);
+    assert_spans_nest(&ast);
+
+    let mut streaming = StreamingParser::new();
+    for (i, ch) in MIXED.char_indices() {
+        streaming.push(&MIXED[i..i + ch.len_utf8()]);
+        assert_spans_nest(streaming.ast());
+    }
+    let streamed = streaming.finish();
+    assert_eq!(ast.node_spans, streamed.node_spans);
+
+    let start = MIXED.find("docs").unwrap() as u32;
+    let edit = Span {
+        start,
+        end: start + 4,
+    };
+    let reparse = ast.reparse_with_edit(edit, "the **docs**");
+    assert_spans_nest(&reparse.ast);
+    let fresh = parse(&reparse.ast.source);
+    assert_eq!(fresh.node_spans, reparse.ast.node_spans);
+}
+
+#[test]
+fn serialized_positions_use_exact_spans() {
+    let source = "Hello **world**\n";
+    let ast = parse(source);
+    let options = SerializeOptions {
+        include_positions: true,
+    };
+    let tree: Value = serde_json::from_str(&serialize_tree_with_options(&ast, &options)).unwrap();
+
+    let paragraph = &tree["children"][0];
+    assert_eq!(0, paragraph["position"]["start"]);
+    assert_eq!(15, paragraph["position"]["end"]);
+    let strong = &paragraph["children"][1];
+    assert_eq!(6, strong["position"]["start"]);
+    assert_eq!(15, strong["position"]["end"]);
+}
+
+#[test]
+fn spans_hold_a_trailing_whitespace_only_line() {
+    for breaks in [false, true] {
+        let options = ParseOptions {
+            breaks,
+            ..Default::default()
+        };
+        for source in ["a\n ", "para text\n ", "a\nb\n\t", "> a\n "] {
+            let ast = parse_with_options(source, &options);
+            assert_spans_nest(&ast);
+            let last = source.len() as u32 - 1;
+            assert_eq!(
+                Some(NodeTag::Text),
+                ast.node_at_offset(last)
+                    .map(|idx| ast.nodes[idx as usize].tag),
+                "{source:?} with breaks: {breaks}"
+            );
+        }
+    }
+}
+
+#[test]
+fn recovery_spans_end_before_the_line_break() {
+    for (source, skipped) in [
+        ("{x\n\nnext\n", "{x"),
+        ("<Card title=\"x\n\nnext\n", "<Card title=\"x"),
+        ("| a |\n|---|\n| {x |\n", "| {x |"),
+        ("**a\n\nnext\n", "**a"),
+    ] {
+        let ast = parse(source);
+        let recovery = nodes_by_tag(&ast, NodeTag::Recovery)[0];
+        assert_eq!(skipped, ast.node_source(recovery), "{source:?}");
+        assert_eq!(Some(skipped), ast.recovery_source(recovery));
+        assert_spans_nest(&ast);
+    }
+}
@@ -0,0 +1,128 @@
+use hypernote_mdx::ast::{Ast, NodeTag};
+use hypernote_mdx::semantic::{NostrEntityKind, decode_nostr_entity};
+use hypernote_mdx::{ParseOptions, parse, parse_with_options, render, serialize_tree};
+use serde_json::Value;
+
+mod common;
+use common::nodes_by_tag;
+
+const NPUB: &str = "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg";
+const PUBKEY: &str = "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e";
+const NOTE: &str = "note1h865g8j9egu30yequqp3e7ccudq8seeaes7nuw3m82vpwc9226tqtudlvp";
+const EVENT_ID: &str = "b9f5441e45ca39179320e0031cfb18e34078673dcc3d3e3a3b3a981760aa5696";
+const NEVENT: &str = "nevent1qqstna2yrezu5wghjvswqqculvvwxsrcvu7uc0f78gan4xqhvz49d9spz3mhxue69uhhyetvv9ujuerpd46hxtnfdupzqln7n3p2jxl77x06j209lksmwtswhsdycy2pvulz09prfkr2mh6wqvzqqqqqqywka6k9";
+const NADDR: &str = "naddr1qq9ks7tsv4exumm5v5knzqgdwaehxw309ahx7uewd3hkcq3q0elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qxpqqqp65wfkp834";
+
+fn parse_nostr(source: &str) -> Ast<'static> {
+    let options = ParseOptions {
+        nostr: true,
+        ..ParseOptions::default()
+    };
+    parse_with_options(source, &options)
+}
+
+#[test]
+fn nostr_uris_are_off_by_default() {
+    let source = format!("Hi nostr:{NPUB}!\n");
+    let ast = parse(&source);
+
+    assert!(nodes_by_tag(&ast, NodeTag::NostrReference).is_empty());
+    assert_eq!(source, render(&ast));
+}
+
+#[test]
+fn uris_decode_to_hex_and_relay_hints() {
+    let source =
+        format!("By nostr:{NPUB} in nostr:{NEVENT}, see nostr:{NOTE} and nostr:{NADDR}.\n");
+    let ast = parse_nostr(&source);
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let views: Vec<_> = nodes_by_tag(&ast, NodeTag::NostrReference)
+        .into_iter()
+        .map(|idx| ast.nostr_reference_view(idx).unwrap())
+        .collect();
+    assert_eq!(
+        vec![NPUB, NEVENT, NOTE, NADDR],
+        views.iter().map(|v| v.bech32).collect::<Vec<_>>()
+    );
+
+    let npub = &views[0].entity;
+    assert_eq!(NostrEntityKind::Npub, npub.kind);
+    assert_eq!(Some(PUBKEY), npub.pubkey.as_deref());
+
+    let nevent = &views[1].entity;
+    assert_eq!(NostrEntityKind::Nevent, nevent.kind);
+    assert_eq!(Some(EVENT_ID), nevent.event_id.as_deref());
+    assert_eq!(Some(PUBKEY), nevent.pubkey.as_deref());
+    assert_eq!(Some(1), nevent.event_kind);
+    assert_eq!(vec!["wss://relay.damus.io"], nevent.relays);
+
+    assert_eq!(Some(EVENT_ID), views[2].entity.event_id.as_deref());
+
+    let naddr = &views[3].entity;
+    assert_eq!(NostrEntityKind::Naddr, naddr.kind);
+    assert_eq!(Some("hypernote-1"), naddr.identifier.as_deref());
+    assert_eq!(Some(30023), naddr.event_kind);
+    assert_eq!(vec!["wss://nos.lol"], naddr.relays);
+}
+
+#[test]
+fn invalid_entities_stay_text() {
+    let bad_checksum = &NPUB.replace("ptg", "ptq");
+    let cases = [
+        format!("Typo nostr:{bad_checksum} here.\n"),
+        format!("Glued xnostr:{NPUB} here.\n"),
+        "Missing nostr:nevent1qyxhwumn8ghj7mn0wvhxcmmvxev8k0 event id.\n".to_string(),
+        "Secret nostr:nsec1vl029mgpspedva04g90vltkh6fvh240zqtv9k0t9af8935ke9laqsnlfe5 key.\n"
+            .to_string(),
+    ];
+    for source in &cases {
+        let ast = parse_nostr(source);
+        assert!(
+            nodes_by_tag(&ast, NodeTag::NostrReference).is_empty(),
+            "for {source:?}"
+        );
+        assert_eq!(*source, render(&ast));
+    }
+}
+
+#[test]
+fn attribute_values_decode_through_the_semantic_helper() {
+    let source = format!("<Profile pubkey=\"{NPUB}\" id=\"nostr:{NEVENT}\" size={{2}} />\n");
+    let ast = parse_nostr(&source);
+    let element = nodes_by_tag(&ast, NodeTag::MdxJsxSelfClosing)[0];
+    let view = ast.jsx_element_view(element).unwrap();
+
+    let pubkey = view.attrs[0].value.nostr_entity().unwrap();
+    assert_eq!(Some(PUBKEY), pubkey.pubkey.as_deref());
+    let id = view.attrs[1].value.nostr_entity().unwrap();
+    assert_eq!(Some(EVENT_ID), id.event_id.as_deref());
+    assert!(view.attrs[2].value.nostr_entity().is_none());
+
+    assert_eq!(
+        Some(PUBKEY.to_string()),
+        decode_nostr_entity(NPUB).unwrap().pubkey
+    );
+    assert!(decode_nostr_entity(PUBKEY).is_none());
+}
+
+#[test]
+fn references_round_trip_through_render_and_serialize() {
+    let source = format!("Thanks nostr:{NPUB}!\n");
+    let ast = parse_nostr(&source);
+
+    assert_eq!(source, render(&ast));
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        Some(format!("Thanks nostr:{NPUB}!")),
+        ast.plain_text(paragraph)
+    );
+
+    let json: Value = serde_json::from_str(&serialize_tree(&ast)).unwrap();
+    let reference = &json["children"][0]["children"][1];
+    assert_eq!("nostr_reference", reference["type"]);
+    assert_eq!("npub", reference["entity"]);
+    assert_eq!(NPUB, reference["value"]);
+    assert_eq!(PUBKEY, reference["pubkey"]);
+    assert!(reference.get("relays").is_none());
+}
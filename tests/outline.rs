@@ -0,0 +1,80 @@
+use hypernote_mdx::ast::NodeTag;
+use hypernote_mdx::semantic::{OutlineEntry, OutlineOptions};
+use hypernote_mdx::{parse, render_outline};
+
+mod common;
+use common::nodes_by_tag;
+
+fn titles(entries: &[OutlineEntry]) -> Vec<String> {
+    entries.iter().map(|entry| entry.title.clone()).collect()
+}
+
+#[test]
+fn headings_nest_under_shallower_headings() {
+    let ast = parse("# Report\n\n## Summary\n\n### Details\n\n## Results\n\n# Appendix\n");
+    let outline = ast.outline();
+
+    assert_eq!(vec!["Report", "Appendix"], titles(&outline));
+    assert_eq!(vec!["Summary", "Results"], titles(&outline[0].children));
+    assert_eq!(vec!["Details"], titles(&outline[0].children[0].children));
+    assert!(outline[0].children[1].children.is_empty());
+    assert!(outline[1].children.is_empty());
+}
+
+#[test]
+fn entries_carry_title_anchor_node_and_span() {
+    let source = "# Intro\n\n## Setup **now** {#setup}\n";
+    let ast = parse(source);
+    let outline = ast.outline();
+
+    let headings = nodes_by_tag(&ast, NodeTag::Heading);
+    let intro = &outline[0];
+    assert_eq!(
+        (headings[0], 1, "intro"),
+        (intro.node, intro.level, intro.anchor())
+    );
+    assert_eq!(
+        "# Intro",
+        &source[intro.span.start as usize..intro.span.end as usize]
+    );
+
+    let setup = &intro.children[0];
+    assert_eq!(headings[1], setup.node);
+    assert_eq!("Setup now", setup.title);
+    assert_eq!((Some("setup"), "setup"), (setup.id, setup.slug.as_str()));
+    assert_eq!("setup", setup.anchor());
+}
+
+#[test]
+fn skipped_levels_and_leading_subheadings_still_nest() {
+    let ast = parse("### Early\n\n# Top\n\n#### Deep\n\n## Mid\n");
+    let outline = ast.outline();
+
+    assert_eq!(vec!["Early", "Top"], titles(&outline));
+    assert_eq!(vec!["Deep", "Mid"], titles(&outline[1].children));
+}
+
+#[test]
+fn jsx_headings_are_included_unless_excluded() {
+    let source = "# Overview\n\n<Card>\n\n## Inside\n\n</Card>\n\n## Outside\n";
+    let ast = parse(source);
+
+    assert_eq!(
+        vec!["Inside", "Outside"],
+        titles(&ast.outline()[0].children)
+    );
+
+    let options = OutlineOptions { include_jsx: false };
+    let outline = ast.outline_with_options(&options);
+    assert_eq!(vec!["Outside"], titles(&outline[0].children));
+}
+
+#[test]
+fn outline_renders_as_nested_link_list() {
+    let ast = parse("# Guide\n\n## Install {#setup}\n\n### Use `[x]`\n\n## FAQ\n");
+
+    assert_eq!(
+        "- [Guide](#guide)\n  - [Install](#setup)\n    - [Use \\[x\\]](#use-x)\n  - [FAQ](#faq)\n",
+        render_outline(&ast.outline())
+    );
+}
@@ -0,0 +1,131 @@
+use hypernote_mdx::ast::Ast;
+use hypernote_mdx::{
+    ParseOptions, ParserArena, ParserPool, parse_borrowed, parse_with_options, serialize_tree,
+};
+use std::collections::HashMap;
+use std::thread;
+
+mod common;
+use common::allocations_in;
+
+#[global_allocator]
+static GLOBAL: common::CountingAllocator = common::CountingAllocator;
+
+fn arrays(ast: &Ast) -> String {
+    format!(
+        "{}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}",
+        ast.source,
+        ast.token_tags,
+        ast.token_starts,
+        ast.nodes,
+        ast.extra_data,
+        ast.errors,
+        ast.source_edits,
+        ast.custom_emoji
+    )
+}
+
+fn build_messages() -> Vec<String> {
+    (0..200usize)
+        .map(|i| match i % 5 {
+            0 => format!("status update {i}: user-{} finished review\n", i % 19),
+            1 => format!(
+                "# release note {i}\n\n- status: ready\n- docs: [runbook-{i}](https://example.com/{i})\n\n> ship it :tada:\n"
+            ),
+            2 => format!("| item | state |\n| :--- | ---: |\n| alpha-{i} | {} |\n", i % 9),
+            3 => format!(
+                "<Card>\n<Body>batch {i} is ready for `review`</Body>\n<SubmitButton action=\"approve_{i}\" />\n</Card>\n"
+            ),
+            _ => format!("broken <Card title=\"{i}\n\n**unclosed and [ref][missing-{i}]\n"),
+        })
+        .collect()
+}
+
+#[test]
+fn arena_parses_match_fresh_parses() {
+    let options = ParseOptions {
+        normalize_emoji_shortcodes: true,
+        custom_emoji: HashMap::from([(
+            "pika".to_string(),
+            "https://example.com/pika.png".to_string(),
+        )]),
+        ..ParseOptions::default()
+    };
+    let mut messages = build_messages();
+    messages.push("a :pika: and :tada:\n".to_string());
+    let mut arena = ParserArena::with_options(&options);
+
+    // Recycled vectors start out holding the previous document's data.
+    for message in messages.iter().rev() {
+        let ast = arena.parse(message);
+        assert_eq!(arrays(&parse_with_options(message, &options)), arrays(&ast));
+        arena.recycle(ast);
+    }
+}
+
+#[test]
+fn recycled_arena_allocates_far_less_than_fresh_parses() {
+    let messages = build_messages();
+    let mut arena = ParserArena::new();
+    arena.parse_many(&messages, |_| ());
+
+    let fresh = allocations_in(|| {
+        for message in &messages {
+            drop(parse_borrowed(message));
+        }
+    });
+    let reused = allocations_in(|| {
+        arena.parse_many(&messages, |ast| ast.nodes.len());
+    });
+    assert!(reused * 2 < fresh, "fresh {fresh} reused {reused}");
+}
+
+#[test]
+fn parse_many_keeps_message_order() {
+    let messages = build_messages();
+    let expected: Vec<String> = messages
+        .iter()
+        .map(|message| serialize_tree(&parse_borrowed(message)))
+        .collect();
+
+    let mut arena = ParserArena::new();
+    assert_eq!(expected, arena.parse_many(&messages, serialize_tree));
+
+    for workers in [0, 1, 3, 8] {
+        let mut pool = ParserPool::new(workers);
+        assert_eq!(expected, pool.parse_many(&messages, serialize_tree));
+        assert_eq!(
+            expected[..2],
+            pool.parse_many(&messages[..2], serialize_tree)
+        );
+        assert!(
+            pool.parse_many(&[] as &[&str], |ast| ast.nodes.len())
+                .is_empty()
+        );
+    }
+}
+
+#[test]
+fn pool_spreads_only_batches_large_enough_to_pay_for_threads() {
+    // 1 KiB messages; each worker needs 16 KiB of input.
+    let messages: Vec<String> = (0..64)
+        .map(|i| format!("{:<1023}\n", format!("message {i}")))
+        .collect();
+    let caller = thread::current().id();
+    let mut pool = ParserPool::new(4);
+
+    let small = pool.parse_many(&messages[..31], |_| thread::current().id());
+    assert!(small.iter().all(|&id| id == caller));
+
+    let large = pool.parse_many(&messages, |ast| (thread::current().id(), ast.source.len()));
+    let mut threads: Vec<_> = large.iter().map(|&(id, _)| id).collect();
+    assert_eq!(caller, threads[0]);
+    threads.dedup();
+    let parallelism = thread::available_parallelism().map_or(1, |n| n.get());
+    assert_eq!(parallelism.min(4), threads.len());
+    assert!(large.iter().all(|&(_, len)| len == 1024));
+    assert_eq!(
+        pool.parse_many(&messages, serialize_tree),
+        ParserArena::new().parse_many(&messages, serialize_tree)
+    );
+}
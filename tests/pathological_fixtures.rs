@@ -1,3 +1,4 @@
+use hypernote_mdx::ParseOptions;
 use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
@@ -5,11 +6,20 @@ use std::time::Duration;
 /// Parse input on a background thread with a hard timeout.
 /// Panics if parsing takes longer than the deadline.
 fn parse_with_timeout(label: &str, source: &str, timeout: Duration) {
+    parse_with_options_and_timeout(label, source, ParseOptions::default(), timeout);
+}
+
+fn parse_with_options_and_timeout(
+    label: &str,
+    source: &str,
+    options: ParseOptions,
+    timeout: Duration,
+) {
     let src = source.to_string();
     let (tx, rx) = mpsc::channel();
 
     thread::spawn(move || {
-        let ast = hypernote_mdx::parse(&src);
+        let ast = hypernote_mdx::parse_with_options(&src, &options);
         let _ = tx.send(ast);
     });
 
@@ -69,6 +79,66 @@ fn deeply_nested_unclosed_brackets() {
     parse_with_timeout("deeply_nested_unclosed_brackets", &source, TIMEOUT);
 }
 
+#[test]
+fn many_unclosed_brackets() {
+    // Each [ must not scan the rest of the paragraph for its ]
+    let source = "[".repeat(32_000);
+    parse_with_timeout("many_unclosed_brackets", &source, TIMEOUT);
+}
+
+#[test]
+fn many_unclosed_jsx_blocks() {
+    // Each failed block re-tokenizes only until the stream lines up with the old one
+    let source = "<Card>\n\n".repeat(4_000);
+    parse_with_timeout("many_unclosed_jsx_blocks", &source, TIMEOUT);
+}
+
+#[test]
+fn many_unclosed_expression_blocks() {
+    let source = "{x\n\n".repeat(8_000);
+    parse_with_timeout("many_unclosed_expression_blocks", &source, TIMEOUT);
+}
+
+#[test]
+fn many_failing_table_rows() {
+    let source = format!("| a | b |\n|---|---|\n{}", "| {x |\n".repeat(8_000));
+    parse_with_timeout("many_failing_table_rows", &source, TIMEOUT);
+}
+
+#[test]
+fn many_unclosed_chat_delimiters() {
+    // Each opener must not scan the rest of the line for its closer
+    let options = ParseOptions {
+        chat_inlines: true,
+        ..ParseOptions::default()
+    };
+    for unit in ["||a ", "==a ", "++a ", "^a ", "~a "] {
+        let source = unit.repeat(32_000);
+        parse_with_options_and_timeout(unit, &source, options.clone(), TIMEOUT);
+    }
+}
+
+#[test]
+fn many_unclosed_math_delimiters() {
+    let options = ParseOptions {
+        math: true,
+        ..ParseOptions::default()
+    };
+    for unit in ["$a ", "$$a ", "$$\n$\n"] {
+        let source = unit.repeat(32_000);
+        parse_with_options_and_timeout(unit, &source, options.clone(), TIMEOUT);
+    }
+}
+
+#[test]
+fn many_list_markers_on_one_line() {
+    // Markers on one line nest only up to the nesting limit
+    for unit in ["- ", "1. "] {
+        let source = unit.repeat(32_000);
+        parse_with_timeout(unit, &source, TIMEOUT);
+    }
+}
+
 #[test]
 fn many_stray_angle_brackets() {
     let source = "< ".repeat(200);
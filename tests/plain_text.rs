@@ -20,7 +20,7 @@ fn plain_text_parts_preserve_expression_structure() {
 
     assert_eq!(
         vec![
-            PlainTextPart::Text("Value: "),
+            PlainTextPart::Text("Value: ".into()),
             PlainTextPart::Expression {
                 kind: ExpressionKind::Text,
                 source: "state.count",
@@ -88,8 +88,8 @@ fn plain_text_recurse_inside_jsx_children() {
 
     assert_eq!(
         vec![
-            PlainTextPart::Text("bold"),
-            PlainTextPart::Text(" "),
+            PlainTextPart::Text("bold".into()),
+            PlainTextPart::Text(" ".into()),
             PlainTextPart::Expression {
                 kind: ExpressionKind::Text,
                 source: "state.count",
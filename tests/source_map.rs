@@ -0,0 +1,98 @@
+use hypernote_mdx::ast::{Ast, NodeTag, SourcePosition, Span};
+use hypernote_mdx::{ParseOptions, parse, parse_with_options};
+
+mod common;
+use common::nodes_by_tag;
+
+fn parse_normalized(source: &str) -> Ast<'static> {
+    let options = ParseOptions {
+        normalize_emoji_shortcodes: true,
+        ..ParseOptions::default()
+    };
+    parse_with_options(source, &options)
+}
+
+fn original_text(original: &str, span: Span) -> &str {
+    &original[span.start as usize..span.end as usize]
+}
+
+#[test]
+fn unnormalized_sources_map_to_themselves() {
+    let ast = parse("Hi :tada: **there**\n");
+
+    assert!(ast.source_edits.is_empty());
+    let strong = nodes_by_tag(&ast, NodeTag::Strong)[0];
+    assert_eq!(ast.node_span(strong), ast.original_span(strong));
+    assert_eq!(7, ast.original_offset(7));
+}
+
+#[test]
+fn node_spans_map_back_to_the_original_input() {
+    let original = "Ship :rocket: it :tada: **now**\n\n- :+1: done\n";
+    let ast = parse_normalized(original);
+    assert_eq!("Ship 🚀 it 🎉 **now**\n\n- 👍 done\n", ast.source);
+    assert_eq!(3, ast.source_edits.len());
+
+    let strong = nodes_by_tag(&ast, NodeTag::Strong)[0];
+    assert_eq!(
+        original.find("**now").unwrap() as u32,
+        ast.original_span(strong).start
+    );
+    let now = ast.children(strong)[0];
+    assert_eq!("now", original_text(original, ast.original_span(now)));
+    let item_text = *nodes_by_tag(&ast, NodeTag::Text).last().unwrap();
+    assert_eq!(
+        ":+1: done",
+        original_text(original, ast.original_span(item_text)).trim_end()
+    );
+
+    let paragraph = nodes_by_tag(&ast, NodeTag::Paragraph)[0];
+    assert_eq!(
+        "Ship :rocket: it :tada: **now**",
+        original_text(original, ast.original_span(paragraph))
+    );
+}
+
+#[test]
+fn offsets_inside_a_replacement_map_to_its_start() {
+    let original = "a :tada: b\n";
+    let ast = parse_normalized(original);
+    let edit = ast.source_edits[0];
+    assert_eq!(Span { start: 2, end: 8 }, edit.original);
+    assert_eq!(Span { start: 2, end: 6 }, edit.source);
+
+    assert_eq!(2, ast.original_offset(2));
+    assert_eq!(2, ast.original_offset(4));
+    assert_eq!(8, ast.original_offset(6));
+    assert_eq!(9, ast.original_offset(7));
+}
+
+#[test]
+fn error_positions_use_original_columns() {
+    let original = "Fine.\n\n:tada: :rocket: text {oops\n";
+    let ast = parse_normalized(original);
+
+    let error = ast.errors.first().expect("unclosed expression error");
+    let offset = ast.original_offset(error.byte_offset);
+    assert_eq!(original.len() as u32, offset);
+
+    let brace = ast.source.find('{').unwrap() as u32;
+    assert_eq!(
+        SourcePosition {
+            line: 3,
+            column: 10
+        },
+        ast.line_col(brace)
+    );
+    assert_eq!(
+        SourcePosition {
+            line: 3,
+            column: 22
+        },
+        ast.original_line_col(brace)
+    );
+    assert_eq!(
+        original.find('{').unwrap() as u32,
+        ast.original_offset(brace)
+    );
+}
@@ -0,0 +1,190 @@
+use hypernote_mdx::ast::{Ast, NodeIndex, NodeTag};
+use hypernote_mdx::semantic::JsxAttributeValue;
+use hypernote_mdx::{ParseOptions, StreamingParser, parse, parse_with_options, serialize_tree};
+use serde_json::Value;
+
+mod common;
+use common::nodes_by_tag;
+
+fn subtree(ast: &Ast, idx: NodeIndex) -> String {
+    let mut out = format!("{:?}{:?}[", ast.nodes[idx as usize], ast.node_source(idx));
+    for &child in ast.children(idx) {
+        out.push_str(&subtree(ast, child));
+    }
+    out.push(']');
+    out
+}
+
+fn stream_by_char(source: &str, options: &ParseOptions) -> StreamingParser {
+    let mut parser = StreamingParser::with_options(options);
+    for (i, ch) in source.char_indices() {
+        parser.push(&source[i..i + ch.len_utf8()]);
+    }
+    parser
+}
+
+#[test]
+fn finalized_blocks_never_change() {
+    let source = "# Title\n\nFirst **bold** paragraph.\n\n```rust\nfn main() {}\n```\n\n- a\n- b\n\n<Card title=\"x\">\n\nInside\n\n</Card>\n\nLast line\n";
+    let mut parser = StreamingParser::new();
+    let mut seen: Vec<(NodeIndex, String)> = Vec::new();
+
+    for (i, ch) in source.char_indices() {
+        parser.push(&source[i..i + ch.len_utf8()]);
+        let ast = parser.ast();
+        assert!(ast.errors.is_empty(), "errors after {:?}", &source[..=i]);
+        for (k, &block) in parser.finalized_blocks().iter().enumerate() {
+            let snapshot = (block, subtree(ast, block));
+            match seen.get(k) {
+                Some(previous) => assert_eq!(previous, &snapshot),
+                None => seen.push(snapshot),
+            }
+        }
+    }
+
+    // Everything but the last paragraph settles while the stream is open.
+    assert_eq!(5, seen.len());
+    let ast = parser.finish();
+    let document = nodes_by_tag(&ast, NodeTag::Document)[0];
+    assert_eq!(6, ast.children(document).len());
+    for (block, snapshot) in seen {
+        assert_eq!(snapshot, subtree(&ast, block));
+    }
+}
+
+#[test]
+fn unclosed_inlines_are_auto_closed_and_marked() {
+    let mut parser = StreamingParser::new();
+    parser.push("Hello **wor");
+    let ast = parser.ast();
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+
+    let strong = nodes_by_tag(ast, NodeTag::Strong)[0];
+    assert!(ast.is_incomplete(strong));
+    assert_eq!(Some("wor".to_string()), ast.plain_text(strong));
+    let tree: Value = serde_json::from_str(&serialize_tree(ast)).unwrap();
+    let strong_json = &tree["children"][0]["children"][1];
+    assert_eq!("strong", strong_json["type"]);
+    assert_eq!(true, strong_json["incomplete"]);
+
+    parser.push("ld** and `co");
+    let ast = parser.ast();
+    let strong = nodes_by_tag(ast, NodeTag::Strong)[0];
+    assert!(!ast.is_incomplete(strong));
+    let code = nodes_by_tag(ast, NodeTag::CodeInline)[0];
+    assert_eq!(vec![code], ast.incomplete);
+    assert!(ast.errors.is_empty());
+}
+
+#[test]
+fn open_fences_and_jsx_are_auto_closed() {
+    let mut parser = StreamingParser::new();
+    parser.push("```rust\nfn main");
+    let ast = parser.ast();
+    let code = nodes_by_tag(ast, NodeTag::CodeBlock)[0];
+    assert!(ast.is_incomplete(code));
+    assert_eq!("fn main", ast.code_block_info(code).unwrap().code);
+
+    let mut parser = StreamingParser::new();
+    parser.push("<Card title=\"x\"");
+    let ast = parser.ast();
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    let card = nodes_by_tag(ast, NodeTag::MdxJsxElement)[0];
+    assert!(ast.is_incomplete(card));
+
+    parser.push(">\n\nBody *text*\n\n</Ca");
+    let ast = parser.ast();
+    assert!(ast.errors.is_empty(), "unexpected errors: {:?}", ast.errors);
+    let card = nodes_by_tag(ast, NodeTag::MdxJsxElement)[0];
+    assert_eq!(vec![card], ast.incomplete);
+    assert_eq!(Some("Body text".to_string()), ast.plain_text(card));
+
+    parser.push("rd>\n");
+    assert!(parser.ast().incomplete.is_empty());
+}
+
+#[test]
+fn attribute_values_cut_off_mid_chunk_are_auto_closed() {
+    let cases = [
+        (
+            "**bold** and <Card title=\"Pay",
+            JsxAttributeValue::String("Pay".into()),
+        ),
+        (
+            "**bold** and <Card title={a",
+            JsxAttributeValue::Expression("a"),
+        ),
+        (
+            "**bold** and <Card title=",
+            JsxAttributeValue::String(String::new()),
+        ),
+        (
+            "**bold** and <Card title={",
+            JsxAttributeValue::Expression(""),
+        ),
+        (
+            "**bold** and <Card title='",
+            JsxAttributeValue::String(String::new()),
+        ),
+    ];
+    for (source, expected) in cases {
+        let mut parser = StreamingParser::new();
+        parser.push(source);
+        let ast = parser.ast();
+        assert!(ast.errors.is_empty(), "{source:?}: {:?}", ast.errors);
+        assert!(
+            nodes_by_tag(ast, NodeTag::Recovery).is_empty(),
+            "{source:?}"
+        );
+        assert_eq!(1, nodes_by_tag(ast, NodeTag::Strong).len(), "{source:?}");
+
+        let card = nodes_by_tag(ast, NodeTag::MdxJsxElement)[0];
+        assert!(ast.is_incomplete(card), "{source:?}");
+        let attrs = ast.jsx_attribute_views(card).unwrap();
+        assert_eq!("title", attrs[0].name);
+        assert_eq!(expected, attrs[0].value, "{source:?}");
+    }
+
+    let mut parser = StreamingParser::new();
+    parser.push("<Card title=\"Pay");
+    parser.push("ment\">\n\nDone\n\n</Card>\n");
+    let ast = parser.ast();
+    assert!(ast.errors.is_empty() && ast.incomplete.is_empty());
+    let card = nodes_by_tag(ast, NodeTag::MdxJsxElement)[0];
+    let attrs = ast.jsx_attribute_views(card).unwrap();
+    assert_eq!(JsxAttributeValue::String("Payment".into()), attrs[0].value);
+}
+
+#[test]
+fn finish_reports_what_is_still_open() {
+    let source = "Intro\n\nHello **wor";
+    let mut parser = StreamingParser::new();
+    parser.push(source);
+    assert!(parser.ast().errors.is_empty());
+
+    let ast = parser.finish();
+    let expected = parse(source);
+    assert!(ast.incomplete.is_empty());
+    assert!(!ast.errors.is_empty());
+    assert_eq!(serialize_tree(&expected), serialize_tree(&ast));
+}
+
+#[test]
+fn streamed_documents_match_a_full_parse() {
+    let options = ParseOptions {
+        normalize_emoji_shortcodes: true,
+        ..ParseOptions::default()
+    };
+    let samples = [
+        "---\ntitle: Demo\n---\n\n# Hello :wave:\n\nSome *text* with a [link](https://example.com).\n\n> quote\n> more\n\n1. one\n2. two\n",
+        "See the note[^1] and [docs][ref].\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n[^1]: A footnote.\n\n[ref]: https://example.com/docs\n",
+        "<Note>\n- a :tada:\n- b\n</Note>\n\n{props.value}\n\n<!--\n\ncomment\n\n-->\n\nDone.",
+    ];
+
+    for source in samples {
+        let streamed = stream_by_char(source, &options).finish();
+        let full = parse_with_options(source, &options);
+        assert_eq!(serialize_tree(&full), serialize_tree(&streamed), "{source}");
+        assert_eq!(full.source_edits, streamed.source_edits);
+    }
+}
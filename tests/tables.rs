@@ -312,3 +312,16 @@ fn three_column_table() {
     assert_eq!(info.num_columns, 3);
     assert_eq!(info.num_rows, 3); // header + 2 body
 }
+
+#[test]
+fn failed_body_row_leaves_no_dangling_rows() {
+    let source = "| a | b |\n|---|\n| {x |\n\nok\n";
+    let ast = parse(source);
+
+    let table_idx = find_node(&ast, NodeTag::Table).expect("should have a Table node");
+    for &row in ast.children(table_idx) {
+        assert_eq!(NodeTag::TableRow, ast.nodes[row as usize].tag);
+    }
+    serialize_tree(&ast);
+    render(&ast);
+}
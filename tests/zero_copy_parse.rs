@@ -0,0 +1,94 @@
+use hypernote_mdx::ast::Ast;
+use hypernote_mdx::{
+    ParseOptions, parse, parse_borrowed, parse_borrowed_with_options, parse_owned,
+    parse_with_options, serialize_tree,
+};
+use std::borrow::Cow;
+use std::hint::black_box;
+
+mod common;
+use common::{allocated_by, build_chat_corpus};
+
+#[global_allocator]
+static GLOBAL: common::CountingAllocator = common::CountingAllocator;
+
+fn is_borrowed_from(ast: &Ast, source: &str) -> bool {
+    matches!(ast.source, Cow::Borrowed(borrowed) if borrowed.as_ptr() == source.as_ptr())
+}
+
+#[test]
+fn borrowed_parse_keeps_pointing_at_the_input() {
+    let source = String::from("# Title\n\nSome **bold** text and a [link](https://x.com).\n");
+    let ast = parse_borrowed(&source);
+
+    assert!(is_borrowed_from(&ast, &source));
+    assert_eq!(serialize_tree(&parse(&source)), serialize_tree(&ast));
+    let heading = ast.heading_view(0).unwrap();
+    assert_eq!("title", heading.slug);
+
+    let owned = ast.into_owned();
+    drop(source);
+    assert!(matches!(owned.source, Cow::Owned(_)));
+    assert_eq!(1, owned.outline().len());
+}
+
+#[test]
+fn owned_parse_takes_the_string_without_copying() {
+    let source = String::from("- a\n- b\n\n<Card title=\"x\" />\n");
+    let buffer = source.as_ptr();
+    let expected = serialize_tree(&parse(&source));
+
+    let ast = parse_owned(source);
+    assert!(matches!(&ast.source, Cow::Owned(owned) if owned.as_ptr() == buffer));
+    assert_eq!(expected, serialize_tree(&ast));
+}
+
+#[test]
+fn shortcode_normalization_copies_only_when_it_rewrites() {
+    let options = ParseOptions {
+        normalize_emoji_shortcodes: true,
+        ..ParseOptions::default()
+    };
+
+    let plain = "No shortcodes at 10:30 here.\n";
+    let ast = parse_borrowed_with_options(plain, &options);
+    assert!(is_borrowed_from(&ast, plain));
+    assert!(ast.source_edits.is_empty());
+
+    let rewritten = "Ship it :tada:\n";
+    let ast = parse_borrowed_with_options(rewritten, &options);
+    assert_eq!("Ship it 🎉\n", ast.source);
+    assert_eq!(1, ast.source_edits.len());
+    assert_eq!(
+        serialize_tree(&parse_with_options(rewritten, &options)),
+        serialize_tree(&ast)
+    );
+}
+
+#[test]
+fn chat_history_replay_allocates_no_source_copies() {
+    let history = build_chat_corpus();
+    let source_bytes: usize = history.iter().map(String::len).sum();
+
+    let rounds = 8usize;
+    let mut copied_bytes = 0usize;
+    let mut borrowed_bytes = 0usize;
+    let mut total_nodes = 0usize;
+
+    for _ in 0..rounds {
+        for message in &history {
+            let (copied, allocated) = allocated_by(|| parse(message));
+            copied_bytes += allocated;
+            let (borrowed, allocated) = allocated_by(|| parse_borrowed(message));
+            borrowed_bytes += allocated;
+
+            assert_eq!(copied.nodes.len(), borrowed.nodes.len());
+            total_nodes += black_box(borrowed).nodes.len();
+        }
+    }
+
+    black_box(total_nodes);
+    // `parse` differs from `parse_borrowed` by exactly one copy of each message.
+    assert_eq!(rounds * source_bytes, copied_bytes - borrowed_bytes);
+    assert!(total_nodes > rounds * history.len() * 4);
+}